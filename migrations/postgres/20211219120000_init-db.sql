@@ -0,0 +1,111 @@
+CREATE TABLE projects (
+  id                UUID PRIMARY KEY NOT NULL,
+  version           INTEGER DEFAULT 0 NOT NULL,
+  name              TEXT NOT NULL,
+  body              TEXT NOT NULL,
+
+  owner_id          UUID NOT NULL,
+
+  created_at        TIMESTAMPTZ NOT NULL
+                        DEFAULT current_timestamp,
+  updated_at        TIMESTAMPTZ NOT NULL
+                        DEFAULT current_timestamp
+);
+
+CREATE TABLE documents (
+  id                UUID PRIMARY KEY NOT NULL,
+  doctype           TEXT NOT NULL,
+  version           INTEGER DEFAULT 0 NOT NULL,
+  name              TEXT NOT NULL,
+  body              JSONB NOT NULL,
+
+  created_at        TIMESTAMPTZ NOT NULL
+                        DEFAULT current_timestamp,
+  updated_at        TIMESTAMPTZ NOT NULL
+                        DEFAULT current_timestamp,
+
+  owner_id          UUID NOT NULL,
+  project_id        UUID NOT NULL,
+
+  FOREIGN KEY (project_id)
+  REFERENCES projects (id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
+
+CREATE INDEX documents_project_id ON documents (project_id);
+
+CREATE TABLE translations (
+  id                UUID PRIMARY KEY NOT NULL,
+  version           INTEGER DEFAULT 0 NOT NULL,
+  name              TEXT NOT NULL,
+  body              JSONB NOT NULL,
+
+  created_at        TIMESTAMPTZ NOT NULL
+                        DEFAULT current_timestamp,
+  updated_at        TIMESTAMPTZ NOT NULL
+                        DEFAULT current_timestamp,
+
+  owner_id          UUID NOT NULL,
+  project_id        UUID NOT NULL,
+  document_id       UUID NOT NULL,
+
+  FOREIGN KEY (document_id)
+  REFERENCES documents (id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION,
+
+  FOREIGN KEY (project_id)
+  REFERENCES projects (id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
+
+CREATE TABLE changes (
+  id                SERIAL PRIMARY KEY,
+  version           INTEGER NOT NULL,
+  kind              TEXT NOT NULL DEFAULT 'edit',
+  forward           JSONB NOT NULL,
+  reverse           JSONB NOT NULL,
+
+  created_at        TIMESTAMPTZ NOT NULL
+                        DEFAULT current_timestamp,
+  updated_at        TIMESTAMPTZ NOT NULL
+                        DEFAULT current_timestamp,
+
+  document_id       UUID NOT NULL,
+
+  FOREIGN KEY (document_id)
+  REFERENCES documents (id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
+
+CREATE INDEX changes_document_id ON changes (document_id);
+
+CREATE FUNCTION set_updated_at() RETURNS TRIGGER AS $$
+  BEGIN
+    NEW.updated_at = current_timestamp;
+    RETURN NEW;
+  END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER project_updated_at
+  BEFORE UPDATE
+  ON projects FOR EACH ROW
+  EXECUTE FUNCTION set_updated_at();
+
+CREATE TRIGGER document_updated_at
+  BEFORE UPDATE
+  ON documents FOR EACH ROW
+  EXECUTE FUNCTION set_updated_at();
+
+CREATE TRIGGER translation_updated_at
+  BEFORE UPDATE
+  ON translations FOR EACH ROW
+  EXECUTE FUNCTION set_updated_at();
+
+CREATE TRIGGER change_updated_at
+  BEFORE UPDATE
+  ON changes FOR EACH ROW
+  EXECUTE FUNCTION set_updated_at();
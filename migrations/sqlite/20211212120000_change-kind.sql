@@ -0,0 +1 @@
+ALTER TABLE changes ADD COLUMN kind TEXT NOT NULL DEFAULT 'edit';
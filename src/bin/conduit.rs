@@ -1,32 +1,126 @@
-use clap::{crate_authors, crate_version, load_yaml, App};
+use clap::{crate_authors, crate_version, load_yaml, App, ArgMatches};
+use std::error::Error;
+use std::io::Read;
+use std::path::Path;
+use uuid::Uuid;
 
 extern crate conduit;
+use conduit::config::{parse, Config, ConfigError, Overrides};
+use conduit::doc::document::{DigraphDocument, DocType};
+use conduit::model::digraph::{DiagramDirection, DiagramFormat, Digraph, DigraphFormat};
+use conduit::storage::engine::{EngineContainer, EngineVariant};
+
+/// Settings given as flags, which override the file and the environment
+fn flags(matches: &ArgMatches) -> Result<Overrides, ConfigError> {
+    let log_level = match matches.occurrences_of("verbose") {
+        0 => matches.value_of("log-level").map(String::from),
+        1 => Some("debug".into()),
+        _ => Some("trace".into()),
+    };
+    Ok(Overrides {
+        listen_addr: matches.value_of("listen").map(String::from),
+        port: matches
+            .value_of("port")
+            .map(|port| parse("port", port))
+            .transpose()?,
+        database_url: matches.value_of("database-url").map(String::from),
+        log_level,
+        ..Overrides::default()
+    })
+}
+
+fn config(matches: &ArgMatches) -> Result<Config, ConfigError> {
+    Config::load(matches.value_of("config").map(Path::new), flags(matches)?)
+}
+
+/// The digraph document `id` in the configured storage
+async fn digraph(config: &Config, id: &str) -> Result<DigraphDocument, Box<dyn Error>> {
+    let id = Uuid::parse_str(id)?;
+    let doc = EngineVariant::open(&config.database_url, false)
+        .await?
+        .boxed()
+        .get_document(&id)
+        .await?;
+    if doc.doctype != DocType::Digraph.to_string() {
+        return Err(format!("Document {} is not a digraph", id).into());
+    }
+    Ok(doc.into())
+}
+
+/// Read `path`, or stdin for `-`, and parse it as a digraph in `format`.
+/// Parse errors are reported with the file name.
+fn read_digraph(format: DigraphFormat, path: &str) -> Result<Digraph, Box<dyn Error>> {
+    let (name, source) = if path == "-" {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source)?;
+        ("<stdin>", source)
+    } else {
+        let source = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        (path, source)
+    };
+    Ok(Digraph::import(format, &source).map_err(|err| format!("{}:{}", name, err))?)
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<(), Box<dyn Error>> {
     let yaml = load_yaml!("cli.yaml");
     let matches = App::from(yaml)
+        // The yaml `multiple` would also let -v take values
+        .mut_arg("verbose", |arg| arg.multiple_occurrences(true))
         .version(crate_version!())
         .author(crate_authors!())
         .get_matches();
 
-    if let Some(matches) = matches.subcommand_matches("db") {
+    if let Some(matches) = matches.subcommand_matches("config") {
+        if let Some(matches) = matches.subcommand_matches("show") {
+            print!("{}", config(matches)?.show());
+        }
+    } else if let Some(matches) = matches.subcommand_matches("db") {
         println!("Running command 'db'");
-        let url = "test.db".to_string();
-        if let Some(_matches) = matches.subcommand_matches("migrate") {
-            match conduit::storage::sqlite::Sqlite::setup(url).await {
-                Ok(db) => {
-                    println!("db: running migrations");
-                    db.migrate().await?
-                }
-                Err(err) => {
-                    println!("Error {}", err);
-                }
+        if let Some(matches) = matches.subcommand_matches("migrate") {
+            let config = config(matches)?;
+            let storage = EngineVariant::connect(&config.database_url).await?;
+            if storage.has_migrations() {
+                println!("db: running migrations");
+                storage.migrate().await?;
+            } else {
+                println!("db: only sql storage has migrations");
             }
         }
-    } else if let Some(_matches) = matches.subcommand_matches("serve") {
+    } else if let Some(matches) = matches.subcommand_matches("diagram") {
+        let config = config(matches)?;
+        let format: DiagramFormat = matches.value_of("format").unwrap_or_default().parse()?;
+        let direction: DiagramDirection = match matches.value_of("direction") {
+            Some(direction) => direction.parse()?,
+            None => DiagramDirection::default(),
+        };
+        let doc = digraph(&config, matches.value_of("id").unwrap_or_default()).await?;
+        print!("{}", doc.body.diagram(format, direction));
+    } else if let Some(matches) = matches.subcommand_matches("export") {
+        let config = config(matches)?;
+        let format: DigraphFormat = matches.value_of("format").unwrap_or_default().parse()?;
+        let doc = digraph(&config, matches.value_of("id").unwrap_or_default()).await?;
+        print!("{}", doc.body.export(format));
+    } else if let Some(matches) = matches.subcommand_matches("import") {
+        let config = config(matches)?;
+        let format = matches.value_of("format").unwrap_or_default().parse()?;
+        let project_id = Uuid::parse_str(matches.value_of("project-id").unwrap_or_default())?;
+        let body = read_digraph(format, matches.value_of("file").unwrap_or("-"))?;
+
+        let storage = EngineVariant::open(&config.database_url, true)
+            .await?
+            .boxed();
+        let project = storage.get_project(&project_id).await?;
+        let doc = DigraphDocument::with_body(&project, body);
+        storage.store_document(doc.clone().into()).await?;
+        println!("{}", doc.id);
+    } else if let Some(matches) = matches.subcommand_matches("serve") {
         println!("Running command 'serve'");
-        let _ = conduit::http::server::serve().await;
+        let config = config(matches)?;
+        let storage = EngineVariant::connect(&config.database_url).await?;
+        storage.migrate().await?;
+        let engine = EngineContainer::boxed(storage.boxed());
+        let _ = conduit::http::server::serve(engine, config).await;
     } else {
         println!("none: No matching command found");
     }
@@ -0,0 +1,265 @@
+//! Settings of the conduit binary. They are layered: built-in defaults,
+//! then a TOML file, then environment variables, then command line flags,
+//! each overriding the ones before.
+//!
+//! ```toml
+//! listen_addr = "localhost:8000"
+//! database_url = "sqlite://test.db"
+//! log_level = "info"
+//!
+//! [cors]
+//! allowed_origins = ["http://localhost:3000"]
+//!
+//! [auth]
+//! bearer_token = "secret"
+//!
+//! [limits]
+//! page_size = 100
+//! max_page_size = 1000
+//! max_query_depth = 32
+//! ```
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The file read when no other file is named and it exists
+pub const DEFAULT_CONFIG_FILE: &str = "conduit.toml";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Address and port the HTTP server binds to
+    pub listen_addr: String,
+    /// Storage URL, see `EngineVariant::connect`
+    pub database_url: String,
+    /// An `env_logger` filter such as `info` or `conduit=debug`
+    pub log_level: String,
+    pub cors: Cors,
+    pub auth: Auth,
+    pub limits: Limits,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen_addr: "localhost:8000".into(),
+            database_url: "sqlite://test.db".into(),
+            log_level: "info".into(),
+            cors: Cors::default(),
+            auth: Auth::default(),
+            limits: Limits::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Cors {
+    /// Origins allowed to make cross-origin requests, `*` allows any.
+    /// No cross-origin requests are allowed when empty.
+    pub allowed_origins: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Auth {
+    /// When set, GraphQL requests have to carry `Authorization: Bearer <token>`
+    /// and subscriptions the same `Authorization` in their `connection_init`
+    /// payload. The playground is served without it.
+    pub bearer_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    /// Number of records in a page when neither `first` nor `last` is given
+    pub page_size: usize,
+    /// Upper bound for `first` and `last`
+    pub max_page_size: usize,
+    /// Deepest nesting of fields a GraphQL query may have
+    pub max_query_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            page_size: 100,
+            max_page_size: 1000,
+            max_query_depth: 32,
+        }
+    }
+}
+
+/// Settings given by environment variables or command line flags. Only
+/// the ones that are set replace what is configured so far.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Overrides {
+    pub listen_addr: Option<String>,
+    /// Replaces the port of the listen address
+    pub port: Option<u16>,
+    pub database_url: Option<String>,
+    pub log_level: Option<String>,
+    pub allowed_origins: Option<Vec<String>>,
+    pub bearer_token: Option<String>,
+    pub page_size: Option<usize>,
+    pub max_page_size: Option<usize>,
+    pub max_query_depth: Option<usize>,
+}
+
+impl Overrides {
+    /// Read overrides from the environment through `var`:
+    ///
+    /// - `LISTEN_ADDR`
+    /// - `DATABASE_URL`
+    /// - `CONDUIT_LOG_LEVEL`
+    /// - `CONDUIT_CORS_ORIGINS`, a comma separated list
+    /// - `CONDUIT_AUTH_TOKEN`
+    /// - `CONDUIT_PAGE_SIZE`, `CONDUIT_MAX_PAGE_SIZE`, `CONDUIT_MAX_QUERY_DEPTH`
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let number = |name: &'static str| -> Result<Option<usize>, ConfigError> {
+            var(name).map(|value| parse(name, &value)).transpose()
+        };
+        Ok(Self {
+            listen_addr: var("LISTEN_ADDR"),
+            port: None,
+            database_url: var("DATABASE_URL"),
+            log_level: var("CONDUIT_LOG_LEVEL"),
+            allowed_origins: var("CONDUIT_CORS_ORIGINS").map(|origins| split_list(&origins)),
+            bearer_token: var("CONDUIT_AUTH_TOKEN"),
+            page_size: number("CONDUIT_PAGE_SIZE")?,
+            max_page_size: number("CONDUIT_MAX_PAGE_SIZE")?,
+            max_query_depth: number("CONDUIT_MAX_QUERY_DEPTH")?,
+        })
+    }
+}
+
+/// Split a comma separated list, dropping empty entries
+pub fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Parse `value`, naming the setting it is for when it is invalid
+pub fn parse<T: std::str::FromStr>(name: &'static str, value: &str) -> Result<T, ConfigError> {
+    value
+        .parse()
+        .map_err(|_| ConfigError::InvalidValue(name, value.to_string()))
+}
+
+impl Config {
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| ConfigError::Io(path.to_path_buf(), err.to_string()))?;
+        Self::from_toml(&text).map_err(|err| match err {
+            ConfigError::Parse(_, message) => ConfigError::Parse(Some(path.to_path_buf()), message),
+            err => err,
+        })
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        toml::from_str(text).map_err(|err| ConfigError::Parse(None, err.to_string()))
+    }
+
+    /// Load the effective configuration. The file is `file` when given,
+    /// otherwise `CONDUIT_CONFIG` or, if it exists, `conduit.toml`. The
+    /// process environment and then `flags` are applied on top of it.
+    pub fn load(file: Option<&Path>, flags: Overrides) -> Result<Self, ConfigError> {
+        let file = file
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::var_os("CONDUIT_CONFIG").map(PathBuf::from))
+            .or_else(|| {
+                let default = PathBuf::from(DEFAULT_CONFIG_FILE);
+                Some(default).filter(|path| path.exists())
+            });
+
+        let mut config = match file {
+            Some(path) => Self::from_file(&path)?,
+            None => Self::default(),
+        };
+        config.apply(Overrides::from_env(|name| std::env::var(name).ok())?)?;
+        config.apply(flags)?;
+        Ok(config)
+    }
+
+    pub fn apply(&mut self, overrides: Overrides) -> Result<(), ConfigError> {
+        if let Some(listen_addr) = overrides.listen_addr {
+            self.listen_addr = listen_addr;
+        }
+        if let Some(port) = overrides.port {
+            let host = match self.listen_addr.rsplit_once(':') {
+                Some((host, _)) => host,
+                None => self.listen_addr.as_str(),
+            };
+            self.listen_addr = format!("{}:{}", host, port);
+        }
+        if let Some(database_url) = overrides.database_url {
+            self.database_url = database_url;
+        }
+        if let Some(log_level) = overrides.log_level {
+            self.log_level = log_level;
+        }
+        if let Some(allowed_origins) = overrides.allowed_origins {
+            self.cors.allowed_origins = allowed_origins;
+        }
+        if let Some(bearer_token) = overrides.bearer_token {
+            self.auth.bearer_token = Some(bearer_token).filter(|token| !token.is_empty());
+        }
+        if let Some(page_size) = overrides.page_size {
+            self.limits.page_size = page_size;
+        }
+        if let Some(max_page_size) = overrides.max_page_size {
+            self.limits.max_page_size = max_page_size;
+        }
+        if let Some(max_query_depth) = overrides.max_query_depth {
+            self.limits.max_query_depth = max_query_depth;
+        }
+        self.validate()
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.limits.page_size == 0 {
+            return Err(ConfigError::InvalidValue("page_size", "0".into()));
+        }
+        if self.limits.max_page_size < self.limits.page_size {
+            return Err(ConfigError::InvalidValue(
+                "max_page_size",
+                self.limits.max_page_size.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The configuration as TOML, with secrets masked
+    pub fn show(&self) -> String {
+        let mut shown = self.clone();
+        if shown.auth.bearer_token.is_some() {
+            shown.auth.bearer_token = Some("********".into());
+        }
+        toml::to_string_pretty(&shown).expect("Config to be serializable")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    Io(PathBuf, String),
+    Parse(Option<PathBuf>, String),
+    InvalidValue(&'static str, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, s) => write!(f, "Cannot read {} : {}", path.display(), s),
+            ConfigError::Parse(Some(path), s) => write!(f, "Invalid {} : {}", path.display(), s),
+            ConfigError::Parse(None, s) => write!(f, "Invalid config : {}", s),
+            ConfigError::InvalidValue(name, value) => {
+                write!(f, "Invalid value for {} : {}", name, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -1,12 +1,157 @@
+use std::fmt;
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
+use super::common::DateTime;
+
+#[derive(async_graphql::Enum, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Edit,
+    Undo,
+    Redo,
+}
+
+impl Default for ChangeKind {
+    fn default() -> Self {
+        Self::Edit
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Edit => write!(f, "edit"),
+            Self::Undo => write!(f, "undo"),
+            Self::Redo => write!(f, "redo"),
+        }
+    }
+}
+
+impl FromStr for ChangeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "edit" => Ok(Self::Edit),
+            "undo" => Ok(Self::Undo),
+            "redo" => Ok(Self::Redo),
+            _ => Err(format!("Unknown change kind : {}", s)),
+        }
+    }
+}
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Change {
     pub id: i32,
     pub document_id: Uuid,
     pub version: i32,
+    pub kind: ChangeKind,
     pub forward: Value,
     pub reverse: Value,
+    pub created_at: DateTime,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeFields {
+    Id,
+    Version,
+    CreatedAt,
+}
+
+impl Default for ChangeFields {
+    fn default() -> Self {
+        Self::Id
+    }
+}
+
+impl Change {
+    /// The change an undo should revert: the most recent edit or redo that
+    /// has not been cancelled out by a later undo.
+    pub fn undo_target(changes: &[Change]) -> Option<&Change> {
+        let mut pending_undos = 0;
+        for change in changes.iter().rev() {
+            match change.kind {
+                ChangeKind::Undo => pending_undos += 1,
+                ChangeKind::Edit | ChangeKind::Redo if pending_undos > 0 => pending_undos -= 1,
+                ChangeKind::Edit | ChangeKind::Redo => return Some(change),
+            }
+        }
+        None
+    }
+
+    /// The undo a redo should revert. Redo is only possible while no edit
+    /// has been made since the last undo.
+    pub fn redo_target(changes: &[Change]) -> Option<&Change> {
+        let mut pending_redos = 0;
+        for change in changes.iter().rev() {
+            match change.kind {
+                ChangeKind::Redo => pending_redos += 1,
+                ChangeKind::Undo if pending_redos > 0 => pending_redos -= 1,
+                ChangeKind::Undo => return Some(change),
+                ChangeKind::Edit => return None,
+            }
+        }
+        None
+    }
+}
+
+/// Published by `EngineContainer` after a document update has been stored.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct DocumentChanged {
+    pub document_id: Uuid,
+    pub project_id: Uuid,
+    pub version: i32,
+    pub forward: Value,
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn changes(kinds: &[ChangeKind]) -> Vec<Change> {
+        kinds
+            .iter()
+            .enumerate()
+            .map(|(idx, kind)| Change {
+                id: idx as i32 + 1,
+                document_id: crate::util::naming::empty_uuid(),
+                version: idx as i32 + 1,
+                kind: *kind,
+                forward: Value::Null,
+                reverse: Value::Null,
+                created_at: chrono::Utc::now(),
+            })
+            .collect()
+    }
+
+    fn undo(kinds: &[ChangeKind]) -> Option<i32> {
+        Change::undo_target(&changes(kinds)).map(|change| change.id)
+    }
+
+    fn redo(kinds: &[ChangeKind]) -> Option<i32> {
+        Change::redo_target(&changes(kinds)).map(|change| change.id)
+    }
+
+    #[test]
+    fn test_undo_target() {
+        use ChangeKind::*;
+        assert_eq!(undo(&[]), None);
+        assert_eq!(undo(&[Edit, Edit]), Some(2));
+        assert_eq!(undo(&[Edit, Edit, Undo]), Some(1));
+        assert_eq!(undo(&[Edit, Edit, Undo, Undo]), None);
+        assert_eq!(undo(&[Edit, Edit, Undo, Redo]), Some(4));
+    }
+
+    #[test]
+    fn test_redo_target() {
+        use ChangeKind::*;
+        assert_eq!(redo(&[Edit]), None);
+        assert_eq!(redo(&[Edit, Edit, Undo]), Some(3));
+        assert_eq!(redo(&[Edit, Edit, Undo, Undo]), Some(4));
+        assert_eq!(redo(&[Edit, Edit, Undo, Undo, Redo]), Some(3));
+        assert_eq!(redo(&[Edit, Edit, Undo, Edit]), None);
+    }
 }
@@ -28,6 +28,24 @@ where
         self.version += 1;
         self.version
     }
+
+    /// A deep copy of this document under a new id, at version 0, owned by `project`
+    pub fn duplicate(&self, project: &Project) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            id: Uuid::new_v4(),
+            project_id: project.id,
+            owner_id: project.owner_id,
+            name: self.name.clone(),
+            doctype: self.doctype.clone(),
+            version: 0,
+            body: self.body.clone(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
 }
 
 pub type RawDocument = Document<serde_json::Value>;
@@ -114,10 +132,27 @@ macro_rules! register_doc {
     };
 }
 
+#[derive(async_graphql::Enum, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum DocType {
     Digraph,
 }
 
+#[derive(async_graphql::Enum, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum DocumentFields {
+    Id,
+    Name,
+    Doctype,
+    CreatedAt,
+    UpdatedAt,
+    Version,
+}
+
+impl Default for DocumentFields {
+    fn default() -> Self {
+        Self::Id
+    }
+}
+
 impl fmt::Display for DocType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -128,6 +163,19 @@ impl fmt::Display for DocType {
 
 register_doc!(Digraph, DigraphDocument, digraph);
 
+impl DigraphDocument {
+    /// A new document in `project` holding `body`, named after the digraph
+    /// when it has a name
+    pub fn with_body(project: &Project, body: Digraph) -> Self {
+        let mut doc = Self::create(project);
+        if !body.name.is_empty() {
+            doc.name = body.name.clone();
+        }
+        doc.body = body;
+        doc
+    }
+}
+
 /*
 pub fn raw_doc_to_typed(doc: &RawDocument) -> Result<Doc, String> {
     match doc.doctype.as_ref() {
@@ -146,4 +194,18 @@ mod test {
         let _ = dg.body.add_node(None);
         assert_eq!(dg.body.nodes.len(), 1);
     }
+
+    #[test]
+    fn test_duplicate_digraph_document() {
+        let project = super::Project::new(crate::util::naming::empty_uuid());
+        let mut dg = super::DigraphDocument::default();
+        let _ = dg.body.add_node(None);
+        dg.change();
+
+        let copy = dg.duplicate(&project);
+        assert_ne!(copy.id, dg.id);
+        assert_eq!(copy.project_id, project.id);
+        assert_eq!(copy.version, 0);
+        assert_eq!(copy.body, dg.body);
+    }
 }
@@ -13,7 +13,7 @@ pub struct Project {
     pub updated_at: DateTime,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(async_graphql::Enum, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum ProjectFields {
     Id,
     Name,
@@ -40,4 +40,9 @@ impl Project {
             updated_at: chrono::Utc::now(),
         }
     }
+
+    pub fn change(&mut self) -> i32 {
+        self.version += 1;
+        self.version
+    }
 }
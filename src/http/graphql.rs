@@ -1,12 +1,223 @@
-use crate::doc::document::{DocType, RawDocument};
+use crate::config::Limits;
+use crate::doc::change::{Change, ChangeKind, DocumentChanged};
+use crate::doc::common::DateTime;
+use crate::doc::document::{DocType, DocumentFields, RawDocument};
 use crate::doc::project::{Project, ProjectFields};
-use crate::storage::engine::{EngineContainer, EngineError, QueryRequest};
-use async_graphql::{Context, FieldResult, Object};
+use crate::storage::engine::{
+    check_version, Cursor, Direction, DocumentFilter, EngineContainer, EngineError, Keyed,
+    Pagination, ProjectFilter, QueryRequest, QueryResponse, SortField,
+};
+use async_graphql::connection::{query, Connection, CursorType, Edge};
+use async_graphql::{
+    Context, ErrorExtensions, FieldResult, InputObject, Json, Object, Schema, SimpleObject,
+    Subscription,
+};
+use futures::{future, Future, Stream, StreamExt};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
 use uuid::Uuid;
 use std::default::Default;
 
+pub type ConduitSchema = Schema<Query, MutationRoot, SubscriptionRoot>;
+
+impl ErrorExtensions for EngineError {
+    fn extend(&self) -> async_graphql::Error {
+        async_graphql::Error::new(format!("{}", self)).extend_with(|_, e| match self {
+            EngineError::NotFound => e.set("code", "NOT_FOUND"),
+            EngineError::Storage(_) => e.set("code", "STORAGE"),
+            EngineError::VersionMismatch(expected, current) => {
+                e.set("code", "VERSION_MISMATCH");
+                e.set("expectedVersion", *expected);
+                e.set("currentVersion", *current);
+            }
+            EngineError::NothingToUndo => e.set("code", "NOTHING_TO_UNDO"),
+            EngineError::NothingToRedo => e.set("code", "NOTHING_TO_REDO"),
+            EngineError::InvalidCursor => e.set("code", "INVALID_CURSOR"),
+        })
+    }
+}
+
+impl ErrorExtensions for ParseError {
+    fn extend(&self) -> async_graphql::Error {
+        async_graphql::Error::new(self.message.clone()).extend_with(|_, e| {
+            e.set("code", "PARSE_ERROR");
+            e.set("line", self.line as i32);
+            e.set("column", self.column as i32);
+        })
+    }
+}
+
+impl ErrorExtensions for DigraphError {
+    fn extend(&self) -> async_graphql::Error {
+        async_graphql::Error::new(format!("{}", self)).extend_with(|_, e| match self {
+            DigraphError::IdDoesNotExist(id) => {
+                e.set("code", "ID_DOES_NOT_EXIST");
+                e.set("id", *id);
+            }
+            DigraphError::Cycle(nodes) => {
+                e.set("code", "CYCLE");
+                e.set("nodes", nodes.clone());
+            }
+            DigraphError::InvalidWeight(id, _) => {
+                e.set("code", "INVALID_WEIGHT");
+                e.set("id", *id);
+            }
+            DigraphError::ConstraintViolation(violation) => {
+                e.set("code", "CONSTRAINT_VIOLATION");
+                match violation {
+                    Violation::Cycle(nodes) => {
+                        e.set("constraint", "ACYCLIC");
+                        e.set("nodes", nodes.clone());
+                    }
+                    Violation::SelfLoop(id) => {
+                        e.set("constraint", "NO_SELF_LOOPS");
+                        e.set("id", *id);
+                    }
+                    Violation::ParallelLink(id) => {
+                        e.set("constraint", "NO_PARALLEL_LINKS");
+                        e.set("id", *id);
+                    }
+                    Violation::InDegree(id, _) => {
+                        e.set("constraint", "MAX_IN_DEGREE");
+                        e.set("id", *id);
+                    }
+                    Violation::OutDegree(id, _) => {
+                        e.set("constraint", "MAX_OUT_DEGREE");
+                        e.set("id", *id);
+                    }
+                }
+            }
+            DigraphError::TempIdDoesNotExist(temp) => {
+                e.set("code", "TEMP_ID_DOES_NOT_EXIST");
+                e.set("temp", temp.as_str());
+            }
+            DigraphError::TempIdAlreadyUsed(temp) => {
+                e.set("code", "TEMP_ID_ALREADY_USED");
+                e.set("temp", temp.as_str());
+            }
+            DigraphError::NegativeDegreeLimit(degree, max) => {
+                e.set("code", "NEGATIVE_DEGREE_LIMIT");
+                e.set(
+                    "constraint",
+                    match degree {
+                        Degree::In => "MAX_IN_DEGREE",
+                        Degree::Out => "MAX_OUT_DEGREE",
+                    },
+                );
+                e.set("max", *max);
+            }
+        })
+    }
+}
+
+/// The extensions of the failed message, along with its `index`
+impl ErrorExtensions for BatchError {
+    fn extend(&self) -> async_graphql::Error {
+        let mut err = self.error.extend();
+        err.message = format!("{}", self);
+        err.extend_with(|_, e| e.set("index", self.index as i32))
+    }
+}
+
+/// Cursors are handed out as url-safe base64 of their JSON form, clients
+/// should treat them as opaque.
+impl CursorType for Cursor {
+    type Error = String;
+
+    fn decode_cursor(s: &str) -> Result<Self, Self::Error> {
+        let json = base64::decode_config(s, base64::URL_SAFE_NO_PAD)
+            .map_err(|_| "Invalid cursor".to_string())?;
+        serde_json::from_slice(&json).map_err(|_| "Invalid cursor".to_string())
+    }
+
+    fn encode_cursor(&self) -> String {
+        let json = serde_json::to_vec(self).expect("Cursor to be serializable");
+        base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+    }
+}
+
+/// The configured limits, or the defaults for schemas built without them
+fn limits(ctx: &Context<'_>) -> Limits {
+    ctx.data_opt::<Limits>().copied().unwrap_or_default()
+}
+
+#[derive(SimpleObject)]
+pub struct TotalCount {
+    /// Number of records in the listing, regardless of the page
+    total_count: i32,
+}
+
+pub type ListConnection<T> = Connection<Cursor, T, TotalCount>;
+
+/// Resolve a Relay connection over an engine listing. One record more than
+/// requested is fetched to tell whether another page follows.
+async fn connection<T, R, F, Fut>(
+    limits: Limits,
+    params: QueryRequest<T>,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+    fetch: F,
+) -> FieldResult<ListConnection<R>>
+where
+    T: SortField + Default,
+    R: Keyed<T>,
+    F: FnOnce(QueryRequest<T>) -> Fut,
+    Fut: Future<Output = Result<QueryResponse<R>, EngineError>>,
+{
+    query(after, before, first, last, |after, before, first, last| async move {
+        let from_end = first.is_none() && last.is_some();
+        let limit = first.or(last).unwrap_or(limits.page_size);
+        let limit = limit.min(limits.max_page_size);
+        let (has_after, has_before) = (after.is_some(), before.is_some());
+        let params = QueryRequest {
+            page: Some(Pagination {
+                limit: Some(limit as i32 + 1),
+                offset: Some(0),
+                after,
+                before,
+                from_end,
+            }),
+            ..params
+        };
+
+        let response = fetch(params.clone()).await.map_err(|err| err.extend())?;
+        let mut records = response.data;
+        let has_more = records.len() > limit;
+        if has_more && from_end {
+            records.remove(0);
+        } else {
+            records.truncate(limit);
+        }
+
+        let (has_previous_page, has_next_page) = if from_end {
+            (has_more, has_before)
+        } else {
+            (has_after, has_more)
+        };
+        let mut connection = Connection::with_additional_fields(
+            has_previous_page,
+            has_next_page,
+            TotalCount {
+                total_count: response.meta.total.unwrap_or(0),
+            },
+        );
+        connection.append(
+            records
+                .into_iter()
+                .map(|record| Edge::new(params.cursor(&record), record)),
+        );
+        Ok::<_, async_graphql::Error>(connection)
+    })
+    .await
+}
+
+/// Expose a typed document, with `$fields` resolved next to the common ones
 macro_rules! register_graphql_doc {
     ($doc:ty, $body:ty) => {
+        register_graphql_doc!($doc, $body, {});
+    };
+    ($doc:ty, $body:ty, { $($fields:tt)* }) => {
         #[async_graphql::Object]
         impl $doc {
             async fn id(&self) -> &Uuid {
@@ -24,14 +235,121 @@ macro_rules! register_graphql_doc {
             async fn body(&self) -> &$body {
                 &self.body
             }
+
+            async fn changes(
+                &self,
+                ctx: &Context<'_>,
+                after: Option<String>,
+                before: Option<String>,
+                first: Option<i32>,
+                last: Option<i32>,
+            ) -> FieldResult<ListConnection<Change>> {
+                changes_connection(ctx, self.id, after, before, first, last).await
+            }
+
+            $($fields)*
         }
     };
 }
 
+async fn changes_connection(
+    ctx: &Context<'_>,
+    document_id: Uuid,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+) -> FieldResult<ListConnection<Change>> {
+    let storage = ctx.data::<EngineContainer>().expect("To get a container");
+    connection(
+        limits(ctx),
+        QueryRequest::default(),
+        after,
+        before,
+        first,
+        last,
+        |params| storage.get_document_changes(&document_id, params),
+    )
+    .await
+}
+
 use crate::doc::document::DigraphDocument;
-use crate::model::digraph::{Digraph, DigraphMessage, LinkSettings, NodeSettings};
+use crate::model::digraph::algo::Path;
+use crate::model::digraph::batch::{BatchError, BatchMessage, TempId};
+use crate::model::digraph::constraints::{Constraints, Degree, Violation};
+use crate::model::digraph::{
+    DiagramDirection, DiagramFormat, Digraph, DigraphError, DigraphFormat, LabelSettings, Link,
+    LinkSettings, Node, NodeSettings, ParseError,
+};
+
+register_graphql_doc!(DigraphDocument, Digraph, {
+    /// The body in the Graphviz DOT language
+    async fn dot(&self) -> String {
+        self.body.to_dot()
+    }
+
+    /// The body in `format`
+    async fn export(&self, format: DigraphFormat) -> String {
+        self.body.export(format)
+    }
+
+    /// The body as a diagram in `format`, top down unless another
+    /// `direction` is given
+    async fn diagram(&self, format: DiagramFormat, direction: Option<DiagramDirection>) -> String {
+        self.body.diagram(format, direction.unwrap_or_default())
+    }
+});
+
+/// Analysis of the digraph, see `model::digraph::algo`
+#[async_graphql::ComplexObject]
+impl Digraph {
+    /// The node ids ordered so every link points forward, a `CYCLE` error
+    /// when there is no such order
+    async fn topological_order(&self) -> FieldResult<Vec<i32>> {
+        self.topological_sort().map_err(|err| err.extend())
+    }
+
+    /// The node ids of a cycle in the order its links run, if any
+    async fn cycle(&self) -> Option<Vec<i32>> {
+        self.find_cycle()
+    }
+
+    /// Sets of node ids that can all reach each other
+    async fn components(&self) -> Vec<Vec<i32>> {
+        self.strongly_connected_components()
+    }
 
-register_graphql_doc!(DigraphDocument, Digraph);
+    /// The ids of the nodes that can reach node `id`
+    #[graphql(name = "ancestors")]
+    async fn node_ancestors(&self, id: i32) -> FieldResult<Vec<i32>> {
+        self.ancestors(id).map_err(|err| err.extend())
+    }
+
+    /// The ids of the nodes node `id` can reach
+    #[graphql(name = "descendants")]
+    async fn node_descendants(&self, id: i32) -> FieldResult<Vec<i32>> {
+        self.descendants(id).map_err(|err| err.extend())
+    }
+
+    /// Whether links lead from node `source` to node `target`
+    async fn reachable(&self, source: i32, target: i32) -> FieldResult<bool> {
+        self.is_reachable(source, target)
+            .map_err(|err| err.extend())
+    }
+
+    /// The cheapest path from node `source` to node `target`, if any. Links
+    /// cost the number in their `weight` label when given, and 1 otherwise.
+    #[graphql(name = "shortestPath")]
+    async fn cheapest_path(
+        &self,
+        source: i32,
+        target: i32,
+        weight: Option<String>,
+    ) -> FieldResult<Option<Path>> {
+        self.shortest_path(source, target, weight.as_deref())
+            .map_err(|err| err.extend())
+    }
+}
 
 #[async_graphql::Object]
 impl Project {
@@ -54,12 +372,28 @@ impl Project {
     async fn digraphs(
         &self,
         ctx: &Context<'_>,
-    ) -> FieldResult<Vec<crate::doc::document::DigraphDocument>> {
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> FieldResult<ListConnection<DigraphDocument>> {
         let storage = ctx.data::<EngineContainer>().expect("To get a container");
-        let docs = storage
-            .get_project_documents(&self.id, DocType::Digraph)
-            .await?;
-        Ok(docs.iter().map(|doc| doc.into()).collect())
+        let filter = DocumentFilter {
+            project_id: Some(self.id),
+            doctype: Some(DocType::Digraph),
+            ..Default::default()
+        };
+        let docs = connection(
+            limits(ctx),
+            QueryRequest::default(),
+            after,
+            before,
+            first,
+            last,
+            |params| storage.get_documents(params, filter),
+        )
+        .await?;
+        Ok(docs.map_node(|doc| (&doc).into()))
     }
 }
 
@@ -76,6 +410,108 @@ impl RawDocument {
     async fn name(&self) -> &str {
         &self.name
     }
+
+    async fn doctype(&self) -> &str {
+        &self.doctype
+    }
+
+    async fn body(&self) -> Json<&serde_json::Value> {
+        Json(&self.body)
+    }
+
+    async fn changes(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> FieldResult<ListConnection<Change>> {
+        changes_connection(ctx, self.id, after, before, first, last).await
+    }
+}
+
+#[async_graphql::Object]
+impl Change {
+    async fn id(&self) -> &i32 {
+        &self.id
+    }
+
+    async fn version(&self) -> &i32 {
+        &self.version
+    }
+
+    async fn kind(&self) -> &ChangeKind {
+        &self.kind
+    }
+
+    async fn created_at(&self) -> &DateTime {
+        &self.created_at
+    }
+
+    async fn forward(&self) -> Json<&serde_json::Value> {
+        Json(&self.forward)
+    }
+
+    async fn reverse(&self) -> Json<&serde_json::Value> {
+        Json(&self.reverse)
+    }
+}
+
+#[async_graphql::Object]
+impl DocumentChanged {
+    async fn document_id(&self) -> &Uuid {
+        &self.document_id
+    }
+
+    async fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    async fn version(&self) -> &i32 {
+        &self.version
+    }
+
+    async fn forward(&self) -> Json<&serde_json::Value> {
+        Json(&self.forward)
+    }
+}
+
+#[derive(InputObject)]
+pub struct ProjectOrderBy {
+    field: ProjectFields,
+    #[graphql(default_with = "Direction::Asc")]
+    direction: Direction,
+}
+
+impl From<ProjectOrderBy> for (ProjectFields, Direction) {
+    fn from(order: ProjectOrderBy) -> Self {
+        (order.field, order.direction)
+    }
+}
+
+#[derive(InputObject)]
+pub struct DocumentOrderBy {
+    field: DocumentFields,
+    #[graphql(default_with = "Direction::Asc")]
+    direction: Direction,
+}
+
+impl From<DocumentOrderBy> for (DocumentFields, Direction) {
+    fn from(order: DocumentOrderBy) -> Self {
+        (order.field, order.direction)
+    }
+}
+
+fn sorted<T, O>(order_by: Option<Vec<O>>) -> QueryRequest<T>
+where
+    T: Default,
+    O: Into<(T, Direction)>,
+{
+    QueryRequest {
+        sort: order_by.map(|order_by| order_by.into_iter().map(|order| order.into()).collect()),
+        ..Default::default()
+    }
 }
 
 pub struct Query;
@@ -93,33 +529,172 @@ impl Query {
         Ok(project)
     }
 
-    async fn projects(&self, ctx: &Context<'_>) -> FieldResult<Vec<Project>> {
+    /// A document at its current version, or as it was at `version`
+    async fn document(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        version: Option<i32>,
+    ) -> FieldResult<RawDocument> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+        let doc = match version {
+            Some(version) => storage.get_document_at_version(&id, version).await?,
+            None => storage.get_document(&id).await?,
+        };
+        Ok(doc)
+    }
+
+    /// A digraph at its current version, or as it was at `version`
+    async fn digraph(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        version: Option<i32>,
+    ) -> FieldResult<DigraphDocument> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+        let doc = match version {
+            Some(version) => storage.get_document_at_version(&id, version).await,
+            None => storage.get_document(&id).await,
+        }
+        .map_err(|err| err.extend())?;
+        if doc.doctype != DocType::Digraph.to_string() {
+            return Err(EngineError::NotFound.extend());
+        }
+        Ok(doc.into())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn projects(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<ProjectFilter>,
+        order_by: Option<Vec<ProjectOrderBy>>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> FieldResult<ListConnection<Project>> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+        let filter = filter.unwrap_or_default();
+        connection(
+            limits(ctx),
+            sorted(order_by),
+            after,
+            before,
+            first,
+            last,
+            |params| storage.get_projects(params, filter),
+        )
+        .await
+    }
+
+    async fn documents(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<DocumentFilter>,
+        order_by: Option<Vec<DocumentOrderBy>>,
+    ) -> FieldResult<Vec<RawDocument>> {
         let storage = ctx.data::<EngineContainer>().expect("To get a container");
-        let response = storage.get_projects(QueryRequest::<ProjectFields>::default()).await?;
+        let response = storage
+            .get_documents(sorted(order_by), filter.unwrap_or_default())
+            .await?;
         Ok(response.data)
     }
 }
 
 pub struct MutationRoot;
 
-async fn digraph_change(
+/// Run `update` on the body of a digraph and store the result as one
+/// change, returning the stored document along with what `update` returned
+async fn digraph_update<T, E: ErrorExtensions>(
     ctx: &Context<'_>,
     project_id: Uuid,
     doc_id: Uuid,
-    msg: DigraphMessage,
-) -> Result<DigraphDocument, EngineError> {
+    expected_version: Option<i32>,
+    update: impl FnOnce(&mut Digraph) -> Result<T, E>,
+) -> FieldResult<(DigraphDocument, T)> {
     let storage = ctx.data::<EngineContainer>().expect("To get a container");
-    let _project = storage.get_project(&project_id).await?;
-    let mut doc: DigraphDocument = storage.get_document(&doc_id).await?.into();
+    let mut doc: DigraphDocument = storage
+        .get_document(&doc_id)
+        .await
+        .map_err(|err| err.extend())?
+        .into();
+    // A document is only found through the project it is in
+    if doc.project_id != project_id {
+        return Err(EngineError::NotFound.extend());
+    }
 
-    if let Err(err) = doc.body.message(msg) {
-        Err(EngineError::Storage(format!("{}", err)))
-    } else {
-        let _ = storage.update_document(&mut doc.clone().into()).await?;
-        Ok(doc)
+    check_version(expected_version, doc.version).map_err(|err| err.extend())?;
+    let result = update(&mut doc.body).map_err(|err| err.extend())?;
+
+    let mut doc: RawDocument = doc.into();
+    storage
+        .update_document(&mut doc)
+        .await
+        .map_err(|err| err.extend())?;
+    Ok((doc.into(), result))
+}
+
+/// A digraph after one of its nodes was added or updated
+#[derive(SimpleObject)]
+pub struct DigraphNodeChanged {
+    pub node: Node,
+    pub document: DigraphDocument,
+}
+
+impl DigraphNodeChanged {
+    fn new((document, id): (DigraphDocument, i32)) -> Self {
+        Self {
+            node: document
+                .body
+                .nodes
+                .iter()
+                .find(|e| e.id == id)
+                .cloned()
+                .expect("The node to be in the digraph"),
+            document,
+        }
+    }
+}
+
+/// A digraph after one of its links was added or updated
+#[derive(SimpleObject)]
+pub struct DigraphLinkChanged {
+    pub link: Link,
+    pub document: DigraphDocument,
+}
+
+impl DigraphLinkChanged {
+    fn new((document, id): (DigraphDocument, i32)) -> Self {
+        Self {
+            link: document
+                .body
+                .links
+                .iter()
+                .find(|e| e.id == id)
+                .cloned()
+                .expect("The link to be in the digraph"),
+            document,
+        }
     }
 }
 
+/// A digraph after nodes or links were removed, with their ids. Removing a
+/// node removes its links as well.
+#[derive(SimpleObject)]
+pub struct DigraphRemoved {
+    pub nodes: Vec<i32>,
+    pub links: Vec<i32>,
+    pub document: DigraphDocument,
+}
+
+/// A digraph after a batch of messages, with the ids its temp ids were given
+#[derive(SimpleObject)]
+pub struct DigraphApplied {
+    pub document: DigraphDocument,
+    pub temp_ids: Vec<TempId>,
+}
+
 #[Object]
 impl MutationRoot {
     async fn digraph_create(
@@ -131,21 +706,173 @@ impl MutationRoot {
 
         let project = storage.get_project(&project_id).await?;
         let doc = DigraphDocument::create(&project);
-        let _ = storage.store_document(doc.clone().into()).await?;
+        storage.store_document(doc.clone().into()).await?;
+        Ok(doc)
+    }
+
+    /// Create a digraph in `project_id` from `source` in `format`
+    async fn digraph_import(
+        &self,
+        ctx: &Context<'_>,
+        project_id: Uuid,
+        format: DigraphFormat,
+        source: String,
+    ) -> FieldResult<DigraphDocument> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+
+        let body = Digraph::import(format, &source).map_err(|err| err.extend())?;
+        let project = storage
+            .get_project(&project_id)
+            .await
+            .map_err(|err| err.extend())?;
+        let doc = DigraphDocument::with_body(&project, body);
+        storage
+            .store_document(doc.clone().into())
+            .await
+            .map_err(|err| err.extend())?;
+        Ok(doc)
+    }
+
+    async fn project_create(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        body: Option<String>,
+    ) -> FieldResult<Project> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+
+        let mut project = Project::new(crate::util::naming::empty_uuid());
+        project.name = name;
+        project.body = body.unwrap_or_default();
+        storage
+            .store_project(project.clone())
+            .await
+            .map_err(|err| err.extend())?;
+        Ok(project)
+    }
+
+    async fn project_update(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        name: Option<String>,
+        body: Option<String>,
+        expected_version: Option<i32>,
+    ) -> FieldResult<Project> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+
+        let mut project = storage.get_project(&id).await.map_err(|err| err.extend())?;
+        check_version(expected_version, project.version).map_err(|err| err.extend())?;
+        if let Some(name) = name {
+            project.name = name;
+        }
+        if let Some(body) = body {
+            project.body = body;
+        }
+        storage
+            .update_project(&mut project)
+            .await
+            .map_err(|err| err.extend())?;
+        Ok(project)
+    }
+
+    async fn project_delete(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        expected_version: Option<i32>,
+    ) -> FieldResult<Uuid> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+
+        storage
+            .delete_project(&id, expected_version)
+            .await
+            .map_err(|err| err.extend())?;
+        Ok(id)
+    }
+
+    async fn document_delete(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        expected_version: Option<i32>,
+    ) -> FieldResult<Uuid> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+
+        storage
+            .delete_document(&id, expected_version)
+            .await
+            .map_err(|err| err.extend())?;
+        Ok(id)
+    }
+
+    async fn document_rename(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        name: String,
+        expected_version: Option<i32>,
+    ) -> FieldResult<RawDocument> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+
+        let mut doc = storage.get_document(&id).await.map_err(|err| err.extend())?;
+        check_version(expected_version, doc.version).map_err(|err| err.extend())?;
+        storage
+            .rename_document(&mut doc, name)
+            .await
+            .map_err(|err| err.extend())?;
         Ok(doc)
     }
 
+    /// Copy a document into `projectId`, or into its own project
+    async fn document_duplicate(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        project_id: Option<Uuid>,
+        name: Option<String>,
+    ) -> FieldResult<RawDocument> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+
+        let doc = storage.get_document(&id).await.map_err(|err| err.extend())?;
+        let project = storage
+            .get_project(&project_id.unwrap_or(doc.project_id))
+            .await
+            .map_err(|err| err.extend())?;
+        let mut copy = doc.duplicate(&project);
+        if let Some(name) = name {
+            copy.name = name;
+        }
+        storage
+            .store_document(copy.clone())
+            .await
+            .map_err(|err| err.extend())?;
+        Ok(copy)
+    }
+
+    async fn document_undo(&self, ctx: &Context<'_>, id: Uuid) -> FieldResult<RawDocument> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+        storage.undo_document(&id).await.map_err(|err| err.extend())
+    }
+
+    async fn document_redo(&self, ctx: &Context<'_>, id: Uuid) -> FieldResult<RawDocument> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+        storage.redo_document(&id).await.map_err(|err| err.extend())
+    }
+
     async fn digraph_add_node(
         &self,
         ctx: &Context<'_>,
         project_id: Uuid,
         doc_id: Uuid,
         attrs: Option<NodeSettings>,
-    ) -> FieldResult<DigraphDocument> {
-        use crate::model::digraph::DigraphMessage;
-        let msg = DigraphMessage::AddNode(attrs.unwrap_or_default());
-
-        Ok(digraph_change(ctx, project_id, doc_id, msg).await?)
+        expected_version: Option<i32>,
+    ) -> FieldResult<DigraphNodeChanged> {
+        digraph_update(ctx, project_id, doc_id, expected_version, |body| {
+            body.add_node(attrs)
+        })
+        .await
+        .map(DigraphNodeChanged::new)
     }
 
     async fn digraph_update_node(
@@ -155,11 +882,14 @@ impl MutationRoot {
         doc_id: Uuid,
         node_id: i32,
         attrs: Option<NodeSettings>,
-    ) -> FieldResult<DigraphDocument> {
-        use crate::model::digraph::DigraphMessage;
-        let msg = DigraphMessage::UpdateNode(node_id, attrs.unwrap_or_default());
-
-        Ok(digraph_change(ctx, project_id, doc_id, msg).await?)
+        expected_version: Option<i32>,
+    ) -> FieldResult<DigraphNodeChanged> {
+        digraph_update(ctx, project_id, doc_id, expected_version, |body| {
+            body.update_node(node_id, attrs)
+                .map(|()| node_id)
+        })
+        .await
+        .map(DigraphNodeChanged::new)
     }
 
     async fn digraph_remove_node(
@@ -168,13 +898,20 @@ impl MutationRoot {
         project_id: Uuid,
         doc_id: Uuid,
         node_id: i32,
-    ) -> FieldResult<DigraphDocument> {
-        use crate::model::digraph::DigraphMessage;
-        let msg = DigraphMessage::RemoveNode(node_id);
-
-        Ok(digraph_change(ctx, project_id, doc_id, msg).await?)
+        expected_version: Option<i32>,
+    ) -> FieldResult<DigraphRemoved> {
+        let (document, links) = digraph_update(ctx, project_id, doc_id, expected_version, |body| {
+            body.remove_node(node_id)
+        })
+        .await?;
+        Ok(DigraphRemoved {
+            nodes: vec![node_id],
+            links,
+            document,
+        })
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn digraph_add_link(
         &self,
         ctx: &Context<'_>,
@@ -183,11 +920,13 @@ impl MutationRoot {
         source_id: i32,
         target_id: i32,
         attrs: Option<LinkSettings>,
-    ) -> FieldResult<DigraphDocument> {
-        use crate::model::digraph::DigraphMessage;
-        let msg = DigraphMessage::AddLink(source_id, target_id, attrs.unwrap_or_default());
-
-        Ok(digraph_change(ctx, project_id, doc_id, msg).await?)
+        expected_version: Option<i32>,
+    ) -> FieldResult<DigraphLinkChanged> {
+        digraph_update(ctx, project_id, doc_id, expected_version, |body| {
+            body.add_link(source_id, target_id, attrs)
+        })
+        .await
+        .map(DigraphLinkChanged::new)
     }
 
     async fn digraph_update_link(
@@ -197,11 +936,14 @@ impl MutationRoot {
         doc_id: Uuid,
         link_id: i32,
         attrs: Option<LinkSettings>,
-    ) -> FieldResult<DigraphDocument> {
-        use crate::model::digraph::DigraphMessage;
-        let msg = DigraphMessage::UpdateLink(link_id, attrs.unwrap_or_default());
-
-        Ok(digraph_change(ctx, project_id, doc_id, msg).await?)
+        expected_version: Option<i32>,
+    ) -> FieldResult<DigraphLinkChanged> {
+        digraph_update(ctx, project_id, doc_id, expected_version, |body| {
+            body.update_link(link_id, attrs)
+                .map(|()| link_id)
+        })
+        .await
+        .map(DigraphLinkChanged::new)
     }
 
     async fn digraph_remove_link(
@@ -210,11 +952,124 @@ impl MutationRoot {
         project_id: Uuid,
         doc_id: Uuid,
         link_id: i32,
+        expected_version: Option<i32>,
+    ) -> FieldResult<DigraphRemoved> {
+        let (document, ()) = digraph_update(ctx, project_id, doc_id, expected_version, |body| {
+            body.remove_link(link_id)
+        })
+        .await?;
+        Ok(DigraphRemoved {
+            nodes: vec![],
+            links: vec![link_id],
+            document,
+        })
+    }
+
+    /// Hold the digraph to `constraints`, replacing the ones it has. Fails
+    /// with a `CONSTRAINT_VIOLATION` when the digraph breaks them already.
+    async fn digraph_set_constraints(
+        &self,
+        ctx: &Context<'_>,
+        project_id: Uuid,
+        doc_id: Uuid,
+        constraints: Constraints,
+        expected_version: Option<i32>,
+    ) -> FieldResult<DigraphDocument> {
+        digraph_update(ctx, project_id, doc_id, expected_version, |body| {
+            body.set_constraints(constraints)
+        })
+        .await
+        .map(|(document, ())| document)
+    }
+
+    /// Rename the digraph itself, which is kept apart from the name of its
+    /// document
+    async fn digraph_set_name(
+        &self,
+        ctx: &Context<'_>,
+        project_id: Uuid,
+        doc_id: Uuid,
+        name: String,
+        expected_version: Option<i32>,
+    ) -> FieldResult<DigraphDocument> {
+        digraph_update(ctx, project_id, doc_id, expected_version, |body| {
+            body.set_name(name)
+        })
+        .await
+        .map(|(document, ())| document)
+    }
+
+    /// Merge, replace or remove the labels of the digraph itself
+    async fn digraph_update_labels(
+        &self,
+        ctx: &Context<'_>,
+        project_id: Uuid,
+        doc_id: Uuid,
+        attrs: LabelSettings,
+        expected_version: Option<i32>,
     ) -> FieldResult<DigraphDocument> {
-        use crate::model::digraph::DigraphMessage;
-        let msg = DigraphMessage::RemoveLink(link_id);
+        digraph_update(ctx, project_id, doc_id, expected_version, |body| {
+            body.update_labels(attrs)
+        })
+        .await
+        .map(|(document, ())| document)
+    }
+
+    /// Apply `messages` in order as a single change, or none of them when
+    /// one fails. Nodes and links added with a `tempId` can be referred to
+    /// by later messages with `{ temp: ... }`.
+    async fn digraph_apply(
+        &self,
+        ctx: &Context<'_>,
+        project_id: Uuid,
+        doc_id: Uuid,
+        messages: Vec<BatchMessage>,
+        expected_version: Option<i32>,
+    ) -> FieldResult<DigraphApplied> {
+        let (document, temp_ids) =
+            digraph_update(ctx, project_id, doc_id, expected_version, |body| {
+                body.apply_all(messages)
+            })
+            .await?;
+        Ok(DigraphApplied { document, temp_ids })
+    }
+}
+
+pub struct SubscriptionRoot;
 
-        Ok(digraph_change(ctx, project_id, doc_id, msg).await?)
+fn document_events(events: Receiver<DocumentChanged>) -> impl Stream<Item = DocumentChanged> {
+    futures::stream::unfold(events, |mut events| async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => return Some((event, events)),
+                // A slow subscriber skips the events it missed
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+#[Subscription]
+impl SubscriptionRoot {
+    async fn document_changed(
+        &self,
+        ctx: &Context<'_>,
+        doc_id: Uuid,
+    ) -> impl Stream<Item = DocumentChanged> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+        document_events(storage.subscribe())
+            .filter(move |event| future::ready(event.document_id == doc_id))
+    }
+
+    async fn project_changed(
+        &self,
+        ctx: &Context<'_>,
+        project_id: Uuid,
+    ) -> impl Stream<Item = DocumentChanged> {
+        let storage = ctx.data::<EngineContainer>().expect("To get a container");
+        document_events(storage.subscribe())
+            .filter(move |event| future::ready(event.project_id == project_id))
     }
 }
 
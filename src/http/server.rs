@@ -1,64 +1,181 @@
 use async_graphql::extensions::{ApolloTracing, Logger};
-use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
-use async_graphql::{EmptySubscription, Schema};
+use async_graphql::http::{playground_source, GraphQLPlaygroundConfig, ALL_WEBSOCKET_PROTOCOLS};
+use async_graphql::{Data, Schema};
 use async_graphql::{Request, Response};
+use async_graphql_axum::{GraphQLProtocol, GraphQLWebSocket};
 
+use axum::body::BoxBody;
+use axum::extract::WebSocketUpgrade;
+use axum::handler::Handler;
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{self, HeaderValue, Method, StatusCode};
 use axum::response::IntoResponse;
 use axum::{extract::Extension, response::Html, routing::get, AddExtensionLayer, Json, Router};
-use std::net::SocketAddr;
+use std::net::{SocketAddr, ToSocketAddrs};
+use subtle::ConstantTimeEq;
+use tower_http::auth::{AuthorizeRequest, RequireAuthorizationLayer};
+use tower_http::cors::{any, CorsLayer, Origin};
+
+use super::graphql::{ConduitSchema, MutationRoot, Query, SubscriptionRoot};
+use crate::config::{Auth, Config};
+use crate::storage::engine::EngineContainer;
+
+/// Whether `authorization` is `Bearer` with `token`, compared in constant
+/// time so that the comparison does not tell how much of it matched
+fn is_bearer(authorization: &[u8], token: &str) -> bool {
+    let expected = format!("Bearer {}", token);
+    authorization.ct_eq(expected.as_bytes()).into()
+}
 
-use std::env;
+/// Lets through the requests whose `Authorization` header carries the
+/// bearer token
+#[derive(Clone)]
+struct BearerToken(String);
 
-use super::graphql::{MutationRoot, Query};
-use crate::storage::engine::{Engine, EngineContainer};
-use crate::storage::sqlite::Sqlite;
+impl<B> AuthorizeRequest<B> for BearerToken {
+    type ResponseBody = BoxBody;
 
-async fn graphql_handler(
-    schema: Extension<Schema<Query, MutationRoot, EmptySubscription>>,
-    req: Json<Request>,
-) -> Json<Response> {
-    schema.execute(req.0).await.into()
+    fn authorize(&mut self, request: &mut http::Request<B>) -> Result<(), http::Response<BoxBody>> {
+        let authorization = request
+            .headers()
+            .get(AUTHORIZATION)
+            .map_or(&[][..], |value| value.as_bytes());
+        if is_bearer(authorization, &self.0) {
+            Ok(())
+        } else {
+            Err(StatusCode::UNAUTHORIZED.into_response())
+        }
+    }
 }
 
-async fn graphql_playground() -> impl IntoResponse {
-    Html(playground_source(GraphQLPlaygroundConfig::new("/")))
+async fn graphql_handler(schema: Extension<ConduitSchema>, req: Json<Request>) -> Json<Response> {
+    schema.execute(req.0).await.into()
 }
 
-pub async fn serve() -> Result<(), ()> {
-    env_logger::init();
-    let listen_addr = env::var("LISTEN_ADDR").unwrap_or_else(|_| "localhost:8000".to_owned());
+async fn graphql_subscription(
+    schema: Extension<ConduitSchema>,
+    auth: Extension<Auth>,
+    protocol: GraphQLProtocol,
+    upgrade: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let schema = schema.0.clone();
+    let token = auth.0.bearer_token;
+    upgrade
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |stream| {
+            GraphQLWebSocket::new(stream, schema, protocol)
+                .on_connection_init(move |payload| connection_init(token.clone(), payload))
+                .serve()
+        })
+}
 
-    // let storage = Sqlite::setup(":memory:".into())
-    let storage = Sqlite::setup("test.db".into())
-        .await
-        .expect("The sqlite storage to be set up");
-    let _ = storage
-        .migrate()
-        .await
-        .expect("The sqlite storage to be migrated");
+/// Accept a subscription when its `connection_init` payload carries the
+/// same `Authorization` value that GraphQL requests have to send
+async fn connection_init(
+    token: Option<String>,
+    payload: serde_json::Value,
+) -> async_graphql::Result<Data> {
+    let token = match token {
+        Some(token) => token,
+        None => return Ok(Data::default()),
+    };
+    let authorization = payload
+        .get("Authorization")
+        .or_else(|| payload.get("authorization"))
+        .and_then(|value| value.as_str())
+        .unwrap_or_default();
+    if is_bearer(authorization.as_bytes(), &token) {
+        Ok(Data::default())
+    } else {
+        Err("Unauthorized".into())
+    }
+}
 
-    let project = crate::doc::project::Project::new(crate::util::naming::empty_uuid());
+async fn graphql_playground() -> impl IntoResponse {
+    Html(playground_source(
+        GraphQLPlaygroundConfig::new("/").subscription_endpoint("/ws"),
+    ))
+}
 
-    let _ = storage
-        .store_project(project.clone())
-        .await
-        .expect("The project to be inserted");
+/// The CORS layer for the configured origins, none of which may be invalid
+fn cors(config: &Config) -> Result<Option<CorsLayer>, String> {
+    let origins = &config.cors.allowed_origins;
+    if origins.is_empty() {
+        return Ok(None);
+    }
+    let layer = CorsLayer::new()
+        .allow_methods(vec![Method::GET, Method::POST])
+        .allow_headers(vec![AUTHORIZATION, CONTENT_TYPE]);
+    if origins.iter().any(|origin| origin == "*") {
+        return Ok(Some(layer.allow_origin(any())));
+    }
+    let origins = origins
+        .iter()
+        .map(|origin| {
+            HeaderValue::from_str(origin).map_err(|_| format!("Invalid CORS origin {}", origin))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(layer.allow_origin(Origin::list(origins))))
+}
 
-    let engine = EngineContainer::new(storage);
+pub async fn serve(engine: EngineContainer, config: Config) -> Result<(), ()> {
+    env_logger::Builder::new()
+        .parse_filters(&config.log_level)
+        .init();
 
-    let schema = Schema::build(Query, MutationRoot, EmptySubscription)
+    let schema = Schema::build(Query, MutationRoot, SubscriptionRoot)
         .extension(Logger)
         .extension(ApolloTracing)
+        .limit_depth(config.limits.max_query_depth)
         .data(engine)
+        .data(config.limits)
         .finish();
 
-    let app = Router::new()
-        .route("/", get(graphql_playground).post(graphql_handler))
-        .layer(AddExtensionLayer::new(schema));
-
-    println!("Playground: http://{}", listen_addr);
+    // Only queries and mutations need the token in a header, the playground
+    // is public and subscriptions send it with `connection_init`
+    let root = match &config.auth.bearer_token {
+        Some(token) => {
+            if HeaderValue::from_str(&format!("Bearer {}", token)).is_err() {
+                println!("Err Invalid bearer token");
+                return Err(());
+            }
+            let auth = RequireAuthorizationLayer::custom(BearerToken(token.clone()));
+            get(graphql_playground).post(graphql_handler.layer(auth))
+        }
+        None => get(graphql_playground).post(graphql_handler),
+    };
+
+    let mut app = Router::new()
+        .route("/", root)
+        .route("/ws", get(graphql_subscription))
+        .layer(AddExtensionLayer::new(schema))
+        .layer(AddExtensionLayer::new(config.auth.clone()));
+
+    match cors(&config) {
+        Ok(Some(layer)) => app = app.layer(layer),
+        Ok(None) => {}
+        Err(err) => {
+            println!("Err {}", err);
+            return Err(());
+        }
+    }
 
-    let sock_addr: SocketAddr = listen_addr.parse().unwrap();
+    println!("Playground: http://{}", config.listen_addr);
+
+    // Resolve host names such as localhost as well
+    let sock_addr: SocketAddr = match config.listen_addr.to_socket_addrs() {
+        Ok(mut addrs) => match addrs.next() {
+            Some(addr) => addr,
+            None => {
+                println!("Err {} : no address found", config.listen_addr);
+                return Err(());
+            }
+        },
+        Err(err) => {
+            println!("Err {} : {}", config.listen_addr, err);
+            return Err(());
+        }
+    };
     match axum::Server::bind(&sock_addr)
         .serve(app.into_make_service())
         .await
@@ -1,6 +1,7 @@
 #[macro_use]
 extern crate serde;
 
+pub mod config;
 pub mod doc;
 pub mod http;
 pub mod model;
@@ -0,0 +1,459 @@
+//! Analysis of the structure of a digraph. Nodes are given by id. Where an
+//! algorithm has a choice, it takes nodes in the order of the digraph, so
+//! results are stable between calls.
+
+use super::{Digraph, DigraphError};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+/// A path through a digraph
+#[derive(async_graphql::SimpleObject, Clone, Debug, PartialEq)]
+pub struct Path {
+    /// The nodes on the path, from source to target
+    pub nodes: Vec<i32>,
+    /// The links followed, one fewer than the nodes
+    pub links: Vec<i32>,
+    /// The sum of the weights of the links
+    pub cost: f64,
+}
+
+/// The links of each node, with nodes and links by position
+struct Adjacency {
+    ids: Vec<i32>,
+    positions: HashMap<i32, usize>,
+    /// The target and the link position of the links leaving a node
+    outgoing: Vec<Vec<(usize, usize)>>,
+    /// The source and the link position of the links reaching a node
+    incoming: Vec<Vec<(usize, usize)>>,
+}
+
+impl Adjacency {
+    fn new(graph: &Digraph) -> Self {
+        let ids: Vec<i32> = graph.nodes.iter().map(|node| node.id).collect();
+        let positions: HashMap<i32, usize> = ids
+            .iter()
+            .enumerate()
+            .map(|(position, id)| (*id, position))
+            .collect();
+        let mut outgoing = vec![vec![]; ids.len()];
+        let mut incoming = vec![vec![]; ids.len()];
+        for (position, link) in graph.links.iter().enumerate() {
+            let ends = (positions.get(&link.source), positions.get(&link.target));
+            if let (Some(&source), Some(&target)) = ends {
+                outgoing[source].push((target, position));
+                incoming[target].push((source, position));
+            }
+        }
+        Self {
+            ids,
+            positions,
+            outgoing,
+            incoming,
+        }
+    }
+
+    fn position(&self, id: i32) -> Result<usize, DigraphError> {
+        self.positions
+            .get(&id)
+            .copied()
+            .ok_or(DigraphError::IdDoesNotExist(id))
+    }
+
+    fn ids(&self, positions: impl IntoIterator<Item = usize>) -> Vec<i32> {
+        positions.into_iter().map(|pos| self.ids[pos]).collect()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    New,
+    Open,
+    Done,
+}
+
+/// The nodes reachable from `start` through `links`, nearest first,
+/// without `start` itself
+fn search(links: &[Vec<(usize, usize)>], start: usize) -> Vec<usize> {
+    let mut seen = vec![false; links.len()];
+    seen[start] = true;
+    let mut queue = VecDeque::from(vec![start]);
+    let mut found = vec![];
+    while let Some(node) = queue.pop_front() {
+        for &(next, _) in &links[node] {
+            if !seen[next] {
+                seen[next] = true;
+                found.push(next);
+                queue.push_back(next);
+            }
+        }
+    }
+    found
+}
+
+/// A node to visit in a shortest path search, the cheapest first
+#[derive(PartialEq)]
+struct Candidate {
+    cost: f64,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Digraph {
+    /// The node ids ordered so every link points forward, or a `Cycle`
+    /// error when there is none
+    pub fn topological_sort(&self) -> Result<Vec<i32>, DigraphError> {
+        let adjacency = Adjacency::new(self);
+        let mut pending: Vec<usize> = adjacency.incoming.iter().map(Vec::len).collect();
+        // Min-heap of positions, so the earliest ready node comes first
+        let mut ready: BinaryHeap<Reverse<usize>> = pending
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count == 0)
+            .map(|(node, _)| Reverse(node))
+            .collect();
+        let mut order = vec![];
+        while let Some(Reverse(node)) = ready.pop() {
+            order.push(node);
+            for &(next, _) in &adjacency.outgoing[node] {
+                pending[next] -= 1;
+                if pending[next] == 0 {
+                    ready.push(Reverse(next));
+                }
+            }
+        }
+        if order.len() < adjacency.ids.len() {
+            let cycle = self
+                .find_cycle()
+                .expect("A cycle when not all nodes are sorted");
+            return Err(DigraphError::Cycle(cycle));
+        }
+        Ok(adjacency.ids(order))
+    }
+
+    /// The nodes of a cycle in the order its links run, if there is one.
+    /// The last node links back to the first.
+    pub fn find_cycle(&self) -> Option<Vec<i32>> {
+        let adjacency = Adjacency::new(self);
+        let mut visits = vec![Visit::New; adjacency.ids.len()];
+        for start in 0..adjacency.ids.len() {
+            if visits[start] != Visit::New {
+                continue;
+            }
+            visits[start] = Visit::Open;
+            // The path being explored, with the next link to follow
+            let mut path: Vec<(usize, usize)> = vec![(start, 0)];
+            while let Some(&(node, next)) = path.last() {
+                let target = match adjacency.outgoing[node].get(next) {
+                    Some(&(target, _)) => target,
+                    None => {
+                        visits[node] = Visit::Done;
+                        path.pop();
+                        continue;
+                    }
+                };
+                if let Some(top) = path.last_mut() {
+                    top.1 += 1;
+                }
+                match visits[target] {
+                    Visit::New => {
+                        visits[target] = Visit::Open;
+                        path.push((target, 0));
+                    }
+                    Visit::Open => {
+                        let from = path
+                            .iter()
+                            .position(|(node, _)| *node == target)
+                            .expect("An open node to be on the path");
+                        return Some(adjacency.ids(path[from..].iter().map(|(node, _)| *node)));
+                    }
+                    Visit::Done => {}
+                }
+            }
+        }
+        None
+    }
+
+    /// The strongly connected components, each a set of nodes that can all
+    /// reach each other. A node on no cycle is a component of its own.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<i32>> {
+        let adjacency = Adjacency::new(self);
+        let count = adjacency.ids.len();
+
+        // Kosaraju: nodes by the time their search finishes, then searches
+        // against the links from the last to finish
+        let mut visits = vec![Visit::New; count];
+        let mut finished = Vec::with_capacity(count);
+        for start in 0..count {
+            if visits[start] != Visit::New {
+                continue;
+            }
+            visits[start] = Visit::Open;
+            let mut path: Vec<(usize, usize)> = vec![(start, 0)];
+            while let Some(&(node, next)) = path.last() {
+                match adjacency.outgoing[node].get(next) {
+                    Some(&(target, _)) => {
+                        if let Some(top) = path.last_mut() {
+                            top.1 += 1;
+                        }
+                        if visits[target] == Visit::New {
+                            visits[target] = Visit::Open;
+                            path.push((target, 0));
+                        }
+                    }
+                    None => {
+                        visits[node] = Visit::Done;
+                        finished.push(node);
+                        path.pop();
+                    }
+                }
+            }
+        }
+
+        let mut component_of: Vec<Option<usize>> = vec![None; count];
+        let mut components: Vec<Vec<usize>> = vec![];
+        for &root in finished.iter().rev() {
+            if component_of[root].is_some() {
+                continue;
+            }
+            let mut component = vec![root];
+            component_of[root] = Some(components.len());
+            let mut stack = vec![root];
+            while let Some(node) = stack.pop() {
+                for &(source, _) in &adjacency.incoming[node] {
+                    if component_of[source].is_none() {
+                        component_of[source] = Some(components.len());
+                        component.push(source);
+                        stack.push(source);
+                    }
+                }
+            }
+            component.sort_unstable();
+            components.push(component);
+        }
+        components.sort_by_key(|component| component[0]);
+        components
+            .into_iter()
+            .map(|component| adjacency.ids(component))
+            .collect()
+    }
+
+    /// The nodes that can reach `id`, nearest first
+    pub fn ancestors(&self, id: i32) -> Result<Vec<i32>, DigraphError> {
+        let adjacency = Adjacency::new(self);
+        let node = adjacency.position(id)?;
+        Ok(adjacency.ids(search(&adjacency.incoming, node)))
+    }
+
+    /// The nodes `id` can reach, nearest first
+    pub fn descendants(&self, id: i32) -> Result<Vec<i32>, DigraphError> {
+        let adjacency = Adjacency::new(self);
+        let node = adjacency.position(id)?;
+        Ok(adjacency.ids(search(&adjacency.outgoing, node)))
+    }
+
+    /// Whether links lead from `source` to `target`. Every node reaches
+    /// itself.
+    pub fn is_reachable(&self, source: i32, target: i32) -> Result<bool, DigraphError> {
+        let adjacency = Adjacency::new(self);
+        let source = adjacency.position(source)?;
+        let target = adjacency.position(target)?;
+        Ok(source == target || search(&adjacency.outgoing, source).contains(&target))
+    }
+
+    /// The cheapest path from `source` to `target`, if there is one. Links
+    /// cost the number in their `weight` label when a label is given, and
+    /// 1 otherwise or when they do not have it.
+    pub fn shortest_path(
+        &self,
+        source: i32,
+        target: i32,
+        weight: Option<&str>,
+    ) -> Result<Option<Path>, DigraphError> {
+        let adjacency = Adjacency::new(self);
+        let source = adjacency.position(source)?;
+        let target = adjacency.position(target)?;
+
+        let mut costs = Vec::with_capacity(self.links.len());
+        for link in &self.links {
+            let cost = match weight.and_then(|key| link.labels.get(key)) {
+                Some(value) => match value.trim().parse::<f64>() {
+                    Ok(cost) if cost >= 0.0 && cost.is_finite() => cost,
+                    _ => return Err(DigraphError::InvalidWeight(link.id, value.clone())),
+                },
+                None => 1.0,
+            };
+            costs.push(cost);
+        }
+
+        // Dijkstra, remembering the node and link each node was reached by
+        let mut best: Vec<Option<f64>> = vec![None; adjacency.ids.len()];
+        let mut reached_by: Vec<Option<(usize, usize)>> = vec![None; adjacency.ids.len()];
+        let mut done = vec![false; adjacency.ids.len()];
+        best[source] = Some(0.0);
+        let mut candidates = BinaryHeap::from(vec![Candidate {
+            cost: 0.0,
+            node: source,
+        }]);
+        while let Some(Candidate { cost, node }) = candidates.pop() {
+            if done[node] {
+                continue;
+            }
+            done[node] = true;
+            if node == target {
+                break;
+            }
+            for &(next, link) in &adjacency.outgoing[node] {
+                let cost = cost + costs[link];
+                let shorter = match best[next] {
+                    None => true,
+                    Some(known) => cost < known,
+                };
+                if shorter {
+                    best[next] = Some(cost);
+                    reached_by[next] = Some((node, link));
+                    candidates.push(Candidate { cost, node: next });
+                }
+            }
+        }
+
+        let cost = match best[target] {
+            Some(cost) => cost,
+            None => return Ok(None),
+        };
+        let mut nodes = vec![target];
+        let mut links = vec![];
+        let mut node = target;
+        while let Some((previous, link)) = reached_by[node] {
+            nodes.push(previous);
+            links.push(self.links[link].id);
+            node = previous;
+        }
+        nodes.reverse();
+        links.reverse();
+        Ok(Some(Path {
+            nodes: adjacency.ids(nodes),
+            links,
+            cost,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::model::digraph::test_util::digraph;
+    use crate::model::digraph::{Labels, LinkSettings};
+
+    #[test]
+    fn test_topological_sort() {
+        let dg = digraph(4, &[(3, 1), (1, 2), (3, 4), (4, 2)]);
+        assert_eq!(dg.topological_sort(), Ok(vec![3, 1, 4, 2]));
+        assert_eq!(dg.find_cycle(), None);
+        assert_eq!(Digraph::new().topological_sort(), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_cycles() {
+        let dg = digraph(4, &[(1, 2), (2, 3), (3, 4), (4, 2)]);
+        assert_eq!(dg.find_cycle(), Some(vec![2, 3, 4]));
+        assert_eq!(
+            dg.topological_sort(),
+            Err(DigraphError::Cycle(vec![2, 3, 4]))
+        );
+
+        let dg = digraph(2, &[(1, 2), (2, 2)]);
+        assert_eq!(dg.find_cycle(), Some(vec![2]));
+    }
+
+    #[test]
+    fn test_strongly_connected_components() {
+        let dg = digraph(6, &[(1, 2), (2, 1), (2, 3), (3, 4), (4, 5), (5, 3)]);
+        assert_eq!(
+            dg.strongly_connected_components(),
+            vec![vec![1, 2], vec![3, 4, 5], vec![6]]
+        );
+    }
+
+    #[test]
+    fn test_reachability() {
+        let dg = digraph(5, &[(1, 2), (2, 3), (1, 4), (5, 1)]);
+        assert_eq!(dg.descendants(1), Ok(vec![2, 4, 3]));
+        assert_eq!(dg.ancestors(3), Ok(vec![2, 1, 5]));
+        assert_eq!(dg.descendants(3), Ok(vec![]));
+        assert_eq!(dg.is_reachable(5, 3), Ok(true));
+        assert_eq!(dg.is_reachable(3, 5), Ok(false));
+        assert_eq!(dg.is_reachable(3, 3), Ok(true));
+        assert_eq!(dg.ancestors(9), Err(DigraphError::IdDoesNotExist(9)));
+        assert_eq!(dg.is_reachable(1, 9), Err(DigraphError::IdDoesNotExist(9)));
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let mut dg = digraph(4, &[(1, 2), (2, 4), (1, 3), (3, 4)]);
+        // Links 5 to 8, make the way through node 3 cheaper
+        for (id, weight) in &[(5, "2"), (6, "2"), (7, "1"), (8, "1.5")] {
+            let labels: Labels = vec![("weight".to_string(), weight.to_string())]
+                .into_iter()
+                .collect();
+            dg.update_link(
+                *id,
+                Some(LinkSettings {
+                    name: None,
+                    labels: Some(labels),
+                    ..LinkSettings::default()
+                }),
+            )
+            .expect("The link to be updated");
+        }
+
+        assert_eq!(
+            dg.shortest_path(1, 4, None),
+            Ok(Some(Path {
+                nodes: vec![1, 2, 4],
+                links: vec![5, 6],
+                cost: 2.0,
+            }))
+        );
+        assert_eq!(
+            dg.shortest_path(1, 4, Some("weight")),
+            Ok(Some(Path {
+                nodes: vec![1, 3, 4],
+                links: vec![7, 8],
+                cost: 2.5,
+            }))
+        );
+        assert_eq!(dg.shortest_path(4, 1, None), Ok(None));
+        assert_eq!(
+            dg.shortest_path(2, 2, None),
+            Ok(Some(Path {
+                nodes: vec![2],
+                links: vec![],
+                cost: 0.0,
+            }))
+        );
+
+        dg.links[0].labels.insert("weight".into(), "-1".into());
+        assert_eq!(
+            dg.shortest_path(1, 4, Some("weight")),
+            Err(DigraphError::InvalidWeight(5, "-1".into()))
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Batches of messages applied to a digraph all at once, so that building
+//! a graph takes one version instead of one per node and link.
+//!
+//! Nodes and links added by a batch can be given a temp id chosen by the
+//! client, which later messages of the same batch use to refer to them
+//! before their ids are known.
+
+use super::{Digraph, DigraphError, DigraphMessage, IdRef};
+use std::{error, fmt};
+
+/// A `DigraphMessage` of a batch, which may refer to nodes and links by
+/// the temp ids earlier messages gave them
+#[derive(async_graphql::InputObject, Serialize, Deserialize, Debug, PartialEq)]
+#[graphql(name = "DigraphMessageInput")]
+pub struct BatchMessage {
+    pub message: DigraphMessage,
+    /// The temp id to give the node or link the message adds
+    pub temp_id: Option<String>,
+}
+
+/// The id given to a node or link added with a temp id
+#[derive(async_graphql::SimpleObject, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TempId {
+    pub temp: String,
+    pub id: i32,
+}
+
+/// The message of a batch that failed, and why
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct BatchError {
+    /// The position of the message in the batch
+    pub index: usize,
+    pub error: DigraphError,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "message {} failed : {}", self.index, self.error)
+    }
+}
+
+impl error::Error for BatchError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// The temp ids of a batch, in the order they were given out
+#[derive(Default)]
+struct TempIds(Vec<TempId>);
+
+impl TempIds {
+    /// Replace a temp id by the id it was given
+    fn resolve(&self, id: &mut IdRef) -> Result<(), DigraphError> {
+        if let IdRef::Temp(temp) = id {
+            let resolved = self
+                .0
+                .iter()
+                .find(|e| &e.temp == temp)
+                .map(|e| e.id)
+                .ok_or_else(|| DigraphError::TempIdDoesNotExist(temp.clone()))?;
+            *id = IdRef::Id(resolved);
+        }
+        Ok(())
+    }
+
+    /// Fail when `temp` was given out already, so that it stays unambiguous
+    fn check(&self, temp: &Option<String>) -> Result<(), DigraphError> {
+        match temp {
+            Some(temp) if self.0.iter().any(|e| &e.temp == temp) => {
+                Err(DigraphError::TempIdAlreadyUsed(temp.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn assign(&mut self, temp: Option<String>, id: Option<i32>) {
+        if let (Some(temp), Some(id)) = (temp, id) {
+            self.0.push(TempId { temp, id });
+        }
+    }
+}
+
+impl Digraph {
+    /// Apply `msgs` in order, or none of them when one fails. Returns the
+    /// ids given to the nodes and links added with a temp id.
+    pub fn apply_all(&mut self, msgs: Vec<BatchMessage>) -> Result<Vec<TempId>, BatchError> {
+        let mut dg = self.clone();
+        let mut temp_ids = TempIds::default();
+
+        for (index, msg) in msgs.into_iter().enumerate() {
+            dg.apply(msg, &mut temp_ids)
+                .map_err(|error| BatchError { index, error })?;
+        }
+
+        *self = dg;
+        Ok(temp_ids.0)
+    }
+
+    fn apply(&mut self, msg: BatchMessage, temp_ids: &mut TempIds) -> Result<(), DigraphError> {
+        let BatchMessage {
+            mut message,
+            temp_id,
+        } = msg;
+        temp_ids.check(&temp_id)?;
+        for id in message.ids_mut() {
+            temp_ids.resolve(id)?;
+        }
+        let id = self.message(message)?;
+        temp_ids.assign(temp_id, id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::model::digraph::test_util::digraph;
+    use crate::model::digraph::{LinkUpdate, NewLink, NodeRemoval, NodeSettings, NodeUpdate};
+
+    fn batched(message: DigraphMessage) -> BatchMessage {
+        BatchMessage {
+            message,
+            temp_id: None,
+        }
+    }
+
+    fn new_node(temp: &str) -> BatchMessage {
+        BatchMessage {
+            message: DigraphMessage::AddNode(NodeSettings::default()),
+            temp_id: Some(temp.into()),
+        }
+    }
+
+    fn new_link(temp: Option<&str>, source: IdRef, target: IdRef) -> BatchMessage {
+        BatchMessage {
+            message: DigraphMessage::AddLink(NewLink {
+                source,
+                target,
+                attrs: None,
+            }),
+            temp_id: temp.map(String::from),
+        }
+    }
+
+    fn temp(temp: &str) -> IdRef {
+        IdRef::Temp(temp.into())
+    }
+
+    #[test]
+    fn test_apply_all() {
+        let mut dg = Digraph::new();
+        dg.add_node(None).expect("The node to be added");
+
+        let temp_ids = dg
+            .apply_all(vec![
+                new_node("a"),
+                new_node("b"),
+                new_link(Some("ab"), temp("a"), temp("b")),
+                new_link(None, IdRef::Id(1), temp("a")),
+                batched(DigraphMessage::UpdateNode(NodeUpdate {
+                    id: temp("b"),
+                    attrs: Some(NodeSettings {
+                        name: Some("b".into()),
+                        labels: None,
+                        ..NodeSettings::default()
+                    }),
+                })),
+            ])
+            .expect("The batch to be applied");
+
+        assert_eq!(
+            temp_ids,
+            vec![
+                TempId {
+                    temp: "a".into(),
+                    id: 2
+                },
+                TempId {
+                    temp: "b".into(),
+                    id: 3
+                },
+                TempId {
+                    temp: "ab".into(),
+                    id: 4
+                },
+            ]
+        );
+        assert_eq!(dg.nodes.len(), 3);
+        assert_eq!(dg.nodes[2].name, "b");
+        let links: Vec<(i32, i32, i32)> = dg
+            .links
+            .iter()
+            .map(|e| (e.id, e.source, e.target))
+            .collect();
+        assert_eq!(links, vec![(4, 2, 3), (5, 1, 2)]);
+    }
+
+    #[test]
+    fn test_apply_all_is_atomic() {
+        let mut dg = Digraph::new();
+        dg.add_node(None).expect("The node to be added");
+        let before = dg.clone();
+
+        assert_eq!(
+            dg.apply_all(vec![new_node("a"), new_link(None, temp("a"), temp("b")),]),
+            Err(BatchError {
+                index: 1,
+                error: DigraphError::TempIdDoesNotExist("b".into())
+            })
+        );
+        assert_eq!(
+            dg.apply_all(vec![new_node("a"), new_node("a")]),
+            Err(BatchError {
+                index: 1,
+                error: DigraphError::TempIdAlreadyUsed("a".into())
+            })
+        );
+        assert_eq!(
+            dg.apply_all(vec![
+                new_node("a"),
+                batched(DigraphMessage::RemoveNode(NodeRemoval { id: IdRef::Id(7) }))
+            ]),
+            Err(BatchError {
+                index: 1,
+                error: DigraphError::IdDoesNotExist(7)
+            })
+        );
+        assert_eq!(dg, before);
+    }
+
+    #[test]
+    fn test_apply_all_without_attrs() {
+        let mut dg = digraph(2, &[(1, 2)]);
+        let before = dg.clone();
+
+        dg.apply_all(vec![
+            batched(DigraphMessage::UpdateNode(NodeUpdate {
+                id: IdRef::Id(1),
+                attrs: None,
+            })),
+            batched(DigraphMessage::UpdateLink(LinkUpdate {
+                id: IdRef::Id(3),
+                attrs: None,
+            })),
+        ])
+        .expect("The batch to be applied");
+        assert_eq!(dg, before);
+
+        assert_eq!(
+            dg.apply_all(vec![batched(DigraphMessage::UpdateLink(LinkUpdate {
+                id: IdRef::Id(4),
+                attrs: None,
+            }))]),
+            Err(BatchError {
+                index: 0,
+                error: DigraphError::IdDoesNotExist(4)
+            })
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Rules a digraph can be held to, such as staying acyclic for documents
+//! that model pipelines. The rules are kept with the digraph, links that
+//! would break them are rejected, and rules can only be set when the
+//! digraph keeps them already.
+
+use super::{Digraph, DigraphError};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(
+    async_graphql::SimpleObject,
+    async_graphql::InputObject,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+)]
+#[graphql(input_name = "ConstraintsInput")]
+#[serde(default)]
+pub struct Constraints {
+    /// No links lead from a node back to itself
+    #[graphql(default)]
+    pub acyclic: bool,
+    /// No link has the same node as source and target
+    #[graphql(default)]
+    pub no_self_loops: bool,
+    /// No two links have the same source and target
+    #[graphql(default)]
+    pub no_parallel_links: bool,
+    /// The most links that may reach a node
+    pub max_in_degree: Option<i32>,
+    /// The most links that may leave a node
+    pub max_out_degree: Option<i32>,
+}
+
+impl Constraints {
+    /// Whether this allows any digraph
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// How an operation would break the constraints of a digraph
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Violation {
+    /// The nodes of the cycle, in the order its links run
+    Cycle(Vec<i32>),
+    /// A link from the node to itself
+    SelfLoop(i32),
+    /// A link between the same nodes as the existing link
+    ParallelLink(i32),
+    /// The node and the most links that may reach it
+    InDegree(i32, i32),
+    /// The node and the most links that may leave it
+    OutDegree(i32, i32),
+}
+
+/// Which links of a node a degree limit counts
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Degree {
+    /// The links reaching the node
+    In,
+    /// The links leaving the node
+    Out,
+}
+
+impl fmt::Display for Degree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Degree::In => write!(f, "links reaching a node"),
+            Degree::Out => write!(f, "links leaving a node"),
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::Cycle(nodes) => {
+                let nodes: Vec<String> = nodes.iter().map(i32::to_string).collect();
+                write!(f, "cycle through nodes {}", nodes.join(", "))
+            }
+            Violation::SelfLoop(id) => write!(f, "link from node {} to itself", id),
+            Violation::ParallelLink(id) => write!(f, "link parallel to link {}", id),
+            Violation::InDegree(id, max) => {
+                write!(f, "more than {} links reaching node {}", max, id)
+            }
+            Violation::OutDegree(id, max) => {
+                write!(f, "more than {} links leaving node {}", max, id)
+            }
+        }
+    }
+}
+
+impl Digraph {
+    /// Fail with a `ConstraintViolation` when a link from `source` to
+    /// `target` would break the constraints
+    pub fn check_link(&self, source: i32, target: i32) -> Result<(), DigraphError> {
+        let constraints = &self.constraints;
+        let violation = |violation| Err(DigraphError::ConstraintViolation(violation));
+
+        if constraints.no_self_loops && source == target {
+            return violation(Violation::SelfLoop(source));
+        }
+        if constraints.no_parallel_links {
+            let parallel = self
+                .links
+                .iter()
+                .find(|link| link.source == source && link.target == target);
+            if let Some(link) = parallel {
+                return violation(Violation::ParallelLink(link.id));
+            }
+        }
+        if let Some(max) = constraints.max_out_degree {
+            let count = self
+                .links
+                .iter()
+                .filter(|link| link.source == source)
+                .count();
+            if count as i32 >= max {
+                return violation(Violation::OutDegree(source, max));
+            }
+        }
+        if let Some(max) = constraints.max_in_degree {
+            let count = self
+                .links
+                .iter()
+                .filter(|link| link.target == target)
+                .count();
+            if count as i32 >= max {
+                return violation(Violation::InDegree(target, max));
+            }
+        }
+        if constraints.acyclic {
+            // The link closes a cycle when the target already leads back
+            if let Some(path) = self.shortest_path(target, source, None)? {
+                let mut cycle = path.nodes;
+                cycle.pop();
+                cycle.insert(0, source);
+                return violation(Violation::Cycle(cycle));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail with a `ConstraintViolation` when the digraph as it is breaks
+    /// `constraints`
+    pub fn check_constraints(&self, constraints: &Constraints) -> Result<(), DigraphError> {
+        let violation = |violation| Err(DigraphError::ConstraintViolation(violation));
+
+        if constraints.no_self_loops {
+            if let Some(link) = self.links.iter().find(|link| link.source == link.target) {
+                return violation(Violation::SelfLoop(link.source));
+            }
+        }
+        if constraints.no_parallel_links {
+            let mut seen = HashMap::new();
+            for link in &self.links {
+                if let Some(id) = seen.insert((link.source, link.target), link.id) {
+                    return violation(Violation::ParallelLink(id));
+                }
+            }
+        }
+        let mut out_degrees: HashMap<i32, i32> = HashMap::new();
+        let mut in_degrees: HashMap<i32, i32> = HashMap::new();
+        for link in &self.links {
+            *out_degrees.entry(link.source).or_default() += 1;
+            *in_degrees.entry(link.target).or_default() += 1;
+        }
+        for node in &self.nodes {
+            if let Some(max) = constraints.max_out_degree {
+                if out_degrees.get(&node.id).copied().unwrap_or_default() > max {
+                    return violation(Violation::OutDegree(node.id, max));
+                }
+            }
+            if let Some(max) = constraints.max_in_degree {
+                if in_degrees.get(&node.id).copied().unwrap_or_default() > max {
+                    return violation(Violation::InDegree(node.id, max));
+                }
+            }
+        }
+        if constraints.acyclic {
+            if let Some(cycle) = self.find_cycle() {
+                return violation(Violation::Cycle(cycle));
+            }
+        }
+        Ok(())
+    }
+
+    /// Hold the digraph to `constraints` from now on, failing when it
+    /// breaks them already
+    pub fn set_constraints(&mut self, constraints: Constraints) -> Result<(), DigraphError> {
+        let limits = [
+            (Degree::In, constraints.max_in_degree),
+            (Degree::Out, constraints.max_out_degree),
+        ];
+        for (degree, max) in limits.iter() {
+            if let Some(max) = max.filter(|max| *max < 0) {
+                return Err(DigraphError::NegativeDegreeLimit(*degree, max));
+            }
+        }
+        self.check_constraints(&constraints)?;
+        self.constraints = constraints;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::model::digraph::test_util::digraph;
+
+    fn violation<T>(violation: Violation) -> Result<T, DigraphError> {
+        Err(DigraphError::ConstraintViolation(violation))
+    }
+
+    #[test]
+    fn test_acyclic() {
+        let mut dg = digraph(3, &[(1, 2), (2, 3)]);
+        dg.set_constraints(Constraints {
+            acyclic: true,
+            ..Constraints::default()
+        })
+        .expect("The constraints to be set");
+
+        assert_eq!(
+            dg.add_link(3, 1, None),
+            violation(Violation::Cycle(vec![3, 1, 2]))
+        );
+        assert_eq!(
+            dg.add_link(2, 2, None),
+            violation(Violation::Cycle(vec![2]))
+        );
+        assert_eq!(dg.add_link(1, 3, None), Ok(6));
+        assert_eq!(dg.links.len(), 3);
+    }
+
+    #[test]
+    fn test_links() {
+        let mut dg = digraph(3, &[(1, 2)]);
+        dg.set_constraints(Constraints {
+            no_self_loops: true,
+            no_parallel_links: true,
+            ..Constraints::default()
+        })
+        .expect("The constraints to be set");
+
+        assert_eq!(dg.add_link(3, 3, None), violation(Violation::SelfLoop(3)));
+        assert_eq!(
+            dg.add_link(1, 2, None),
+            violation(Violation::ParallelLink(4))
+        );
+        assert_eq!(dg.add_link(2, 1, None), Ok(5));
+    }
+
+    #[test]
+    fn test_degrees() {
+        let mut dg = digraph(3, &[(1, 2)]);
+        dg.set_constraints(Constraints {
+            max_in_degree: Some(1),
+            max_out_degree: Some(2),
+            ..Constraints::default()
+        })
+        .expect("The constraints to be set");
+
+        assert_eq!(
+            dg.add_link(3, 2, None),
+            violation(Violation::InDegree(2, 1))
+        );
+        assert_eq!(dg.add_link(1, 3, None), Ok(5));
+        assert_eq!(
+            dg.add_link(1, 1, None),
+            violation(Violation::OutDegree(1, 2))
+        );
+    }
+
+    #[test]
+    fn test_set_constraints_when_broken() {
+        let mut dg = digraph(2, &[(1, 2), (2, 1), (1, 2)]);
+        let broken =
+            |constraints| digraph(2, &[(1, 2), (2, 1), (1, 2)]).check_constraints(&constraints);
+
+        assert_eq!(
+            broken(Constraints {
+                acyclic: true,
+                ..Constraints::default()
+            }),
+            violation(Violation::Cycle(vec![1, 2]))
+        );
+        assert_eq!(
+            broken(Constraints {
+                no_parallel_links: true,
+                ..Constraints::default()
+            }),
+            violation(Violation::ParallelLink(3))
+        );
+        assert_eq!(
+            broken(Constraints {
+                max_out_degree: Some(1),
+                ..Constraints::default()
+            }),
+            violation(Violation::OutDegree(1, 1))
+        );
+
+        let constraints = Constraints {
+            max_in_degree: Some(1),
+            ..Constraints::default()
+        };
+        assert_eq!(
+            dg.set_constraints(constraints),
+            violation(Violation::InDegree(2, 1))
+        );
+        assert!(dg.constraints.is_empty());
+    }
+
+    #[test]
+    fn test_set_negative_degree_limits() {
+        let mut dg = digraph(2, &[]);
+
+        assert_eq!(
+            dg.set_constraints(Constraints {
+                max_in_degree: Some(-1),
+                ..Constraints::default()
+            }),
+            Err(DigraphError::NegativeDegreeLimit(Degree::In, -1))
+        );
+        assert_eq!(
+            dg.set_constraints(Constraints {
+                max_in_degree: Some(0),
+                max_out_degree: Some(-2),
+                ..Constraints::default()
+            }),
+            Err(DigraphError::NegativeDegreeLimit(Degree::Out, -2))
+        );
+        assert!(dg.constraints.is_empty());
+        assert_eq!(
+            dg.set_constraints(Constraints {
+                max_in_degree: Some(0),
+                ..Constraints::default()
+            }),
+            Ok(())
+        );
+    }
+}
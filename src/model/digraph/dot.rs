@@ -0,0 +1,999 @@
+//! The Graphviz DOT language, so digraphs can be rendered with standard
+//! tooling and existing `.dot` files can be imported.
+//!
+//! Nodes are written with their id as DOT id, their name as `label` and
+//! their labels as further attributes. Links are written as edges with
+//! their name as `label`. The labels of the digraph become graph
+//! attributes.
+//!
+//! Importing does the reverse. Nodes and links get new ids in the order
+//! they appear, and a node without `label` is named after its DOT id.
+//! Ports are dropped. A node in a named subgraph gets the path of that
+//! subgraph as `subgraph` label, and attributes of the subgraph are kept
+//! as digraph labels prefixed with that path. Attributes of anonymous
+//! subgraphs, such as `rank=same`, have no such path and are dropped.
+//! Subgraphs nested deeper than 256 levels are rejected.
+
+use super::{Digraph, Labels, LinkSettings, NodeSettings, ParseError, MAX_NESTING};
+use std::collections::{BTreeMap, HashMap};
+
+/// The label holding the subgraph of an imported node
+pub const SUBGRAPH_LABEL: &str = "subgraph";
+
+const KEYWORDS: [&str; 6] = ["node", "edge", "graph", "digraph", "subgraph", "strict"];
+
+/// `text` as a DOT id, quoted unless it is a plain identifier or number
+pub fn id(text: &str) -> String {
+    if is_identifier(text) || is_numeral(text) {
+        text.to_string()
+    } else {
+        quote(text)
+    }
+}
+
+/// `text` as a quoted DOT string. Backslashes are doubled, since Graphviz
+/// reads `\n`, `\l` and the like as escapes in labels.
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => {}
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&text.to_ascii_lowercase().as_str())
+}
+
+fn is_numeral(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let mut parts = digits.splitn(2, '.');
+    let whole = parts.next().unwrap_or_default();
+    let fraction = parts.next().unwrap_or_default();
+    (!whole.is_empty() || !fraction.is_empty())
+        && whole.chars().all(|c| c.is_ascii_digit())
+        && fraction.chars().all(|c| c.is_ascii_digit())
+}
+
+/// An attribute list with `name` as label, followed by `labels` by key.
+/// A `label` in `labels` takes the place of the name.
+fn attributes(name: &str, labels: &Labels) -> String {
+    let mut attrs: BTreeMap<&str, &str> = BTreeMap::new();
+    if !name.is_empty() {
+        attrs.insert("label", name);
+    }
+    for (key, value) in labels {
+        attrs.insert(key, value);
+    }
+    if attrs.is_empty() {
+        return "".into();
+    }
+    let attrs: Vec<String> = attrs
+        .iter()
+        .map(|(key, value)| format!("{}={}", id(key), id(value)))
+        .collect();
+    format!(" [{}]", attrs.join(", "))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// An identifier, numeral or string, and whether it was quoted
+    Id(String, bool),
+    Html(String),
+    Open,
+    Close,
+    OpenAttrs,
+    CloseAttrs,
+    Equals,
+    Semicolon,
+    Comma,
+    Colon,
+    Plus,
+    Edge,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Id(text, _) => format!("'{}'", text),
+            Token::Html(_) => "an HTML string".into(),
+            Token::Open => "'{'".into(),
+            Token::Close => "'}'".into(),
+            Token::OpenAttrs => "'['".into(),
+            Token::CloseAttrs => "']'".into(),
+            Token::Equals => "'='".into(),
+            Token::Semicolon => "';'".into(),
+            Token::Comma => "','".into(),
+            Token::Colon => "':'".into(),
+            Token::Plus => "'+'".into(),
+            Token::Edge => "an edge operator".into(),
+        }
+    }
+
+    /// Whether this is the unquoted keyword `keyword`, in any case
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Id(text, false) if text.eq_ignore_ascii_case(keyword))
+    }
+}
+
+/// A token with the line and column it starts at
+type Spanned = (Token, usize, usize);
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn next_if(&mut self, expected: char) -> bool {
+        if self.chars.peek() == Some(&expected) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The tokens of the source, and the position of its end
+    fn tokens(mut self) -> Result<(Vec<Spanned>, (usize, usize)), ParseError> {
+        let mut tokens = vec![];
+        while let Some(&c) = self.chars.peek() {
+            let (line, column) = (self.line, self.column);
+            if c.is_whitespace() {
+                self.next();
+                continue;
+            }
+            if c == '#' && column == 1 {
+                // Output of the C preprocessor
+                while !matches!(self.next(), Some('\n') | None) {}
+                continue;
+            }
+            self.next();
+            let token = match c {
+                '/' if self.next_if('/') => {
+                    while !matches!(self.next(), Some('\n') | None) {}
+                    continue;
+                }
+                '/' if self.next_if('*') => {
+                    loop {
+                        match self.next() {
+                            Some('*') if self.next_if('/') => break,
+                            Some(_) => {}
+                            None => {
+                                return Err(ParseError {
+                                    line,
+                                    column,
+                                    message: "Unterminated comment".into(),
+                                })
+                            }
+                        }
+                    }
+                    continue;
+                }
+                '{' => Token::Open,
+                '}' => Token::Close,
+                '[' => Token::OpenAttrs,
+                ']' => Token::CloseAttrs,
+                '=' => Token::Equals,
+                ';' => Token::Semicolon,
+                ',' => Token::Comma,
+                ':' => Token::Colon,
+                '+' => Token::Plus,
+                '-' if self.next_if('>') || self.next_if('-') => Token::Edge,
+                '"' => self.string(line, column)?,
+                '<' => self.html(line, column)?,
+                c if c == '-' || c == '.' || c.is_ascii_digit() => self.numeral(c, line, column)?,
+                c if c == '_' || c.is_alphabetic() => {
+                    let mut text = c.to_string();
+                    while let Some(&c) = self.chars.peek() {
+                        if c == '_' || c.is_alphanumeric() {
+                            text.push(c);
+                            self.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    Token::Id(text, false)
+                }
+                c => {
+                    return Err(ParseError {
+                        line,
+                        column,
+                        message: format!("Unexpected character '{}'", c),
+                    })
+                }
+            };
+            tokens.push((token, line, column));
+        }
+        Ok((tokens, (self.line, self.column)))
+    }
+
+    /// A quoted string, without its quotes. `\"`, `\\` and `\n` are read
+    /// as what they stand for and escaped line breaks are dropped, other
+    /// escapes are kept for Graphviz.
+    fn string(&mut self, line: usize, column: usize) -> Result<Token, ParseError> {
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(Token::Id(text, true)),
+                Some('\\') => match self.next() {
+                    Some('"') => text.push('"'),
+                    Some('\\') => text.push('\\'),
+                    Some('n') => text.push('\n'),
+                    Some('\n') => {}
+                    Some(c) => {
+                        text.push('\\');
+                        text.push(c);
+                    }
+                    None => break,
+                },
+                Some(c) => text.push(c),
+                None => break,
+            }
+        }
+        Err(ParseError {
+            line,
+            column,
+            message: "Unterminated string".into(),
+        })
+    }
+
+    /// An HTML string, with its outer angle brackets
+    fn html(&mut self, line: usize, column: usize) -> Result<Token, ParseError> {
+        let mut text = "<".to_string();
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some(c) => {
+                    match c {
+                        '<' => depth += 1,
+                        '>' => depth -= 1,
+                        _ => {}
+                    }
+                    text.push(c);
+                }
+                None => {
+                    return Err(ParseError {
+                        line,
+                        column,
+                        message: "Unterminated HTML string".into(),
+                    })
+                }
+            }
+        }
+        Ok(Token::Html(text))
+    }
+
+    fn numeral(&mut self, first: char, line: usize, column: usize) -> Result<Token, ParseError> {
+        let mut text = first.to_string();
+        let mut dot = first == '.';
+        while let Some(&c) = self.chars.peek() {
+            if c.is_ascii_digit() || (c == '.' && !dot) {
+                dot = dot || c == '.';
+                text.push(c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+        if !text.chars().any(|c| c.is_ascii_digit()) {
+            return Err(ParseError {
+                line,
+                column,
+                message: format!("Invalid number '{}'", text),
+            });
+        }
+        Ok(Token::Id(text, false))
+    }
+}
+
+/// Attribute defaults of a graph or subgraph
+#[derive(Clone, Default)]
+struct Scope {
+    node: Vec<(String, String)>,
+    edge: Vec<(String, String)>,
+    /// Names of the enclosing named subgraphs, joined by `/`
+    subgraph: Option<String>,
+    /// Whether this is an anonymous subgraph, whose attributes are dropped
+    anonymous: bool,
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    pos: usize,
+    end: (usize, usize),
+    graph: Digraph,
+    /// Node ids by DOT id
+    ids: HashMap<String, i32>,
+    scopes: Vec<Scope>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _, _)| token)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens
+            .get(self.pos + offset)
+            .map(|(token, _, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn next_if(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, ParseError> {
+        let (line, column) = match self.tokens.get(self.pos) {
+            Some((_, line, column)) => (*line, *column),
+            None => self.end,
+        };
+        Err(ParseError {
+            line,
+            column,
+            message,
+        })
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, ParseError> {
+        let found = match self.peek() {
+            Some(token) => token.describe(),
+            None => "the end of the input".into(),
+        };
+        self.error(format!("Expected {}, found {}", expected, found))
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        if self.next_if(&expected) {
+            Ok(())
+        } else {
+            self.unexpected(&expected.describe())
+        }
+    }
+
+    fn scope(&self) -> &Scope {
+        self.scopes.last().expect("The graph scope to exist")
+    }
+
+    fn scope_mut(&mut self) -> &mut Scope {
+        self.scopes.last_mut().expect("The graph scope to exist")
+    }
+
+    /// An ID, joining quoted strings concatenated with `+`
+    fn id(&mut self) -> Result<String, ParseError> {
+        match self.peek().cloned() {
+            Some(Token::Id(mut text, quoted)) => {
+                self.pos += 1;
+                while quoted && self.peek() == Some(&Token::Plus) {
+                    self.pos += 1;
+                    match self.next() {
+                        Some(Token::Id(more, true)) => text.push_str(&more),
+                        _ => {
+                            self.pos -= 1;
+                            return self.unexpected("a quoted string");
+                        }
+                    }
+                }
+                Ok(text)
+            }
+            Some(Token::Html(text)) => {
+                self.pos += 1;
+                Ok(text)
+            }
+            _ => self.unexpected("an ID"),
+        }
+    }
+
+    fn graph(mut self) -> Result<Digraph, ParseError> {
+        if self.peek().map(|token| token.is_keyword("strict")) == Some(true) {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(token) if token.is_keyword("digraph") || token.is_keyword("graph") => {
+                self.pos += 1;
+            }
+            _ => return self.unexpected("'digraph'"),
+        }
+        if self.peek() != Some(&Token::Open) {
+            self.graph.name = self.id()?;
+        }
+        self.expect(Token::Open)?;
+        self.statements()?;
+        self.expect(Token::Close)?;
+        if self.peek().is_some() {
+            return self.unexpected("the end of the input");
+        }
+        Ok(self.graph)
+    }
+
+    /// Statements up to the closing brace, returning the nodes they mention
+    fn statements(&mut self) -> Result<Vec<i32>, ParseError> {
+        let mut mentioned = vec![];
+        while !matches!(self.peek(), Some(Token::Close) | None) {
+            self.statement(&mut mentioned)?;
+            self.next_if(&Token::Semicolon);
+        }
+        Ok(mentioned)
+    }
+
+    fn statement(&mut self, mentioned: &mut Vec<i32>) -> Result<(), ParseError> {
+        let token = self.peek().cloned();
+        let token = match token {
+            Some(token) => token,
+            None => return self.unexpected("a statement"),
+        };
+
+        for kind in &["graph", "node", "edge"] {
+            if token.is_keyword(kind) {
+                self.pos += 1;
+                let attrs = self.attributes()?;
+                match *kind {
+                    "graph" => self.graph_attributes(attrs),
+                    "node" => self.scope_mut().node.extend(attrs),
+                    _ => self.scope_mut().edge.extend(attrs),
+                }
+                return Ok(());
+            }
+        }
+
+        let is_attribute = self.peek_at(1) == Some(&Token::Equals);
+        if is_attribute && matches!(token, Token::Id(..) | Token::Html(_)) {
+            let key = self.id()?;
+            self.expect(Token::Equals)?;
+            let value = self.id()?;
+            self.graph_attributes(vec![(key, value)]);
+            return Ok(());
+        }
+
+        let first = self.operand()?;
+        mentioned.extend(&first);
+        if self.peek() == Some(&Token::Edge) {
+            let mut operands = vec![first];
+            while self.next_if(&Token::Edge) {
+                let operand = self.operand()?;
+                mentioned.extend(&operand);
+                operands.push(operand);
+            }
+            let attrs = self.attributes_if_any()?;
+            for pair in operands.windows(2) {
+                for source in &pair[0] {
+                    for target in &pair[1] {
+                        self.link(*source, *target, &attrs);
+                    }
+                }
+            }
+        } else if let Token::Id(..) | Token::Html(_) = token {
+            let attrs = self.attributes_if_any()?;
+            for id in first {
+                self.set_node_attributes(id, attrs.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// A node or a subgraph, as the nodes it stands for
+    fn operand(&mut self) -> Result<Vec<i32>, ParseError> {
+        match self.peek() {
+            Some(Token::Open) => self.subgraph(None),
+            Some(token) if token.is_keyword("subgraph") => {
+                self.pos += 1;
+                let name = match self.peek() {
+                    Some(Token::Open) => None,
+                    _ => Some(self.id()?),
+                };
+                self.subgraph(name)
+            }
+            Some(Token::Id(..)) | Some(Token::Html(_)) => {
+                let name = self.id()?;
+                // Ports are not kept
+                while self.next_if(&Token::Colon) {
+                    self.id()?;
+                }
+                Ok(vec![self.node(&name)])
+            }
+            _ => self.unexpected("a node or subgraph"),
+        }
+    }
+
+    fn subgraph(&mut self, name: Option<String>) -> Result<Vec<i32>, ParseError> {
+        if self.scopes.len() > MAX_NESTING {
+            return self.error(format!(
+                "Subgraphs nested deeper than {} levels",
+                MAX_NESTING
+            ));
+        }
+        let mut scope = self.scope().clone();
+        scope.anonymous = name.is_none();
+        if let Some(name) = name {
+            scope.subgraph = Some(match scope.subgraph {
+                Some(parent) => format!("{}/{}", parent, name),
+                None => name,
+            });
+        }
+        self.scopes.push(scope);
+        self.expect(Token::Open)?;
+        let mentioned = self.statements()?;
+        self.expect(Token::Close)?;
+        self.scopes.pop();
+        Ok(mentioned)
+    }
+
+    fn attributes_if_any(&mut self) -> Result<Vec<(String, String)>, ParseError> {
+        if self.peek() == Some(&Token::OpenAttrs) {
+            self.attributes()
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    /// One or more attribute lists
+    fn attributes(&mut self) -> Result<Vec<(String, String)>, ParseError> {
+        let mut attrs = vec![];
+        self.expect(Token::OpenAttrs)?;
+        loop {
+            if self.next_if(&Token::CloseAttrs) {
+                if self.peek() != Some(&Token::OpenAttrs) {
+                    return Ok(attrs);
+                }
+                self.pos += 1;
+                continue;
+            }
+            let key = self.id()?;
+            let value = if self.next_if(&Token::Equals) {
+                self.id()?
+            } else {
+                "true".into()
+            };
+            attrs.push((key, value));
+            if !self.next_if(&Token::Comma) {
+                self.next_if(&Token::Semicolon);
+            }
+        }
+    }
+
+    /// Attributes of the graph, or of the current subgraph
+    fn graph_attributes(&mut self, attrs: Vec<(String, String)>) {
+        if self.scope().anonymous {
+            return;
+        }
+        let prefix = self.scope().subgraph.clone();
+        for (key, value) in attrs {
+            let key = match &prefix {
+                Some(prefix) => format!("{}.{}", prefix, key),
+                None => key,
+            };
+            self.graph.labels.insert(key, value);
+        }
+    }
+
+    /// The id of the node named `name` in DOT, adding it when it is new
+    fn node(&mut self, name: &str) -> i32 {
+        let id = match self.ids.get(name) {
+            Some(id) => *id,
+            None => {
+                let _ = self.graph.add_node(Some(NodeSettings {
+                    name: Some(name.to_string()),
+                    labels: None,
+                    ..NodeSettings::default()
+                }));
+                let id = self.graph.nodes.last().expect("The node to be added").id;
+                self.ids.insert(name.to_string(), id);
+                let defaults = self.scope().node.clone();
+                self.set_node_attributes(id, defaults);
+                id
+            }
+        };
+        if let Some(subgraph) = self.scope().subgraph.clone() {
+            self.set_node_attributes(id, vec![(SUBGRAPH_LABEL.into(), subgraph)]);
+        }
+        id
+    }
+
+    fn set_node_attributes(&mut self, id: i32, attrs: Vec<(String, String)>) {
+        let (name, labels) = settings(attrs);
+        let _ = self.graph.update_node(
+            id,
+            Some(NodeSettings {
+                name,
+                labels,
+                ..NodeSettings::default()
+            }),
+        );
+    }
+
+    fn link(&mut self, source: i32, target: i32, attrs: &[(String, String)]) {
+        let mut all = self.scope().edge.clone();
+        all.extend_from_slice(attrs);
+        let (name, labels) = settings(all);
+        let _ = self.graph.add_link(
+            source,
+            target,
+            Some(LinkSettings {
+                name: Some(name.unwrap_or_default()),
+                labels,
+                ..LinkSettings::default()
+            }),
+        );
+    }
+}
+
+/// A name from the `label` attribute and labels from the others
+fn settings(attrs: Vec<(String, String)>) -> (Option<String>, Option<Labels>) {
+    let mut name = None;
+    let mut labels = Labels::new();
+    for (key, value) in attrs {
+        if key == "label" {
+            name = Some(value);
+        } else {
+            labels.insert(key, value);
+        }
+    }
+    (name, Some(labels))
+}
+
+impl Digraph {
+    /// Build a digraph from a graph in the Graphviz DOT language
+    pub fn from_dot(source: &str) -> Result<Self, ParseError> {
+        let lexer = Lexer {
+            chars: source.chars().peekable(),
+            line: 1,
+            column: 1,
+        };
+        let (tokens, end) = lexer.tokens()?;
+        let parser = Parser {
+            tokens,
+            pos: 0,
+            end,
+            graph: Digraph::new(),
+            ids: HashMap::new(),
+            scopes: vec![Scope::default()],
+        };
+        parser.graph()
+    }
+
+    /// Render this digraph in the Graphviz DOT language
+    pub fn to_dot(&self) -> String {
+        let mut dot = if self.name.is_empty() {
+            "digraph {\n".to_string()
+        } else {
+            format!("digraph {} {{\n", id(&self.name))
+        };
+
+        let labels: BTreeMap<&String, &String> = self.labels.iter().collect();
+        for (key, value) in labels {
+            dot.push_str(&format!("    {}={}\n", id(key), id(value)));
+        }
+        for node in &self.nodes {
+            dot.push_str(&format!(
+                "    {}{}\n",
+                node.id,
+                attributes(&node.name, &node.labels)
+            ));
+        }
+        for link in &self.links {
+            dot.push_str(&format!(
+                "    {} -> {}{}\n",
+                link.source,
+                link.target,
+                attributes(&link.name, &link.labels)
+            ));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::model::digraph::test_util::labels;
+    use crate::model::digraph::{LinkSettings, NodeSettings};
+
+    #[test]
+    fn test_ids() {
+        assert_eq!(id("node_1"), "node_1");
+        assert_eq!(id("12"), "12");
+        assert_eq!(id("-1.5"), "-1.5");
+        assert_eq!(id("1a"), "\"1a\"");
+        assert_eq!(id("Node"), "\"Node\"");
+        assert_eq!(id(""), "\"\"");
+        assert_eq!(id("two words"), "\"two words\"");
+        assert_eq!(id("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(id("a\\b\nc"), "\"a\\\\b\\nc\"");
+    }
+
+    fn import(source: &str) -> Digraph {
+        Digraph::from_dot(source).expect("The DOT source to be valid")
+    }
+
+    fn error(source: &str) -> (usize, usize, String) {
+        let err = Digraph::from_dot(source).expect_err("The DOT source to be invalid");
+        (err.line, err.column, err.message)
+    }
+
+    #[test]
+    fn test_empty_digraph() {
+        assert_eq!(Digraph::new().to_dot(), "digraph {\n}\n");
+    }
+
+    #[test]
+    fn test_digraph_to_dot() {
+        let mut dg = Digraph::new();
+        dg.name = "Service map".into();
+        dg.labels = labels(&[("rankdir", "LR")]);
+        dg.add_node(Some(NodeSettings {
+            name: Some("Web \"frontend\"".into()),
+            labels: Some(labels(&[("shape", "box"), ("color", "blue")])),
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_node(Some(NodeSettings {
+            name: Some("db".into()),
+            labels: None,
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_node(Some(NodeSettings {
+            name: Some("".into()),
+            labels: None,
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_link(
+            1,
+            2,
+            Some(LinkSettings {
+                name: Some("reads from".into()),
+                labels: Some(labels(&[("style", "dashed")])),
+                ..LinkSettings::default()
+            }),
+        )
+        .expect("The link to be added");
+        dg.add_link(
+            2,
+            3,
+            Some(LinkSettings {
+                name: Some("".into()),
+                labels: Some(labels(&[("label", "replaces the name")])),
+                ..LinkSettings::default()
+            }),
+        )
+        .expect("The link to be added");
+
+        assert_eq!(
+            dg.to_dot(),
+            "digraph \"Service map\" {
+    rankdir=LR
+    1 [color=blue, label=\"Web \\\"frontend\\\"\", shape=box]
+    2 [label=db]
+    3
+    1 -> 2 [label=\"reads from\", style=dashed]
+    2 -> 3 [label=\"replaces the name\"]
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_import_nodes_and_links() {
+        let dg = import(
+            "
+            // A small service map
+            digraph \"Service map\" {
+                rankdir = LR;
+                node [shape=box]
+                web [label=\"Web \\\"frontend\\\"\", color=blue];
+                web -> db -> cache [label=\"reads\" style=dashed];
+                /* Nodes that only appear in edges are added too */
+                db -> \"disk 1\":p1;
+            }
+            ",
+        );
+
+        assert_eq!(dg.name, "Service map");
+        assert_eq!(dg.labels, labels(&[("rankdir", "LR")]));
+
+        let nodes: Vec<(i32, &str)> = dg.nodes.iter().map(|n| (n.id, n.name.as_str())).collect();
+        assert_eq!(
+            nodes,
+            vec![
+                (1, "Web \"frontend\""),
+                (2, "db"),
+                (3, "cache"),
+                (6, "disk 1")
+            ]
+        );
+        assert_eq!(
+            dg.nodes[0].labels,
+            labels(&[("shape", "box"), ("color", "blue")])
+        );
+        assert_eq!(dg.nodes[3].labels, labels(&[("shape", "box")]));
+
+        let links: Vec<(i32, i32, i32, &str)> = dg
+            .links
+            .iter()
+            .map(|l| (l.id, l.source, l.target, l.name.as_str()))
+            .collect();
+        assert_eq!(
+            links,
+            vec![(4, 1, 2, "reads"), (5, 2, 3, "reads"), (7, 2, 6, "")]
+        );
+        assert_eq!(dg.links[0].labels, labels(&[("style", "dashed")]));
+        assert_eq!(dg.links[2].labels, labels(&[]));
+    }
+
+    #[test]
+    fn test_import_subgraphs() {
+        let dg = import(
+            "
+            strict digraph {
+                subgraph cluster_backend {
+                    label = \"Backend\"
+                    api; db
+                    subgraph cluster_storage { db }
+                }
+                { rank = same; ui }
+                ui -> { api db }
+            }
+            ",
+        );
+
+        let subgraph = |name: &str| {
+            dg.nodes
+                .iter()
+                .find(|node| node.name == name)
+                .and_then(|node| node.labels.get(SUBGRAPH_LABEL))
+                .map(String::as_str)
+        };
+        assert_eq!(subgraph("api"), Some("cluster_backend"));
+        assert_eq!(subgraph("db"), Some("cluster_backend/cluster_storage"));
+        assert_eq!(subgraph("ui"), None);
+        assert_eq!(dg.labels, labels(&[("cluster_backend.label", "Backend")]));
+        assert_eq!(dg.links.len(), 2);
+    }
+
+    #[test]
+    fn test_import_anonymous_subgraphs() {
+        let dg = import(
+            "
+            digraph {
+                rankdir = LR
+                { rank = same; a b }
+                subgraph cluster_x {
+                    color = blue
+                    { rank = min; c }
+                    subgraph { label = \"Inner\"; d }
+                }
+            }
+            ",
+        );
+
+        assert_eq!(
+            dg.labels,
+            labels(&[("rankdir", "LR"), ("cluster_x.color", "blue")])
+        );
+        let subgraph = |name: &str| {
+            dg.nodes
+                .iter()
+                .find(|node| node.name == name)
+                .and_then(|node| node.labels.get(SUBGRAPH_LABEL))
+                .map(String::as_str)
+        };
+        assert_eq!(subgraph("a"), None);
+        assert_eq!(subgraph("c"), Some("cluster_x"));
+        assert_eq!(subgraph("d"), Some("cluster_x"));
+    }
+
+    #[test]
+    fn test_import_exported() {
+        let mut dg = Digraph::new();
+        dg.name = "Round trip".into();
+        dg.add_node(Some(NodeSettings {
+            name: Some("a \\\\ b\nc".into()),
+            labels: Some(labels(&[("shape", "box")])),
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_node(Some(NodeSettings {
+            name: Some("graph".into()),
+            labels: None,
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_link(
+            1,
+            2,
+            Some(LinkSettings {
+                name: Some("uses".into()),
+                labels: Some(labels(&[("weight", "2")])),
+                ..LinkSettings::default()
+            }),
+        )
+        .expect("The link to be added");
+
+        assert_eq!(import(&dg.to_dot()), dg);
+    }
+
+    #[test]
+    fn test_import_errors() {
+        assert_eq!(
+            error("graph {\n  a -> \n}"),
+            (3, 1, "Expected a node or subgraph, found '}'".into())
+        );
+        assert_eq!(
+            error("digraph {\n  a [color=red\n"),
+            (3, 1, "Expected an ID, found the end of the input".into())
+        );
+        assert_eq!(
+            error("digraph {\n  a -> b ! c\n}"),
+            (2, 10, "Unexpected character '!'".into())
+        );
+        assert_eq!(
+            error("digraph {\n  a [label=\"open\n}"),
+            (2, 12, "Unterminated string".into())
+        );
+        assert_eq!(
+            error("node { }"),
+            (1, 1, "Expected 'digraph', found 'node'".into())
+        );
+        assert_eq!(
+            error("digraph { } digraph { }"),
+            (
+                1,
+                13,
+                "Expected the end of the input, found 'digraph'".into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_import_deep_nesting() {
+        let nested =
+            |depth: usize| format!("digraph {{{}a{}}}", "{".repeat(depth), "}".repeat(depth));
+
+        assert_eq!(import(&nested(MAX_NESTING)).nodes.len(), 1);
+        assert_eq!(
+            error(&nested(200_000)),
+            (1, 266, "Subgraphs nested deeper than 256 levels".into())
+        );
+    }
+}
@@ -0,0 +1,366 @@
+//! GEXF, the XML format of Gephi.
+//!
+//! Node and link names are written as GEXF labels, and their labels as
+//! attribute values for attributes declared per class, typed after the
+//! values they have. GEXF has no attributes for a graph, so the name of
+//! the digraph is kept as description in `<meta>` and its labels as a
+//! JSON object in the keywords. Nodes and links keep their id, so a
+//! written digraph reads back as it was.
+//!
+//! Importing does the reverse, with declared defaults applied. Nodes and
+//! links keep an integer id that is not taken and get a new one
+//! otherwise, and a node without label is then named after its GEXF id.
+//! Keywords that are not a JSON object of strings are kept as label
+//! `keywords`. Visualization data, spells and nested nodes are dropped.
+
+use super::xml::{self, declarations, Element, Ids};
+use super::{Digraph, Labels, Link, Node, ParseError};
+use std::collections::{BTreeMap, HashMap};
+
+/// The attributes declared for `class`, with their title and default by id
+fn attributes<'a>(
+    graph: Element<'a, '_>,
+    class: &str,
+) -> Result<HashMap<&'a str, (&'a str, Option<String>)>, ParseError> {
+    let mut declared = HashMap::new();
+    for attributes in graph.children("attributes") {
+        if attributes.attribute("class") != Some(class) {
+            continue;
+        }
+        for attribute in attributes.children("attribute") {
+            let id = attribute.required("id")?;
+            let title = attribute.attribute("title").unwrap_or(id);
+            let default = attribute.child("default").map(|default| default.text());
+            declared.insert(id, (title, default));
+        }
+    }
+    Ok(declared)
+}
+
+/// The labels of `element` from its attribute values and the defaults
+fn labels(
+    declared: &HashMap<&str, (&str, Option<String>)>,
+    element: Element,
+) -> Result<Labels, ParseError> {
+    let mut values: HashMap<&str, String> = declared
+        .iter()
+        .filter_map(|(id, (_, default))| Some((*id, default.clone()?)))
+        .collect();
+    for attvalues in element.children("attvalues") {
+        for attvalue in attvalues.children("attvalue") {
+            let id = match attvalue.attribute("for") {
+                Some(id) => id,
+                None => attvalue.required("id")?,
+            };
+            values.insert(id, attvalue.required("value")?.to_string());
+        }
+    }
+    Ok(values
+        .into_iter()
+        .map(|(id, value)| {
+            let title = declared.get(id).map_or(id, |(title, _)| title);
+            (title.to_string(), value)
+        })
+        .collect())
+}
+
+/// `<attvalues>` for `labels`, with attribute ids by title
+fn attvalues(labels: &Labels, ids: &HashMap<&str, usize>) -> String {
+    let labels: BTreeMap<&String, &String> = labels.iter().collect();
+    let mut attvalues = String::new();
+    for (key, value) in labels {
+        attvalues.push_str(&format!(
+            "          <attvalue for=\"{}\" value=\"{}\"/>\n",
+            ids[key.as_str()],
+            xml::escape(value)
+        ));
+    }
+    if attvalues.is_empty() {
+        attvalues
+    } else {
+        format!("        <attvalues>\n{}        </attvalues>\n", attvalues)
+    }
+}
+
+/// An opening tag with `attrs`, which closes itself when there is no
+/// `content`
+fn element(name: &str, attrs: &str, content: &str) -> String {
+    if content.is_empty() {
+        format!("      <{}{}/>\n", name, attrs)
+    } else {
+        format!("      <{}{}>\n{}      </{}>\n", name, attrs, content, name)
+    }
+}
+
+impl Digraph {
+    /// Build a digraph from a GEXF document
+    pub fn from_gexf(source: &str) -> Result<Self, ParseError> {
+        let document = xml::parse(source)?;
+        let root = Element::root(&document);
+        if root.name() != "gexf" {
+            return root.error(format!("Expected <gexf>, found <{}>", root.name()));
+        }
+        let graph = match root.child("graph") {
+            Some(graph) => graph,
+            None => return root.error("Missing <graph>".into()),
+        };
+
+        let mut digraph = Digraph::new();
+        if let Some(meta) = root.child("meta") {
+            if let Some(description) = meta.child("description") {
+                digraph.name = description.text();
+            }
+            if let Some(keywords) = meta.child("keywords") {
+                let keywords = keywords.text();
+                digraph.labels = match serde_json::from_str(&keywords) {
+                    Ok(labels) => labels,
+                    Err(_) => vec![("keywords".to_string(), keywords)]
+                        .into_iter()
+                        .collect(),
+                };
+            }
+        }
+
+        let node_attributes = attributes(graph, "node")?;
+        let edge_attributes = attributes(graph, "edge")?;
+        let nodes: Vec<Element> = graph
+            .children("nodes")
+            .flat_map(|n| n.children("node"))
+            .collect();
+        let edges: Vec<Element> = graph
+            .children("edges")
+            .flat_map(|e| e.children("edge"))
+            .collect();
+
+        let mut ids = Ids::new(
+            nodes
+                .iter()
+                .chain(edges.iter())
+                .filter_map(|element| element.attribute("id")),
+        );
+        let mut node_ids: HashMap<&str, i32> = HashMap::new();
+        for node in nodes {
+            let gexf_id = node.required("id")?;
+            if node_ids.contains_key(gexf_id) {
+                return node.error(format!("Duplicate node id '{}'", gexf_id));
+            }
+            let (id, kept) = ids.assign(Some(gexf_id));
+            node_ids.insert(gexf_id, id);
+            let name = match node.attribute("label") {
+                Some(label) => label.to_string(),
+                None if kept => "".into(),
+                None => gexf_id.to_string(),
+            };
+            digraph.nodes.push(Node {
+                id,
+                name,
+                labels: labels(&node_attributes, node)?,
+            });
+        }
+
+        for edge in edges {
+            let mut ends = vec![];
+            for end in &["source", "target"] {
+                let node = edge.required(end)?;
+                match node_ids.get(node) {
+                    Some(id) => ends.push(*id),
+                    None => return edge.error(format!("Unknown node '{}'", node)),
+                }
+            }
+            let (id, _) = ids.assign(edge.attribute("id"));
+            digraph.links.push(Link {
+                id,
+                name: edge.attribute("label").unwrap_or_default().to_string(),
+                source: ends[0],
+                target: ends[1],
+                labels: labels(&edge_attributes, edge)?,
+            });
+        }
+        Ok(digraph)
+    }
+
+    /// Render this digraph as a GEXF document
+    pub fn to_gexf(&self) -> String {
+        let mut gexf = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n",
+        ));
+
+        let mut meta = String::new();
+        if !self.name.is_empty() {
+            meta.push_str(&format!(
+                "    <description>{}</description>\n",
+                xml::escape(&self.name)
+            ));
+        }
+        if !self.labels.is_empty() {
+            let labels: BTreeMap<&String, &String> = self.labels.iter().collect();
+            let labels = serde_json::to_string(&labels).expect("Labels to serialize");
+            meta.push_str(&format!(
+                "    <keywords>{}</keywords>\n",
+                xml::escape(&labels)
+            ));
+        }
+        if !meta.is_empty() {
+            gexf.push_str(&format!("  <meta>\n{}  </meta>\n", meta));
+        }
+
+        gexf.push_str("  <graph defaultedgetype=\"directed\" mode=\"static\">\n");
+        let mut ids: HashMap<&str, HashMap<&str, usize>> = HashMap::new();
+        let classes = [
+            ("node", declarations(self.nodes.iter().map(|n| &n.labels))),
+            ("edge", declarations(self.links.iter().map(|l| &l.labels))),
+        ];
+        for (class, declared) in &classes {
+            let class_ids = ids.entry(class).or_default();
+            if declared.is_empty() {
+                continue;
+            }
+            gexf.push_str(&format!("    <attributes class=\"{}\">\n", class));
+            for (idx, (title, value_type)) in declared.iter().enumerate() {
+                gexf.push_str(&format!(
+                    "      <attribute id=\"{}\" title=\"{}\" type=\"{}\"/>\n",
+                    idx,
+                    xml::escape(title),
+                    value_type.name()
+                ));
+                class_ids.insert(title, idx);
+            }
+            gexf.push_str("    </attributes>\n");
+        }
+
+        gexf.push_str("    <nodes>\n");
+        for node in &self.nodes {
+            let mut attrs = format!(" id=\"{}\"", node.id);
+            if !node.name.is_empty() {
+                attrs.push_str(&format!(" label=\"{}\"", xml::escape(&node.name)));
+            }
+            let content = attvalues(&node.labels, &ids["node"]);
+            gexf.push_str(&element("node", &attrs, &content));
+        }
+        gexf.push_str("    </nodes>\n    <edges>\n");
+        for link in &self.links {
+            let mut attrs = format!(
+                " id=\"{}\" source=\"{}\" target=\"{}\"",
+                link.id, link.source, link.target
+            );
+            if !link.name.is_empty() {
+                attrs.push_str(&format!(" label=\"{}\"", xml::escape(&link.name)));
+            }
+            let content = attvalues(&link.labels, &ids["edge"]);
+            gexf.push_str(&element("edge", &attrs, &content));
+        }
+        gexf.push_str("    </edges>\n  </graph>\n</gexf>\n");
+        gexf
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::model::digraph::test_util::labels;
+    use crate::model::digraph::{LinkSettings, NodeSettings};
+
+    fn import(source: &str) -> Digraph {
+        Digraph::from_gexf(source).expect("The GEXF to be imported")
+    }
+
+    #[test]
+    fn test_import_exported() {
+        let mut dg = Digraph::new();
+        dg.name = "Round trip".into();
+        dg.labels = labels(&[("owner key", "ops & dev")]);
+        dg.add_node(Some(NodeSettings {
+            name: Some("a\tb\nc".into()),
+            labels: Some(labels(&[("size", "10"), ("kind", "service")])),
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_node(None).expect("The node to be added");
+        dg.add_link(
+            1,
+            2,
+            Some(LinkSettings {
+                name: Some("".into()),
+                labels: Some(labels(&[("weight", "0.5")])),
+                ..LinkSettings::default()
+            }),
+        )
+        .expect("The link to be added");
+
+        let gexf = dg.to_gexf();
+        assert!(gexf.contains("<attribute id=\"1\" title=\"size\" type=\"long\"/>"));
+        assert!(gexf.contains("<node id=\"1\" label=\"a&#9;b&#10;c\">"));
+        assert!(gexf.contains("<edge id=\"3\" source=\"1\" target=\"2\">"));
+        assert!(
+            gexf.contains("<keywords>{&quot;owner key&quot;:&quot;ops &amp; dev&quot;}</keywords>")
+        );
+        assert_eq!(import(&gexf), dg);
+        assert_eq!(import(&Digraph::new().to_gexf()), Digraph::new());
+    }
+
+    #[test]
+    fn test_import_foreign() {
+        let dg = import(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<gexf xmlns="http://www.gexf.net/1.2draft" xmlns:viz="http://www.gexf.net/1.2draft/viz" version="1.2">
+  <meta lastmodifieddate="2020-01-01">
+    <creator>Gephi</creator>
+    <keywords>small, directed</keywords>
+    <description>A &quot;small&quot; graph</description>
+  </meta>
+  <graph defaultedgetype="directed">
+    <attributes class="node">
+      <attribute id="0" title="group" type="string">
+        <default>none</default>
+      </attribute>
+    </attributes>
+    <nodes>
+      <node id="a" label="Alpha">
+        <attvalues><attvalue for="0" value="core"/></attvalues>
+        <viz:color r="255" g="0" b="0"/>
+      </node>
+      <node id="b"/>
+    </nodes>
+    <edges>
+      <edge id="0" source="a" target="b" label="feeds" weight="2"/>
+    </edges>
+  </graph>
+</gexf>"#,
+        );
+        assert_eq!(dg.name, "A \"small\" graph");
+        assert_eq!(dg.labels, labels(&[("keywords", "small, directed")]));
+        let nodes: Vec<(i32, &str, Labels)> = dg
+            .nodes
+            .iter()
+            .map(|n| (n.id, n.name.as_str(), n.labels.clone()))
+            .collect();
+        assert_eq!(
+            nodes,
+            vec![
+                (1, "Alpha", labels(&[("group", "core")])),
+                (2, "b", labels(&[("group", "none")])),
+            ]
+        );
+        assert_eq!(dg.links.len(), 1);
+        assert_eq!((dg.links[0].id, dg.links[0].name.as_str()), (3, "feeds"));
+        assert_eq!((dg.links[0].source, dg.links[0].target), (1, 2));
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let err =
+            Digraph::from_gexf("<gexf>\n  <graph>\n    <nodes><node id=\"1\"/><node id=\"1\"/>")
+                .expect_err("The GEXF to be rejected");
+        assert_eq!((err.line, err.column), (1, 1));
+        assert_eq!(err.message, "The root node was opened but never closed");
+
+        let err = Digraph::from_gexf(
+            "<gexf><graph><nodes>\n<node id=\"1\"/><node id=\"1\"/>\n</nodes></graph></gexf>",
+        )
+        .expect_err("The GEXF to be rejected");
+        assert_eq!((err.line, err.column), (2, 15));
+        assert_eq!(err.message, "Duplicate node id '1'");
+    }
+}
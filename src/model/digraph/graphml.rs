@@ -0,0 +1,407 @@
+//! GraphML, the XML format of yEd, networkx and most graph libraries.
+//!
+//! The names of the digraph, its nodes and its links are written as data
+//! for the `name` key. Their labels are written as data for keys declared
+//! per domain, typed after the values they have. Nodes and links keep
+//! their id, so a written digraph reads back as it was.
+//!
+//! Importing takes the name from the key with id `name`, or else from the
+//! key with `attr.name` `name` or `label`. Every other key becomes a label
+//! named after its `attr.name`, with declared defaults applied. Nodes and
+//! links keep an integer id that is not taken and get a new one
+//! otherwise, and a node without name is then named after its GraphML id.
+//! Data holding markup, such as yEd graphics, is dropped. Nested graphs
+//! and hyperedges are not supported.
+
+use super::xml::{self, declarations, Element, Ids};
+use super::{Digraph, Labels, Link, Node, ParseError};
+use std::collections::HashMap;
+
+/// The key holding names
+pub const NAME_KEY: &str = "name";
+
+/// A `<key>` declaration
+struct Key<'a> {
+    id: &'a str,
+    name: &'a str,
+    domain: &'a str,
+    default: Option<String>,
+}
+
+impl<'a> Key<'a> {
+    fn applies_to(&self, domain: &str) -> bool {
+        self.domain == domain || self.domain == "all"
+    }
+}
+
+fn keys<'a>(root: Element<'a, '_>) -> Result<Vec<Key<'a>>, ParseError> {
+    root.children("key")
+        .map(|key| {
+            let id = key.required("id")?;
+            Ok(Key {
+                id,
+                name: key.attribute("attr.name").unwrap_or(id),
+                domain: key.attribute("for").unwrap_or("all"),
+                default: key.child("default").map(|default| default.text()),
+            })
+        })
+        .collect()
+}
+
+/// The name and labels of `element` in `domain`, from its data and the
+/// defaults of the keys
+fn properties(keys: &[Key], domain: &str, element: Element) -> (Option<String>, Labels) {
+    let keys: Vec<&Key> = keys.iter().filter(|key| key.applies_to(domain)).collect();
+    let name_key = keys
+        .iter()
+        .find(|key| key.id == NAME_KEY)
+        .or_else(|| keys.iter().find(|key| key.name == NAME_KEY))
+        .or_else(|| keys.iter().find(|key| key.name == "label"))
+        .map(|key| key.id);
+
+    let mut values: HashMap<&str, String> = keys
+        .iter()
+        .filter_map(|key| Some((key.id, key.default.clone()?)))
+        .collect();
+    for data in element.children("data") {
+        if let Some(key) = data.attribute("key") {
+            if !data.has_elements() {
+                values.insert(key, data.text());
+            }
+        }
+    }
+
+    let mut name = None;
+    let mut labels = Labels::new();
+    for (id, value) in values {
+        if Some(id) == name_key {
+            name = Some(value);
+        } else {
+            let key = keys.iter().find(|key| key.id == id);
+            labels.insert(key.map_or(id, |key| key.name).to_string(), value);
+        }
+    }
+    (name, labels)
+}
+
+/// `<data>` elements for a name and labels, with label keys by name
+fn data(indent: &str, name: &str, labels: &Labels, keys: &HashMap<&str, String>) -> String {
+    let mut data = String::new();
+    if !name.is_empty() {
+        data.push_str(&format!(
+            "{}<data key=\"{}\">{}</data>\n",
+            indent,
+            NAME_KEY,
+            xml::escape(name)
+        ));
+    }
+    let mut labels: Vec<(&String, &String)> = labels.iter().collect();
+    labels.sort();
+    for (key, value) in labels {
+        data.push_str(&format!(
+            "{}<data key=\"{}\">{}</data>\n",
+            indent,
+            keys[key.as_str()],
+            xml::escape(value)
+        ));
+    }
+    data
+}
+
+impl Digraph {
+    /// Build a digraph from a GraphML document
+    pub fn from_graphml(source: &str) -> Result<Self, ParseError> {
+        let document = xml::parse(source)?;
+        let root = Element::root(&document);
+        if root.name() != "graphml" {
+            return root.error(format!("Expected <graphml>, found <{}>", root.name()));
+        }
+        let keys = keys(root)?;
+        let graph = match root.child("graph") {
+            Some(graph) => graph,
+            None => return root.error("Missing <graph>".into()),
+        };
+        if let Some(hyperedge) = graph.child("hyperedge") {
+            return hyperedge.error("Hyperedges are not supported".into());
+        }
+
+        let mut digraph = Digraph::new();
+        let (name, labels) = properties(&keys, "graph", graph);
+        digraph.name = name.unwrap_or_default();
+        digraph.labels = labels;
+
+        let mut ids = Ids::new(
+            graph
+                .children("node")
+                .chain(graph.children("edge"))
+                .filter_map(|element| element.attribute("id")),
+        );
+        let mut nodes: HashMap<&str, i32> = HashMap::new();
+        for node in graph.children("node") {
+            let graphml_id = node.required("id")?;
+            if let Some(nested) = node.child("graph") {
+                return nested.error("Nested graphs are not supported".into());
+            }
+            if nodes.contains_key(graphml_id) {
+                return node.error(format!("Duplicate node id '{}'", graphml_id));
+            }
+            let (id, kept) = ids.assign(Some(graphml_id));
+            nodes.insert(graphml_id, id);
+            let (name, labels) = properties(&keys, "node", node);
+            let name = match name {
+                Some(name) => name,
+                None if kept => "".into(),
+                None => graphml_id.to_string(),
+            };
+            digraph.nodes.push(Node { id, name, labels });
+        }
+
+        for edge in graph.children("edge") {
+            let mut ends = vec![];
+            for end in &["source", "target"] {
+                let node = edge.required(end)?;
+                match nodes.get(node) {
+                    Some(id) => ends.push(*id),
+                    None => return edge.error(format!("Unknown node '{}'", node)),
+                }
+            }
+            let (id, _) = ids.assign(edge.attribute("id"));
+            let (name, labels) = properties(&keys, "edge", edge);
+            digraph.links.push(Link {
+                id,
+                name: name.unwrap_or_default(),
+                source: ends[0],
+                target: ends[1],
+                labels,
+            });
+        }
+        Ok(digraph)
+    }
+
+    /// Render this digraph as a GraphML document
+    pub fn to_graphml(&self) -> String {
+        let mut graphml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        ));
+        graphml.push_str(&format!(
+            "  <key id=\"{}\" for=\"all\" attr.name=\"name\" attr.type=\"string\"/>\n",
+            NAME_KEY
+        ));
+
+        let domains = [
+            ("graph", "g", declarations(std::iter::once(&self.labels))),
+            (
+                "node",
+                "n",
+                declarations(self.nodes.iter().map(|n| &n.labels)),
+            ),
+            (
+                "edge",
+                "e",
+                declarations(self.links.iter().map(|l| &l.labels)),
+            ),
+        ];
+        let mut keys: HashMap<&str, HashMap<&str, String>> = HashMap::new();
+        for (domain, prefix, declared) in &domains {
+            let ids = keys.entry(domain).or_default();
+            for (idx, (name, value_type)) in declared.iter().enumerate() {
+                let id = format!("{}{}", prefix, idx);
+                graphml.push_str(&format!(
+                    "  <key id=\"{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>\n",
+                    id,
+                    domain,
+                    xml::escape(name),
+                    value_type.name()
+                ));
+                ids.insert(name, id);
+            }
+        }
+
+        graphml.push_str("  <graph id=\"G\" edgedefault=\"directed\">\n");
+        graphml.push_str(&data("    ", &self.name, &self.labels, &keys["graph"]));
+        for node in &self.nodes {
+            let data = data("      ", &node.name, &node.labels, &keys["node"]);
+            if data.is_empty() {
+                graphml.push_str(&format!("    <node id=\"{}\"/>\n", node.id));
+            } else {
+                graphml.push_str(&format!(
+                    "    <node id=\"{}\">\n{}    </node>\n",
+                    node.id, data
+                ));
+            }
+        }
+        for link in &self.links {
+            let element = format!(
+                "<edge id=\"{}\" source=\"{}\" target=\"{}\"",
+                link.id, link.source, link.target
+            );
+            let data = data("      ", &link.name, &link.labels, &keys["edge"]);
+            if data.is_empty() {
+                graphml.push_str(&format!("    {}/>\n", element));
+            } else {
+                graphml.push_str(&format!("    {}>\n{}    </edge>\n", element, data));
+            }
+        }
+        graphml.push_str("  </graph>\n</graphml>\n");
+        graphml
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::model::digraph::test_util::labels;
+    use crate::model::digraph::{LinkSettings, NodeSettings};
+
+    fn import(source: &str) -> Digraph {
+        Digraph::from_graphml(source).expect("The GraphML to be imported")
+    }
+
+    fn error(source: &str) -> (usize, usize, String) {
+        let err = Digraph::from_graphml(source).expect_err("The GraphML to be rejected");
+        (err.line, err.column, err.message)
+    }
+
+    fn sample() -> Digraph {
+        let mut dg = Digraph::new();
+        dg.name = "Round <trip> & back".into();
+        dg.labels = labels(&[("rankdir", "LR")]);
+        dg.add_node(Some(NodeSettings {
+            name: Some("a \"b\"\nc".into()),
+            labels: Some(labels(&[("name", "shadowed"), ("weight", "2")])),
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_node(Some(NodeSettings {
+            name: Some("".into()),
+            labels: Some(labels(&[("weight", "2.5"), ("done", "true")])),
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_link(
+            1,
+            2,
+            Some(LinkSettings {
+                name: Some("uses".into()),
+                labels: Some(labels(&[("style", "dashed")])),
+                ..LinkSettings::default()
+            }),
+        )
+        .expect("The link to be added");
+        dg.remove_node(1).expect("The node to be removed");
+        dg.add_node(None).expect("The node to be added");
+        dg.add_link(2, 3, None).expect("The link to be added");
+        dg
+    }
+
+    #[test]
+    fn test_digraph_to_graphml() {
+        let graphml = sample().to_graphml();
+        assert!(graphml
+            .contains("<key id=\"n1\" for=\"node\" attr.name=\"weight\" attr.type=\"double\"/>"));
+        assert!(graphml
+            .contains("<key id=\"n0\" for=\"node\" attr.name=\"done\" attr.type=\"boolean\"/>"));
+        assert!(graphml.contains("<data key=\"name\">Round &lt;trip&gt; &amp; back</data>"));
+        assert!(graphml.contains("<node id=\"3\"/>"));
+        assert!(graphml.contains("<edge id=\"4\" source=\"2\" target=\"3\">"));
+    }
+
+    #[test]
+    fn test_import_exported() {
+        let dg = sample();
+        assert_eq!(import(&dg.to_graphml()), dg);
+        assert_eq!(import(&Digraph::new().to_graphml()), Digraph::new());
+    }
+
+    #[test]
+    fn test_import_foreign() {
+        let dg = import(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- written by another tool -->
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns" xmlns:y="http://www.yworks.com/xml/graphml">
+  <key id="d0" for="node" attr.name="label" attr.type="string"/>
+  <key id="d1" for="node" attr.name="color" attr.type="string">
+    <default>yellow</default>
+  </key>
+  <key id="d2" for="edge" attr.name="weight" attr.type="double"/>
+  <key id="d3" for="node" yfiles.type="nodegraphics"/>
+  <graph id="G" edgedefault="directed">
+    <node id="n0">
+      <data key="d0">Start</data>
+      <data key="d3"><y:ShapeNode/></data>
+    </node>
+    <node id="n1"><data key="d1"><![CDATA[blue & <green>]]></data></node>
+    <node id="2"/>
+    <edge source="n0" target="n1"><data key="d2">1.5</data></edge>
+    <edge id="2" source="n1" target="2"/>
+  </graph>
+</graphml>"#,
+        );
+        let nodes: Vec<(i32, &str, Labels)> = dg
+            .nodes
+            .iter()
+            .map(|n| (n.id, n.name.as_str(), n.labels.clone()))
+            .collect();
+        assert_eq!(
+            nodes,
+            vec![
+                (3, "Start", labels(&[("color", "yellow")])),
+                (4, "n1", labels(&[("color", "blue & <green>")])),
+                (2, "", labels(&[("color", "yellow")])),
+            ]
+        );
+        let links: Vec<(i32, i32, i32, Labels)> = dg
+            .links
+            .iter()
+            .map(|l| (l.id, l.source, l.target, l.labels.clone()))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                (5, 3, 4, labels(&[("weight", "1.5")])),
+                (6, 4, 2, Labels::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_errors() {
+        assert_eq!(
+            error("<graph/>"),
+            (1, 1, "Expected <graphml>, found <graph>".into())
+        );
+        assert_eq!(
+            error("<graphml>\n  <graph>\n    <node/>\n  </graph>\n</graphml>"),
+            (3, 5, "Missing attribute 'id' on <node>".into())
+        );
+        assert_eq!(
+            error("<graphml><graph><node id=\"a\"/>\n<edge source=\"a\" target=\"b\"/></graph></graphml>"),
+            (2, 1, "Unknown node 'b'".into())
+        );
+        assert_eq!(
+            error("<graphml>\n  <graph>\n  </graf>\n</graphml>"),
+            (3, 3, "Expected 'graph' tag, not 'graf'".into())
+        );
+        assert_eq!(
+            error("<graphml>\n  <graph id=\"&nbsp;\"/>\n</graphml>"),
+            (2, 14, "Unknown entity reference 'nbsp'".into())
+        );
+        assert_eq!(
+            error("<graphml>\n  <graph>"),
+            (1, 1, "The root node was opened but never closed".into())
+        );
+    }
+
+    #[test]
+    fn test_import_deep_nesting() {
+        let depth = 200_000;
+        let dg = import(&format!(
+            "<graphml><graph><node id=\"a\">{}{}</node></graph></graphml>",
+            "<data>".repeat(depth),
+            "</data>".repeat(depth)
+        ));
+        assert_eq!(dg.nodes.len(), 1);
+    }
+}
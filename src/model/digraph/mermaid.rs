@@ -0,0 +1,170 @@
+//! Mermaid flowcharts, which render in the Markdown of GitHub, GitLab and
+//! most documentation sites.
+//!
+//! Nodes are written as `n<id>` and shown with their name, or with their
+//! id when they have none. The `shape` label of a node picks its shape
+//! from the Graphviz shape names and a few Mermaid ones, and the `style`
+//! label of a link picks a `dashed`, `dotted` or `bold` line. The name of
+//! the digraph becomes the title, in YAML frontmatter where Mermaid entities
+//! are shown as they are.
+
+use super::{DiagramDirection, Digraph, Link, Node, SHAPE_LABEL, STYLE_LABEL};
+
+/// `text` as quoted Mermaid text, for the names of nodes and links
+fn text(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('"', "#quot;")
+            .replace('\r', "")
+            .replace('\n', "<br>")
+    )
+}
+
+/// `text` as a YAML double-quoted string, for the title
+fn yaml(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The brackets around the text of a node with `shape`
+fn brackets(shape: Option<&str>) -> (&'static str, &'static str) {
+    match shape.unwrap_or_default() {
+        "rounded" | "ellipse" | "oval" => ("(", ")"),
+        "stadium" => ("([", "])"),
+        "circle" | "doublecircle" => ("((", "))"),
+        "diamond" | "rhombus" => ("{", "}"),
+        "hexagon" => ("{{", "}}"),
+        "cylinder" | "database" => ("[(", ")]"),
+        "subroutine" => ("[[", "]]"),
+        "parallelogram" => ("[/", "/]"),
+        _ => ("[", "]"),
+    }
+}
+
+fn node(node: &Node) -> String {
+    let (open, close) = brackets(node.labels.get(SHAPE_LABEL).map(String::as_str));
+    let name = if node.name.is_empty() {
+        node.id.to_string()
+    } else {
+        node.name.clone()
+    };
+    format!("n{}{}{}{}", node.id, open, text(&name), close)
+}
+
+fn link(link: &Link) -> String {
+    let arrow = match link.labels.get(STYLE_LABEL).map(String::as_str) {
+        Some("dashed") | Some("dotted") => "-.->",
+        Some("bold") => "==>",
+        _ => "-->",
+    };
+    if link.name.is_empty() {
+        format!("n{} {} n{}", link.source, arrow, link.target)
+    } else {
+        format!(
+            "n{} {}|{}| n{}",
+            link.source,
+            arrow,
+            text(&link.name),
+            link.target
+        )
+    }
+}
+
+impl Digraph {
+    /// Render this digraph as a Mermaid flowchart
+    pub fn to_mermaid(&self, direction: DiagramDirection) -> String {
+        let mut mermaid = String::new();
+        if !self.name.is_empty() {
+            mermaid.push_str(&format!("---\ntitle: {}\n---\n", yaml(&self.name)));
+        }
+        mermaid.push_str(&format!("flowchart {}\n", direction.code()));
+        for node in &self.nodes {
+            mermaid.push_str(&format!("    {}\n", self::node(node)));
+        }
+        for link in &self.links {
+            mermaid.push_str(&format!("    {}\n", self::link(link)));
+        }
+        mermaid
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::model::digraph::test_util::labels;
+    use crate::model::digraph::{LinkSettings, NodeSettings};
+
+    #[test]
+    fn test_digraph_to_mermaid() {
+        let mut dg = Digraph::new();
+        assert_eq!(dg.to_mermaid(DiagramDirection::default()), "flowchart TB\n");
+
+        dg.name = "Data \"flow\" \\ #quot;\n2".into();
+        dg.add_node(Some(NodeSettings {
+            name: Some("Web\napp".into()),
+            labels: None,
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_node(Some(NodeSettings {
+            name: Some("".into()),
+            labels: Some(labels(&[(SHAPE_LABEL, "cylinder")])),
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_node(Some(NodeSettings {
+            name: Some("ok?".into()),
+            labels: Some(labels(&[(SHAPE_LABEL, "diamond")])),
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_link(
+            1,
+            2,
+            Some(LinkSettings {
+                name: Some("reads".into()),
+                labels: Some(labels(&[(STYLE_LABEL, "dashed")])),
+                ..LinkSettings::default()
+            }),
+        )
+        .expect("The link to be added");
+        dg.add_link(
+            1,
+            3,
+            Some(LinkSettings {
+                name: Some("".into()),
+                labels: Some(labels(&[(STYLE_LABEL, "bold")])),
+                ..LinkSettings::default()
+            }),
+        )
+        .expect("The link to be added");
+
+        assert_eq!(
+            dg.to_mermaid(DiagramDirection::LeftRight),
+            concat!(
+                "---\n",
+                "title: \"Data \\\"flow\\\" \\\\ #quot;\\n2\"\n",
+                "---\n",
+                "flowchart LR\n",
+                "    n1[\"Web<br>app\"]\n",
+                "    n2[(\"2\")]\n",
+                "    n3{\"ok?\"}\n",
+                "    n1 -.->|\"reads\"| n2\n",
+                "    n1 ==> n3\n",
+            )
+        );
+    }
+}
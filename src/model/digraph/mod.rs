@@ -0,0 +1,900 @@
+use constraints::{Constraints, Degree, Violation};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::{error, fmt};
+
+pub mod algo;
+pub mod batch;
+pub mod constraints;
+pub mod dot;
+pub mod gexf;
+pub mod graphml;
+pub mod mermaid;
+pub mod plantuml;
+#[cfg(test)]
+pub(crate) mod test_util;
+mod xml;
+
+type Labels = HashMap<String, String>;
+
+/// How deep imported digraphs may nest subgraphs or elements, so that
+/// parsing them cannot overflow the stack
+const MAX_NESTING: usize = 256;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub enum DigraphError {
+    IdDoesNotExist(i32),
+    /// The nodes of a cycle where none may be
+    Cycle(Vec<i32>),
+    /// A link with a weight that is not a non-negative number
+    InvalidWeight(i32, String),
+    /// An operation that would break the constraints of the digraph
+    ConstraintViolation(Violation),
+    /// A temp id no earlier message of the batch was given
+    TempIdDoesNotExist(String),
+    /// A temp id an earlier message of the batch was given already
+    TempIdAlreadyUsed(String),
+    /// A degree limit below zero, which no node could keep
+    NegativeDegreeLimit(Degree, i32),
+}
+
+impl fmt::Display for DigraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DigraphError::IdDoesNotExist(e) => write!(f, "id does not exist : {}", e),
+            DigraphError::Cycle(nodes) => {
+                let nodes: Vec<String> = nodes.iter().map(i32::to_string).collect();
+                write!(f, "cycle through nodes : {}", nodes.join(", "))
+            }
+            DigraphError::InvalidWeight(id, weight) => {
+                write!(f, "invalid weight on link {} : {}", id, weight)
+            }
+            DigraphError::ConstraintViolation(violation) => {
+                write!(f, "constraint violated : {}", violation)
+            }
+            DigraphError::TempIdDoesNotExist(temp) => {
+                write!(f, "temp id does not exist : {}", temp)
+            }
+            DigraphError::TempIdAlreadyUsed(temp) => write!(f, "temp id already used : {}", temp),
+            DigraphError::NegativeDegreeLimit(degree, max) => {
+                write!(f, "negative limit on {} : {}", degree, max)
+            }
+        }
+    }
+}
+
+impl error::Error for DigraphError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// A syntax error in an imported digraph, at a line and column counted
+/// from 1
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// The formats a digraph can be imported from and exported to
+#[derive(async_graphql::Enum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DigraphFormat {
+    Dot,
+    #[graphql(name = "GRAPHML")]
+    GraphMl,
+    Gexf,
+}
+
+impl FromStr for DigraphFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "dot" => Ok(DigraphFormat::Dot),
+            "graphml" => Ok(DigraphFormat::GraphMl),
+            "gexf" => Ok(DigraphFormat::Gexf),
+            _ => Err(format!("Unknown digraph format {}", format)),
+        }
+    }
+}
+
+/// The node label choosing the shape of a node in a diagram
+pub const SHAPE_LABEL: &str = "shape";
+
+/// The link label choosing the line style of a link in a diagram
+pub const STYLE_LABEL: &str = "style";
+
+/// The diagram languages a digraph can be rendered in for documentation
+#[derive(async_graphql::Enum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagramFormat {
+    Mermaid,
+    #[graphql(name = "PLANTUML")]
+    PlantUml,
+}
+
+impl FromStr for DiagramFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "mermaid" => Ok(DiagramFormat::Mermaid),
+            "plantuml" => Ok(DiagramFormat::PlantUml),
+            _ => Err(format!("Unknown diagram format {}", format)),
+        }
+    }
+}
+
+/// The direction links point to in a diagram
+#[derive(async_graphql::Enum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagramDirection {
+    TopDown,
+    BottomUp,
+    LeftRight,
+    RightLeft,
+}
+
+impl Default for DiagramDirection {
+    fn default() -> Self {
+        Self::TopDown
+    }
+}
+
+impl DiagramDirection {
+    /// The direction as Graphviz `rankdir`, which Mermaid uses as well
+    pub fn code(self) -> &'static str {
+        match self {
+            DiagramDirection::TopDown => "TB",
+            DiagramDirection::BottomUp => "BT",
+            DiagramDirection::LeftRight => "LR",
+            DiagramDirection::RightLeft => "RL",
+        }
+    }
+}
+
+impl FromStr for DiagramDirection {
+    type Err = String;
+
+    fn from_str(direction: &str) -> Result<Self, Self::Err> {
+        match direction.to_ascii_uppercase().as_str() {
+            "TB" | "TD" => Ok(DiagramDirection::TopDown),
+            "BT" => Ok(DiagramDirection::BottomUp),
+            "LR" => Ok(DiagramDirection::LeftRight),
+            "RL" => Ok(DiagramDirection::RightLeft),
+            _ => Err(format!("Unknown diagram direction {}", direction)),
+        }
+    }
+}
+
+/// A node or link, by id or, within a batch, by the temp id it was added
+/// with
+#[derive(async_graphql::OneofObject, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum IdRef {
+    Id(i32),
+    Temp(String),
+}
+
+impl IdRef {
+    /// The id, failing for a temp id that was not resolved by a batch
+    fn id(self) -> Result<i32, DigraphError> {
+        match self {
+            IdRef::Id(id) => Ok(id),
+            IdRef::Temp(temp) => Err(DigraphError::TempIdDoesNotExist(temp)),
+        }
+    }
+}
+
+impl From<i32> for IdRef {
+    fn from(id: i32) -> Self {
+        IdRef::Id(id)
+    }
+}
+
+#[derive(async_graphql::InputObject, Serialize, Deserialize, Debug, PartialEq)]
+pub struct NodeUpdate {
+    pub id: IdRef,
+    pub attrs: Option<NodeSettings>,
+}
+
+#[derive(async_graphql::InputObject, Serialize, Deserialize, Debug, PartialEq)]
+pub struct NodeRemoval {
+    pub id: IdRef,
+}
+
+#[derive(async_graphql::InputObject, Serialize, Deserialize, Debug, PartialEq)]
+pub struct NewLink {
+    pub source: IdRef,
+    pub target: IdRef,
+    pub attrs: Option<LinkSettings>,
+}
+
+#[derive(async_graphql::InputObject, Serialize, Deserialize, Debug, PartialEq)]
+pub struct LinkUpdate {
+    pub id: IdRef,
+    pub attrs: Option<LinkSettings>,
+}
+
+#[derive(async_graphql::InputObject, Serialize, Deserialize, Debug, PartialEq)]
+pub struct LinkRemoval {
+    pub id: IdRef,
+}
+
+#[derive(async_graphql::OneofObject, Serialize, Deserialize, Debug, PartialEq)]
+pub enum DigraphMessage {
+    AddNode(NodeSettings),
+    UpdateNode(NodeUpdate),
+    RemoveNode(NodeRemoval),
+    AddLink(NewLink),
+    UpdateLink(LinkUpdate),
+    RemoveLink(LinkRemoval),
+    SetConstraints(Constraints),
+    SetName(String),
+    UpdateLabels(LabelSettings),
+}
+
+impl DigraphMessage {
+    /// The nodes and links the message refers to
+    pub fn ids_mut(&mut self) -> Vec<&mut IdRef> {
+        match self {
+            DigraphMessage::UpdateNode(NodeUpdate { id, .. })
+            | DigraphMessage::RemoveNode(NodeRemoval { id })
+            | DigraphMessage::UpdateLink(LinkUpdate { id, .. })
+            | DigraphMessage::RemoveLink(LinkRemoval { id }) => vec![id],
+            DigraphMessage::AddLink(NewLink { source, target, .. }) => vec![source, target],
+            DigraphMessage::AddNode(_)
+            | DigraphMessage::SetConstraints(_)
+            | DigraphMessage::SetName(_)
+            | DigraphMessage::UpdateLabels(_) => vec![],
+        }
+    }
+}
+
+#[derive(async_graphql::SimpleObject, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[graphql(complex)]
+pub struct Digraph {
+    pub name: String,
+    pub nodes: Vec<Node>,
+    pub links: Vec<Link>,
+    pub labels: Labels,
+    /// Rules links have to keep, see `constraints`
+    #[serde(default, skip_serializing_if = "Constraints::is_empty")]
+    pub constraints: Constraints,
+}
+
+#[derive(async_graphql::SimpleObject, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Node {
+    pub id: i32,
+    pub name: String,
+    pub labels: Labels,
+}
+
+#[derive(async_graphql::InputObject, Serialize, Deserialize, Debug, PartialEq)]
+pub struct NodeSettings {
+    pub name: Option<String>,
+    pub labels: Option<Labels>,
+    /// Keys to remove from the labels, after `labels` are set
+    #[serde(default)]
+    pub remove_labels: Option<Vec<String>>,
+    /// Replace all labels with `labels` instead of merging them in
+    #[serde(default)]
+    pub replace_labels: Option<bool>,
+}
+
+impl Node {
+    pub fn update(&mut self, attrs: NodeSettings) {
+        if let Some(name) = attrs.name {
+            self.name = name;
+        }
+
+        update_labels(
+            &mut self.labels,
+            attrs.labels,
+            attrs.remove_labels,
+            attrs.replace_labels,
+        );
+    }
+}
+
+impl Default for NodeSettings {
+    fn default() -> Self {
+        Self {
+            name: Some("".to_string()),
+            labels: Some(Labels::new()),
+            remove_labels: None,
+            replace_labels: None,
+        }
+    }
+}
+
+#[derive(async_graphql::SimpleObject, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Link {
+    pub id: i32,
+    pub name: String,
+    pub source: i32,
+    pub target: i32,
+    pub labels: Labels,
+}
+
+#[derive(async_graphql::InputObject, Serialize, Deserialize, Debug, PartialEq)]
+pub struct LinkSettings {
+    pub name: Option<String>,
+    pub labels: Option<Labels>,
+    /// Keys to remove from the labels, after `labels` are set
+    #[serde(default)]
+    pub remove_labels: Option<Vec<String>>,
+    /// Replace all labels with `labels` instead of merging them in
+    #[serde(default)]
+    pub replace_labels: Option<bool>,
+}
+
+impl Default for LinkSettings {
+    fn default() -> Self {
+        Self {
+            name: Some("name".into()),
+            labels: Some(Labels::new()),
+            remove_labels: None,
+            replace_labels: None,
+        }
+    }
+}
+
+impl Link {
+    pub fn update(&mut self, attrs: LinkSettings) {
+        if let Some(name) = attrs.name {
+            self.name = name;
+        }
+
+        update_labels(
+            &mut self.labels,
+            attrs.labels,
+            attrs.remove_labels,
+            attrs.replace_labels,
+        );
+    }
+}
+
+/// Changes to the labels of the digraph itself
+#[derive(async_graphql::InputObject, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct LabelSettings {
+    pub labels: Option<Labels>,
+    /// Keys to remove from the labels, after `labels` are set
+    #[serde(default)]
+    pub remove_labels: Option<Vec<String>>,
+    /// Replace all labels with `labels` instead of merging them in
+    #[serde(default)]
+    pub replace_labels: Option<bool>,
+}
+
+/// Merge `labels` into `target`, or make them its only labels when
+/// `replace` is set, then remove the `remove` keys. Replacing without
+/// `labels` clears `target`.
+fn update_labels(
+    target: &mut Labels,
+    labels: Option<Labels>,
+    remove: Option<Vec<String>>,
+    replace: Option<bool>,
+) {
+    if replace.unwrap_or_default() {
+        *target = labels.unwrap_or_default();
+    } else if let Some(labels) = labels {
+        target.extend(labels);
+    }
+
+    for key in remove.unwrap_or_default() {
+        let _ = target.remove(&key);
+    }
+}
+
+impl Default for Digraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Digraph {
+    pub fn new() -> Self {
+        Self {
+            name: "".into(),
+            nodes: Vec::<Node>::new(),
+            links: Vec::<Link>::new(),
+            labels: Labels::new(),
+            constraints: Constraints::default(),
+        }
+    }
+
+    fn node_ids(&self) -> Vec<i32> {
+        let ids: Vec<i32> = self.nodes.iter().map(|e| e.id).collect();
+        ids
+    }
+
+    fn link_ids(&self) -> Vec<i32> {
+        let ids: Vec<i32> = self.links.iter().map(|e| e.id).collect();
+        ids
+    }
+
+    fn all_ids(&self) -> Vec<i32> {
+        let mut ids = self.node_ids();
+        ids.append(&mut self.link_ids());
+        ids
+    }
+
+    fn highest_id(&self) -> i32 {
+        if let Some(id) = self.all_ids().iter().max() {
+            *id
+        } else {
+            0
+        }
+    }
+
+    fn next_id(&self) -> i32 {
+        self.highest_id() + 1
+    }
+
+    /// Add a node and return its id
+    pub fn add_node(&mut self, attrs: Option<NodeSettings>) -> Result<i32, DigraphError> {
+        let attrs = attrs.unwrap_or_default();
+        let id = self.next_id();
+
+        self.nodes.push(Node {
+            id,
+            name: attrs.name.unwrap_or_else(|| "".into()),
+            labels: attrs.labels.unwrap_or_default(),
+        });
+        Ok(id)
+    }
+
+    /// Update a node, leaving it as it is without `attrs`
+    pub fn update_node(
+        &mut self,
+        id: i32,
+        attrs: Option<NodeSettings>,
+    ) -> Result<(), DigraphError> {
+        if let Some(pos) = self.nodes.iter().position(|e| e.id == id) {
+            let node = self
+                .nodes
+                .get_mut(pos)
+                .expect("Node to exist at this position");
+            if let Some(attrs) = attrs {
+                node.update(attrs);
+            }
+            Ok(())
+        } else {
+            Err(DigraphError::IdDoesNotExist(id))
+        }
+    }
+
+    /// Remove a node along with its links and return the ids of those links
+    pub fn remove_node(&mut self, id: i32) -> Result<Vec<i32>, DigraphError> {
+        if let Some(pos) = self.nodes.iter().position(|e| e.id == id) {
+            self.nodes.remove(pos);
+            let links = self
+                .links
+                .iter()
+                .filter(|e| e.source == id || e.target == id)
+                .map(|e| e.id)
+                .collect();
+            self.links.retain(|e| (e.source != id) && (e.target != id));
+            Ok(links)
+        } else {
+            Err(DigraphError::IdDoesNotExist(id))
+        }
+    }
+
+    /// Add a link from `source` to `target` and return its id
+    pub fn add_link(
+        &mut self,
+        source: i32,
+        target: i32,
+        attrs: Option<LinkSettings>,
+    ) -> Result<i32, DigraphError> {
+        let ids = self.node_ids();
+        let attrs = attrs.unwrap_or_default();
+
+        if !ids.contains(&source) {
+            Err(DigraphError::IdDoesNotExist(source))
+        } else if !ids.contains(&target) {
+            Err(DigraphError::IdDoesNotExist(target))
+        } else {
+            self.check_link(source, target)?;
+            let id = self.next_id();
+            self.links.push(Link {
+                id,
+                name: attrs.name.unwrap_or_else(|| "link".into()),
+                source,
+                target,
+                labels: attrs.labels.unwrap_or_default(),
+            });
+            Ok(id)
+        }
+    }
+
+    /// Update a link, leaving it as it is without `attrs`
+    pub fn update_link(
+        &mut self,
+        id: i32,
+        attrs: Option<LinkSettings>,
+    ) -> Result<(), DigraphError> {
+        if let Some(pos) = self.links.iter().position(|e| e.id == id) {
+            let link = self
+                .links
+                .get_mut(pos)
+                .expect("Link to exist at this position");
+            if let Some(attrs) = attrs {
+                link.update(attrs);
+            }
+            Ok(())
+        } else {
+            Err(DigraphError::IdDoesNotExist(id))
+        }
+    }
+
+    pub fn remove_link(&mut self, id: i32) -> Result<(), DigraphError> {
+        if let Some(pos) = self.links.iter().position(|e| e.id == id) {
+            self.links.remove(pos);
+            Ok(())
+        } else {
+            Err(DigraphError::IdDoesNotExist(id))
+        }
+    }
+
+    pub fn set_name(&mut self, name: String) -> Result<(), DigraphError> {
+        self.name = name;
+        Ok(())
+    }
+
+    pub fn update_labels(&mut self, attrs: LabelSettings) -> Result<(), DigraphError> {
+        update_labels(
+            &mut self.labels,
+            attrs.labels,
+            attrs.remove_labels,
+            attrs.replace_labels,
+        );
+        Ok(())
+    }
+
+    pub fn import(format: DigraphFormat, source: &str) -> Result<Self, ParseError> {
+        match format {
+            DigraphFormat::Dot => Self::from_dot(source),
+            DigraphFormat::GraphMl => Self::from_graphml(source),
+            DigraphFormat::Gexf => Self::from_gexf(source),
+        }
+    }
+
+    pub fn export(&self, format: DigraphFormat) -> String {
+        match format {
+            DigraphFormat::Dot => self.to_dot(),
+            DigraphFormat::GraphMl => self.to_graphml(),
+            DigraphFormat::Gexf => self.to_gexf(),
+        }
+    }
+
+    pub fn diagram(&self, format: DiagramFormat, direction: DiagramDirection) -> String {
+        match format {
+            DiagramFormat::Mermaid => self.to_mermaid(direction),
+            DiagramFormat::PlantUml => self.to_plantuml(direction),
+        }
+    }
+
+    /// Apply `msg` and return the id of the node or link it added
+    pub fn message(&mut self, msg: DigraphMessage) -> Result<Option<i32>, DigraphError> {
+        match msg {
+            DigraphMessage::AddNode(attrs) => self.add_node(Some(attrs)).map(Some),
+            DigraphMessage::UpdateNode(NodeUpdate { id, attrs }) => {
+                self.update_node(id.id()?, attrs).map(|()| None)
+            }
+            DigraphMessage::RemoveNode(NodeRemoval { id }) => {
+                self.remove_node(id.id()?).map(|_| None)
+            }
+            DigraphMessage::AddLink(NewLink {
+                source,
+                target,
+                attrs,
+            }) => self.add_link(source.id()?, target.id()?, attrs).map(Some),
+            DigraphMessage::UpdateLink(LinkUpdate { id, attrs }) => {
+                self.update_link(id.id()?, attrs).map(|()| None)
+            }
+            DigraphMessage::RemoveLink(LinkRemoval { id }) => {
+                self.remove_link(id.id()?).map(|()| None)
+            }
+            DigraphMessage::SetConstraints(constraints) => {
+                self.set_constraints(constraints).map(|()| None)
+            }
+            DigraphMessage::SetName(name) => self.set_name(name).map(|()| None),
+            DigraphMessage::UpdateLabels(attrs) => self.update_labels(attrs).map(|()| None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::test_util::labels;
+    use super::*;
+
+    #[test]
+    fn test_add_node() {
+        let mut dg = Digraph::new();
+        let _ = dg.add_node(None);
+        assert_eq!(dg.nodes.len(), 1);
+    }
+
+    #[test]
+    fn test_add_multiple_nodes() {
+        let mut dg = Digraph::new();
+        let _ = dg.add_node(None);
+        let _ = dg.add_node(None);
+        assert_eq!(dg.add_node(None), Ok(3));
+        assert_eq!(dg.nodes.len(), 3);
+        assert_eq!(dg.nodes.first().unwrap().id, 1);
+        assert_eq!(dg.nodes.get(2).unwrap().id, 3);
+    }
+
+    #[test]
+    fn test_add_link() {
+        let mut dg = Digraph::new();
+        let _ = dg.add_node(None);
+        let _ = dg.add_node(None);
+        assert_eq!(dg.add_link(1, 2, None), Ok(3));
+        assert_eq!(dg.links.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_node() {
+        let mut dg = Digraph::new();
+        let _ = dg.add_node(None);
+        assert_eq!(dg.nodes.len(), 1);
+
+        let _ = dg.remove_node(1);
+        assert_eq!(dg.nodes.len(), 0);
+    }
+
+    #[test]
+    fn test_id_generation() {
+        let mut dg = Digraph::new();
+        let _ = dg.add_node(None);
+        let _ = dg.add_node(None);
+        let _ = dg.add_node(None);
+
+        assert_eq!(dg.nodes[0].id, 1);
+        assert_eq!(dg.nodes[1].id, 2);
+        assert_eq!(dg.nodes[2].id, 3);
+
+        let _ = dg.remove_node(2);
+        assert_eq!(dg.nodes.len(), 2);
+
+        let _ = dg.add_node(None);
+
+        assert_eq!(dg.nodes[0].id, 1);
+        assert_eq!(dg.nodes[1].id, 3);
+        assert_eq!(dg.nodes[2].id, 4);
+    }
+
+    #[test]
+    fn test_remove_node_that_does_not_exist() {
+        let mut dg = Digraph::new();
+        let res = dg.remove_node(1);
+        assert_eq!(res, Err(DigraphError::IdDoesNotExist(1)));
+    }
+
+    #[test]
+    fn test_remove_link() {
+        let mut dg = Digraph::new();
+        let _ = dg.add_node(None);
+        let _ = dg.add_node(None);
+        let _ = dg.add_link(1, 2, None);
+        assert_eq!(dg.links.len(), 1);
+        let res = dg.remove_link(3);
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn test_remove_link_that_does_not_exist() {
+        let mut dg = Digraph::new();
+        let res = dg.remove_link(1);
+        assert_eq!(res, Err(DigraphError::IdDoesNotExist(1)));
+    }
+
+    #[test]
+    fn test_remove_node_with_links() {
+        let mut dg = Digraph::new();
+        let _ = dg.add_node(None);
+        let _ = dg.add_node(None);
+        let _ = dg.add_link(1, 2, None);
+        assert_eq!(dg.links.len(), 1);
+
+        assert_eq!(dg.remove_node(1), Ok(vec![3]));
+        assert_eq!(dg.links.len(), 0);
+        assert_eq!(dg.nodes.len(), 1);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut dg = Digraph::new();
+        let _ = dg.add_node(None);
+        let _ = dg.add_node(None);
+        let _ = dg.add_link(1, 2, None);
+        assert_eq!(dg.links.len(), 1);
+
+        assert_eq!(
+            dg,
+            serde_json::from_str(&serde_json::to_string(&dg).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_message_add_node() {
+        let mut dg = Digraph::new();
+        let outcome = dg
+            .message(DigraphMessage::AddNode(NodeSettings::default()))
+            .expect("Can send a message");
+        assert_eq!(outcome, Some(1));
+        assert_eq!(dg.nodes.len(), 1);
+    }
+
+    #[test]
+    fn test_message_update_node() {
+        let mut dg = Digraph::new();
+        dg.message(DigraphMessage::AddNode(NodeSettings::default()))
+            .expect("Can send a message");
+        dg.message(DigraphMessage::UpdateNode(NodeUpdate {
+            id: 1.into(),
+            attrs: Some(NodeSettings {
+                name: Some("Test 1".into()),
+                labels: None,
+                ..NodeSettings::default()
+            }),
+        }))
+        .expect("Can send a message");
+        assert_eq!(dg.nodes.len(), 1);
+        assert_eq!(dg.nodes.first().unwrap().name, "Test 1".to_string());
+    }
+
+    #[test]
+    fn test_message_remove_node() {
+        let mut dg = Digraph::new();
+        dg.message(DigraphMessage::AddNode(NodeSettings::default()))
+            .expect("Can add a node via message");
+        dg.message(DigraphMessage::AddNode(NodeSettings::default()))
+            .expect("Can add a node via message");
+        dg.message(DigraphMessage::AddLink(NewLink {
+            source: 1.into(),
+            target: 2.into(),
+            attrs: None,
+        }))
+        .expect("Can add a link via message");
+        let outcome = dg
+            .message(DigraphMessage::RemoveNode(NodeRemoval { id: 1.into() }))
+            .expect("Can remove a node via message");
+        assert_eq!(outcome, None);
+        assert_eq!(dg.nodes.len(), 1);
+        assert!(dg.links.is_empty());
+        assert_eq!(
+            dg.message(DigraphMessage::RemoveNode(NodeRemoval {
+                id: IdRef::Temp("a".into())
+            })),
+            Err(DigraphError::TempIdDoesNotExist("a".into()))
+        );
+    }
+
+    #[test]
+    fn test_message_add_link() {
+        let mut dg = Digraph::new();
+        dg.message(DigraphMessage::AddNode(NodeSettings::default()))
+            .expect("Can add a node via message");
+        dg.message(DigraphMessage::AddNode(NodeSettings::default()))
+            .expect("Can add a node via message");
+        assert_eq!(dg.nodes.len(), 2);
+        let outcome = dg
+            .message(DigraphMessage::AddLink(NewLink {
+                source: 1.into(),
+                target: 2.into(),
+                attrs: None,
+            }))
+            .expect("Can add a link via message");
+        assert_eq!(outcome, Some(3));
+        assert_eq!(dg.links.len(), 1);
+    }
+
+    #[test]
+    fn test_message_remove_link() {
+        let mut dg = Digraph::new();
+        dg.message(DigraphMessage::AddNode(NodeSettings::default()))
+            .expect("Can add a node via message");
+        dg.message(DigraphMessage::AddNode(NodeSettings::default()))
+            .expect("Can add a node via message");
+        assert_eq!(dg.nodes.len(), 2);
+        dg.message(DigraphMessage::AddLink(NewLink {
+            source: 1.into(),
+            target: 2.into(),
+            attrs: None,
+        }))
+        .expect("Can add a link via message");
+        assert_eq!(dg.links.len(), 1);
+        dg.message(DigraphMessage::RemoveLink(LinkRemoval { id: 3.into() }))
+            .expect("Can remove a link via message");
+        assert_eq!(dg.links.len(), 0);
+        assert_eq!(dg.nodes.len(), 2);
+    }
+
+    #[test]
+    fn test_update_node_labels() {
+        let mut dg = Digraph::new();
+        let _ = dg.add_node(Some(NodeSettings {
+            name: Some("a".into()),
+            labels: Some(labels(&[("color", "red"), ("shape", "box")])),
+            ..NodeSettings::default()
+        }));
+
+        dg.update_node(
+            1,
+            Some(NodeSettings {
+                name: None,
+                labels: Some(labels(&[("color", "blue"), ("size", "2")])),
+                remove_labels: Some(vec!["shape".into(), "missing".into()]),
+                replace_labels: None,
+            }),
+        )
+        .expect("The node to be updated");
+        assert_eq!(dg.nodes[0].name, "a");
+        assert_eq!(
+            dg.nodes[0].labels,
+            labels(&[("color", "blue"), ("size", "2")])
+        );
+
+        dg.update_node(
+            1,
+            Some(NodeSettings {
+                name: None,
+                labels: Some(labels(&[("style", "bold")])),
+                remove_labels: None,
+                replace_labels: Some(true),
+            }),
+        )
+        .expect("The node to be updated");
+        assert_eq!(dg.nodes[0].labels, labels(&[("style", "bold")]));
+
+        dg.update_node(
+            1,
+            Some(NodeSettings {
+                name: None,
+                labels: None,
+                remove_labels: None,
+                replace_labels: Some(true),
+            }),
+        )
+        .expect("The node to be updated");
+        assert!(dg.nodes[0].labels.is_empty());
+    }
+
+    #[test]
+    fn test_message_update_digraph() {
+        let mut dg = Digraph::new();
+        let outcome = dg
+            .message(DigraphMessage::SetName("Pipeline".into()))
+            .expect("Can rename the digraph via message");
+        assert_eq!(outcome, None);
+        assert_eq!(dg.name, "Pipeline");
+
+        dg.message(DigraphMessage::UpdateLabels(LabelSettings {
+            labels: Some(labels(&[("rankdir", "LR"), ("owner", "ops")])),
+            ..LabelSettings::default()
+        }))
+        .expect("Can label the digraph via message");
+        dg.message(DigraphMessage::UpdateLabels(LabelSettings {
+            labels: None,
+            remove_labels: Some(vec!["owner".into()]),
+            replace_labels: None,
+        }))
+        .expect("Can unlabel the digraph via message");
+        assert_eq!(dg.labels, labels(&[("rankdir", "LR")]));
+    }
+}
@@ -0,0 +1,178 @@
+//! PlantUML component diagrams, for documentation rendered with PlantUML.
+//!
+//! Nodes are written as components named `n<id>` and shown with their
+//! name, or with their id when they have none. The `shape` label of a
+//! node picks another element, from the Graphviz shape names or the
+//! PlantUML element names. The `style` label of a link picks a `dashed`,
+//! `dotted` or `bold` line. The name of the digraph becomes the title.
+//!
+//! PlantUML only lays diagrams out top to bottom or left to right, so the
+//! other directions are hinted on every link.
+
+use super::{DiagramDirection, Digraph, Link, Node, SHAPE_LABEL, STYLE_LABEL};
+
+/// `text` as a quoted PlantUML string
+fn text(text: &str) -> String {
+    format!("\"{}\"", escape(text))
+}
+
+/// `text` with the quotes and line breaks PlantUML cannot take literally
+fn escape(text: &str) -> String {
+    text.replace('"', "&#34;")
+        .replace('\r', "")
+        .replace('\n', "\\n")
+}
+
+/// The element for a node with `shape`
+fn element(shape: Option<&str>) -> &'static str {
+    match shape.unwrap_or_default() {
+        "box" | "rect" | "rectangle" | "square" => "rectangle",
+        "ellipse" | "oval" | "rounded" | "stadium" => "usecase",
+        "circle" | "doublecircle" => "circle",
+        "cylinder" | "database" => "database",
+        "hexagon" => "hexagon",
+        "actor" => "actor",
+        "artifact" => "artifact",
+        "card" => "card",
+        "cloud" => "cloud",
+        "file" => "file",
+        "folder" => "folder",
+        "frame" => "frame",
+        "interface" => "interface",
+        "node" => "node",
+        "queue" => "queue",
+        "storage" => "storage",
+        _ => "component",
+    }
+}
+
+fn node(node: &Node) -> String {
+    let name = if node.name.is_empty() {
+        node.id.to_string()
+    } else {
+        node.name.clone()
+    };
+    format!(
+        "{} {} as n{}",
+        element(node.labels.get(SHAPE_LABEL).map(String::as_str)),
+        text(&name),
+        node.id
+    )
+}
+
+fn link(link: &Link, direction: DiagramDirection) -> String {
+    let hint = match direction {
+        DiagramDirection::BottomUp => "up",
+        DiagramDirection::RightLeft => "left",
+        DiagramDirection::TopDown | DiagramDirection::LeftRight => "",
+    };
+    let (line, style) = match link.labels.get(STYLE_LABEL).map(String::as_str) {
+        Some("dashed") => ('.', ""),
+        Some("dotted") => ('-', "[dotted]"),
+        Some("bold") => ('-', "[bold]"),
+        _ => ('-', ""),
+    };
+    let arrow = format!("{}{}{}{}>", line, hint, style, line);
+    if link.name.is_empty() {
+        format!("n{} {} n{}", link.source, arrow, link.target)
+    } else {
+        format!(
+            "n{} {} n{} : {}",
+            link.source,
+            arrow,
+            link.target,
+            escape(&link.name)
+        )
+    }
+}
+
+impl Digraph {
+    /// Render this digraph as a PlantUML component diagram
+    pub fn to_plantuml(&self, direction: DiagramDirection) -> String {
+        let mut plantuml = "@startuml\n".to_string();
+        if !self.name.is_empty() {
+            plantuml.push_str(&format!("title {}\n", escape(&self.name)));
+        }
+        if direction == DiagramDirection::LeftRight {
+            plantuml.push_str("left to right direction\n");
+        }
+        for node in &self.nodes {
+            plantuml.push_str(&format!("{}\n", self::node(node)));
+        }
+        for link in &self.links {
+            plantuml.push_str(&format!("{}\n", self::link(link, direction)));
+        }
+        plantuml.push_str("@enduml\n");
+        plantuml
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::model::digraph::test_util::labels;
+    use crate::model::digraph::{LinkSettings, NodeSettings};
+
+    #[test]
+    fn test_digraph_to_plantuml() {
+        let mut dg = Digraph::new();
+        assert_eq!(
+            dg.to_plantuml(DiagramDirection::default()),
+            "@startuml\n@enduml\n"
+        );
+
+        dg.name = "Data flow".into();
+        dg.add_node(Some(NodeSettings {
+            name: Some("Web \"app\"".into()),
+            labels: None,
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_node(Some(NodeSettings {
+            name: Some("".into()),
+            labels: Some(labels(&[(SHAPE_LABEL, "database")])),
+            ..NodeSettings::default()
+        }))
+        .expect("The node to be added");
+        dg.add_link(
+            1,
+            2,
+            Some(LinkSettings {
+                name: Some("reads\nrows".into()),
+                labels: Some(labels(&[(STYLE_LABEL, "dashed")])),
+                ..LinkSettings::default()
+            }),
+        )
+        .expect("The link to be added");
+        dg.add_link(
+            2,
+            1,
+            Some(LinkSettings {
+                name: Some("".into()),
+                labels: Some(labels(&[(STYLE_LABEL, "bold")])),
+                ..LinkSettings::default()
+            }),
+        )
+        .expect("The link to be added");
+
+        assert_eq!(
+            dg.to_plantuml(DiagramDirection::LeftRight),
+            concat!(
+                "@startuml\n",
+                "title Data flow\n",
+                "left to right direction\n",
+                "component \"Web &#34;app&#34;\" as n1\n",
+                "database \"2\" as n2\n",
+                "n1 ..> n2 : reads\\nrows\n",
+                "n2 -[bold]-> n1\n",
+                "@enduml\n",
+            )
+        );
+
+        let plantuml = dg.to_plantuml(DiagramDirection::BottomUp);
+        assert!(!plantuml.contains("direction"));
+        assert!(plantuml.contains("n1 .up.> n2 : reads\\nrows\n"));
+        assert!(plantuml.contains("n2 -up[bold]-> n1\n"));
+    }
+}
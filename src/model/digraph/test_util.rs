@@ -0,0 +1,24 @@
+//! Helpers shared by the tests of the digraph modules
+
+use super::{Digraph, Labels};
+
+/// Labels from `(key, value)` pairs
+pub fn labels(pairs: &[(&str, &str)]) -> Labels {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// A digraph with nodes 1 to `nodes` and links between them
+pub fn digraph(nodes: i32, links: &[(i32, i32)]) -> Digraph {
+    let mut dg = Digraph::new();
+    for _ in 0..nodes {
+        dg.add_node(None).expect("The node to be added");
+    }
+    for (source, target) in links {
+        dg.add_link(*source, *target, None)
+            .expect("The link to be added");
+    }
+    dg
+}
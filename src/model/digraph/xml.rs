@@ -0,0 +1,205 @@
+//! The XML shared by the GraphML and GEXF formats. Documents are read
+//! with `roxmltree`, which keeps nesting off the stack, and elements are
+//! matched by their local name, whatever their namespace.
+
+use super::{Labels, ParseError};
+use std::collections::{BTreeMap, HashSet};
+
+/// An element of a document read with `parse`
+#[derive(Clone, Copy, Debug)]
+pub struct Element<'a, 'input> {
+    node: roxmltree::Node<'a, 'input>,
+}
+
+impl<'a, 'input> Element<'a, 'input> {
+    /// The root element of `document`
+    pub fn root(document: &'a roxmltree::Document<'input>) -> Self {
+        Self {
+            node: document.root_element(),
+        }
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.node.tag_name().name()
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&'a str> {
+        self.node.attribute(name)
+    }
+
+    /// The attribute `name`, which is an error when it is missing
+    pub fn required(&self, name: &str) -> Result<&'a str, ParseError> {
+        match self.attribute(name) {
+            Some(value) => Ok(value),
+            None => self.error(format!("Missing attribute '{}' on <{}>", name, self.name())),
+        }
+    }
+
+    /// The child elements named `name`
+    pub fn children<'n>(&self, name: &'n str) -> impl Iterator<Item = Element<'a, 'input>> + 'n
+    where
+        'a: 'n,
+        'input: 'n,
+    {
+        self.node
+            .children()
+            .filter(move |node| node.is_element() && node.tag_name().name() == name)
+            .map(|node| Element { node })
+    }
+
+    pub fn child(&self, name: &str) -> Option<Element<'a, 'input>> {
+        self.children(name).next()
+    }
+
+    pub fn has_elements(&self) -> bool {
+        self.node.children().any(|node| node.is_element())
+    }
+
+    /// The text directly inside this element
+    pub fn text(&self) -> String {
+        self.node
+            .children()
+            .filter_map(|node| if node.is_text() { node.text() } else { None })
+            .collect()
+    }
+
+    pub fn error<T>(&self, message: String) -> Result<T, ParseError> {
+        let pos = self.node.document().text_pos_at(self.node.range().start);
+        Err(ParseError {
+            line: pos.row as usize,
+            column: pos.col as usize,
+            message,
+        })
+    }
+}
+
+/// Read an XML document
+pub fn parse(source: &str) -> Result<roxmltree::Document<'_>, ParseError> {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    roxmltree::Document::parse(source).map_err(|err| {
+        let pos = err.pos();
+        // The message without the position `roxmltree` appends
+        let message = err.to_string();
+        let mut chars = message
+            .strip_suffix(&format!(" at {}", pos))
+            .unwrap_or(&message)
+            .chars();
+        let message = match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        };
+        ParseError {
+            line: pos.row as usize,
+            column: pos.col as usize,
+            message,
+        }
+    })
+}
+
+/// `text` escaped for both element content and attribute values. Line
+/// breaks and tabs are written as references, so they survive attribute
+/// value normalization.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' | '\r' | '\t' => escaped.push_str(&format!("&#{};", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The type of a label key, as declared in GraphML and GEXF
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueType {
+    Boolean,
+    Long,
+    Double,
+    String,
+}
+
+impl ValueType {
+    /// The narrowest type that fits all of `values`
+    pub fn of<'a>(mut values: impl Iterator<Item = &'a str> + Clone) -> Self {
+        let is_long = |value: &str| match value.parse::<i64>() {
+            Ok(n) => n.to_string() == value,
+            Err(_) => false,
+        };
+        let is_double = |value: &str| match value.parse::<f64>() {
+            Ok(n) => n.is_finite() && value.chars().all(|c| "0123456789.-+eE".contains(c)),
+            Err(_) => false,
+        };
+        if values
+            .clone()
+            .all(|value| value == "true" || value == "false")
+        {
+            ValueType::Boolean
+        } else if values.clone().all(is_long) {
+            ValueType::Long
+        } else if values.all(is_double) {
+            ValueType::Double
+        } else {
+            ValueType::String
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueType::Boolean => "boolean",
+            ValueType::Long => "long",
+            ValueType::Double => "double",
+            ValueType::String => "string",
+        }
+    }
+}
+
+/// The keys used in `labels`, sorted, with the type of their values
+pub fn declarations<'a>(labels: impl Iterator<Item = &'a Labels>) -> Vec<(&'a str, ValueType)> {
+    let mut values: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for labels in labels {
+        for (key, value) in labels {
+            values.entry(key).or_default().push(value);
+        }
+    }
+    values
+        .into_iter()
+        .map(|(key, values)| (key, ValueType::of(values.into_iter())))
+        .collect()
+}
+
+/// Hands out the ids of imported nodes and links. An integer id is kept
+/// unless it is taken already, anything else gets the next free id.
+pub struct Ids {
+    taken: HashSet<i32>,
+    next: i32,
+}
+
+impl Ids {
+    /// Ids for elements with `ids`, so new ids never take one of them
+    pub fn new<'a>(ids: impl Iterator<Item = &'a str>) -> Self {
+        let highest = ids.filter_map(|id| id.parse::<i32>().ok()).max();
+        Self {
+            taken: HashSet::new(),
+            next: highest.unwrap_or(0).max(0) + 1,
+        }
+    }
+
+    /// The id for an element with `id`, and whether it was kept
+    pub fn assign(&mut self, id: Option<&str>) -> (i32, bool) {
+        if let Some(id) = id.and_then(|id| id.parse::<i32>().ok()) {
+            if id > 0 && self.taken.insert(id) {
+                return (id, true);
+            }
+        }
+        while !self.taken.insert(self.next) {
+            self.next += 1;
+        }
+        (self.next, false)
+    }
+}
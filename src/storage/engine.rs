@@ -1,13 +1,22 @@
 use std::fmt;
+use std::path::Path;
 
-use crate::doc::change::Change;
-use crate::doc::document::{DocType, RawDocument};
+use crate::doc::change::{Change, ChangeFields, ChangeKind, DocumentChanged};
+use crate::doc::common::DateTime;
+use crate::doc::document::{DocType, DocumentFields, RawDocument};
 use crate::doc::project::{Project, ProjectFields};
+use crate::storage::fs::Fs;
+use crate::storage::memory::Memory;
+use crate::storage::postgres::Postgres;
+use crate::storage::sqlite::Sqlite;
 use async_trait::async_trait;
-use json_patch::diff;
+use json_patch::{diff, patch, Patch};
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct QueryRequest<T> where T: Default {
     pub page: Option<Pagination>,
     pub sort: Option<Vec<(T, Direction)>>,
@@ -19,21 +28,196 @@ impl<T> Default for QueryRequest<T> where T : Default {
             page: Some(Pagination {
                 limit: Some(100),
                 offset: Some(0),
+                ..Default::default()
             }),
             sort: None,
         }
     }
 }
 
+impl<T> QueryRequest<T> where T: Default {
+    /// A request for every record, without a limit
+    pub fn all() -> Self {
+        Self {
+            page: Some(Pagination {
+                limit: Some(0),
+                offset: Some(0),
+                ..Default::default()
+            }),
+            sort: None,
+        }
+    }
+
+    /// The requested limit and offset, a limit of 0 means no limit
+    pub fn limit_offset(&self) -> (i32, i32) {
+        let mut limit = 100;
+        let mut offset = 0;
 
+        if let Some(page) = &self.page {
+            if let Some(l) = page.limit {
+                limit = l
+            }
+            if let Some(o) = page.offset {
+                offset = o
+            }
+        }
+        (limit, offset)
+    }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+    /// The requested sort followed by the tiebreakers it does not mention,
+    /// so that every record has a unique position.
+    pub fn ordering(&self) -> Vec<(T, Direction)>
+    where
+        T: SortField,
+    {
+        let mut ordering = self.sort.clone().unwrap_or_default();
+        for field in T::tiebreakers() {
+            if !ordering.iter().any(|(sorted, _)| *sorted == field) {
+                ordering.push((field, Direction::Asc));
+            }
+        }
+        ordering
+    }
+
+    /// The position of `record` in the ordering of this request
+    pub fn cursor<R>(&self, record: &R) -> Cursor
+    where
+        T: SortField,
+        R: Keyed<T>,
+    {
+        Cursor(
+            self.ordering()
+                .iter()
+                .map(|(field, _)| record.key(field))
+                .collect(),
+        )
+    }
+
+    /// Whether the limit counts back from the end of the selected range
+    pub fn from_end(&self) -> bool {
+        self.page.as_ref().map(|page| page.from_end).unwrap_or(false)
+    }
+}
+
+/// A field records can be sorted on
+pub trait SortField: Clone + Copy + PartialEq {
+    /// Fields appended to every ordering to break ties
+    fn tiebreakers() -> Vec<Self>;
+}
+
+impl SortField for ProjectFields {
+    fn tiebreakers() -> Vec<Self> {
+        vec![ProjectFields::UpdatedAt, ProjectFields::Id]
+    }
+}
+
+impl SortField for DocumentFields {
+    fn tiebreakers() -> Vec<Self> {
+        vec![DocumentFields::UpdatedAt, DocumentFields::Id]
+    }
+}
+
+impl SortField for ChangeFields {
+    fn tiebreakers() -> Vec<Self> {
+        vec![ChangeFields::Id]
+    }
+}
+
+/// A record that exposes the values of its sort fields
+pub trait Keyed<T> {
+    fn key(&self, field: &T) -> CursorValue;
+}
+
+impl Keyed<ProjectFields> for Project {
+    fn key(&self, field: &ProjectFields) -> CursorValue {
+        match field {
+            ProjectFields::Id => CursorValue::Uuid(self.id),
+            ProjectFields::Name => CursorValue::Text(self.name.clone()),
+            ProjectFields::CreatedAt => CursorValue::DateTime(self.created_at),
+            ProjectFields::UpdatedAt => CursorValue::DateTime(self.updated_at),
+            ProjectFields::Version => CursorValue::Int(self.version.into()),
+        }
+    }
+}
+
+impl Keyed<DocumentFields> for RawDocument {
+    fn key(&self, field: &DocumentFields) -> CursorValue {
+        match field {
+            DocumentFields::Id => CursorValue::Uuid(self.id),
+            DocumentFields::Name => CursorValue::Text(self.name.clone()),
+            DocumentFields::Doctype => CursorValue::Text(self.doctype.clone()),
+            DocumentFields::CreatedAt => CursorValue::DateTime(self.created_at),
+            DocumentFields::UpdatedAt => CursorValue::DateTime(self.updated_at),
+            DocumentFields::Version => CursorValue::Int(self.version.into()),
+        }
+    }
+}
+
+impl Keyed<ChangeFields> for Change {
+    fn key(&self, field: &ChangeFields) -> CursorValue {
+        match field {
+            ChangeFields::Id => CursorValue::Int(self.id.into()),
+            ChangeFields::Version => CursorValue::Int(self.version.into()),
+            ChangeFields::CreatedAt => CursorValue::DateTime(self.created_at),
+        }
+    }
+}
+
+/// The value of a single sort field
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CursorValue {
+    Int(i64),
+    Text(String),
+    Uuid(Uuid),
+    DateTime(DateTime),
+}
+
+/// The position of a record in an ordering, holding the value of every
+/// field in `QueryRequest::ordering`. Records are selected by comparing
+/// against these values, so a cursor stays valid when its record is gone.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Cursor(pub Vec<CursorValue>);
+
+/// An inclusive range of timestamps, either end can be left open
+#[derive(async_graphql::InputObject, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct DateRange {
+    pub from: Option<DateTime>,
+    pub to: Option<DateTime>,
+}
+
+/// Project listing criteria, a project has to match all of them
+#[derive(async_graphql::InputObject, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct ProjectFilter {
+    pub name_contains: Option<String>,
+    pub owner_id: Option<Uuid>,
+    pub created: Option<DateRange>,
+    pub updated: Option<DateRange>,
+}
+
+/// Document listing criteria, a document has to match all of them
+#[derive(async_graphql::InputObject, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct DocumentFilter {
+    pub name_contains: Option<String>,
+    pub owner_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub doctype: Option<DocType>,
+    pub created: Option<DateRange>,
+    pub updated: Option<DateRange>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
 pub struct Pagination {
     pub limit: Option<i32>,
     pub offset: Option<i32>,
+    /// Only select records after this position
+    pub after: Option<Cursor>,
+    /// Only select records before this position
+    pub before: Option<Cursor>,
+    /// Take the last `limit` records of the selection instead of the first
+    pub from_end: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(async_graphql::Enum, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum Direction {
     Asc,
     Desc,
@@ -55,21 +239,74 @@ pub struct QueryResponse<T> {
 pub trait Engine: Send + Sync {
     async fn get_document(&self, id: &Uuid) -> Result<RawDocument, EngineError>;
     async fn store_document(&self, doc: RawDocument) -> Result<(), EngineError>;
+    /// Store `doc` and its `change` in one transaction, but only while the
+    /// stored document is still at the version before `doc.version`.
+    /// Fails with `EngineError::VersionMismatch` otherwise.
     async fn update_document(
         &self,
         doc: RawDocument,
         change: Option<Change>,
     ) -> Result<(), EngineError>;
-    async fn delete_document(&self, id: &Uuid) -> Result<(), EngineError>;
+    /// Delete a document, but only while it is still at `expected_version`
+    /// when one is given. Fails with `NotFound` when no document was
+    /// deleted.
+    async fn delete_document(
+        &self,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError>;
+    /// Changes recorded for a document, oldest first.
+    async fn get_document_changes(
+        &self,
+        document_id: &Uuid,
+        params: QueryRequest<ChangeFields>,
+    ) -> Result<QueryResponse<Change>, EngineError>;
+    /// Rebuild a document as it was at `version` by applying the reverse
+    /// patches of all later changes to the current body. Only the body is
+    /// rebuilt, other fields keep their current values.
+    async fn get_document_at_version(
+        &self,
+        id: &Uuid,
+        version: i32,
+    ) -> Result<RawDocument, EngineError> {
+        let mut doc = self.get_document(id).await?;
+        if version < 0 || version > doc.version {
+            return Err(EngineError::NotFound);
+        }
+        let changes = self
+            .get_document_changes(id, QueryRequest::all())
+            .await?
+            .data;
+        for change in changes.iter().rev().filter(|change| change.version > version) {
+            apply_patch(&mut doc.body, &change.reverse)?;
+        }
+        doc.version = version;
+        Ok(doc)
+    }
+
+    async fn get_documents(
+        &self,
+        params: QueryRequest<DocumentFields>,
+        filter: DocumentFilter,
+    ) -> Result<QueryResponse<RawDocument>, EngineError>;
 
     async fn get_projects(
         &self,
         params: QueryRequest<ProjectFields>,
+        filter: ProjectFilter,
     ) -> Result<QueryResponse<Project>, EngineError>;
     async fn get_project(&self, id: &Uuid) -> Result<Project, EngineError>;
     async fn store_project(&self, doc: Project) -> Result<(), EngineError>;
+    /// Store `doc`, but only while the stored project is still at the
+    /// version before `doc.version`.
     async fn update_project(&self, doc: Project) -> Result<(), EngineError>;
-    async fn delete_project(&self, id: &Uuid) -> Result<(), EngineError>;
+    /// Delete a project, but only while it is still at `expected_version`
+    /// when one is given. Fails with `NotFound` when no project was deleted.
+    async fn delete_project(
+        &self,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError>;
 
     async fn get_project_documents(
         &self,
@@ -83,6 +320,9 @@ pub enum EngineError {
     NotFound,
     Storage(String),
     VersionMismatch(i32, i32),
+    NothingToUndo,
+    NothingToRedo,
+    InvalidCursor,
 }
 
 impl fmt::Display for EngineError {
@@ -93,19 +333,49 @@ impl fmt::Display for EngineError {
             EngineError::VersionMismatch(a, b) => {
                 write!(f, "Document version mismatch : {}, {}", a, b)
             }
+            EngineError::NothingToUndo => write!(f, "Nothing to undo"),
+            EngineError::NothingToRedo => write!(f, "Nothing to redo"),
+            EngineError::InvalidCursor => write!(f, "Cursor does not match the ordering"),
         }
     }
 }
 
+impl std::error::Error for EngineError {}
+
+impl From<std::io::Error> for EngineError {
+    fn from(err: std::io::Error) -> EngineError {
+        match err.kind() {
+            std::io::ErrorKind::NotFound => EngineError::NotFound,
+            _ => EngineError::Storage(err.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for EngineError {
+    fn from(err: serde_json::Error) -> EngineError {
+        EngineError::Storage(err.to_string())
+    }
+}
+
 pub struct EngineContainer {
     engine: Box<dyn Engine>,
+    events: broadcast::Sender<DocumentChanged>,
 }
 
 impl EngineContainer {
     pub fn new(engine: impl Engine + 'static) -> Self {
-        Self {
-            engine: Box::new(engine),
-        }
+        Self::boxed(Box::new(engine))
+    }
+
+    pub fn boxed(engine: Box<dyn Engine>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { engine, events }
+    }
+
+    /// Receive a `DocumentChanged` event for every document update made
+    /// through this container from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<DocumentChanged> {
+        self.events.subscribe()
     }
 
     pub async fn get_document(&self, id: &Uuid) -> Result<RawDocument, EngineError> {
@@ -122,28 +392,131 @@ impl EngineContainer {
                 current_doc.version,
             ))
         } else {
-            doc.change();
-            let forward = diff(&current_doc.body, &doc.body);
-            let reverse = diff(&doc.body, &current_doc.body);
-            let change = Change {
-                id: 0,
-                document_id: doc.id,
-                version: doc.version,
-                forward: serde_json::to_value(forward).expect("Patch to convert to Value"),
-                reverse: serde_json::to_value(reverse).expect("Patch to convert to Value"),
-            };
-            self.engine.update_document(doc.clone(), Some(change)).await
+            self.commit_change(&current_doc, doc, ChangeKind::Edit).await
         }
     }
-    pub async fn delete_document(&self, id: &Uuid) -> Result<(), EngineError> {
-        self.engine.delete_document(id).await
+
+    /// Rename a document. The name is not part of the body, so a rename is
+    /// kept out of the change log and undo and redo leave it alone.
+    pub async fn rename_document(
+        &self,
+        doc: &mut RawDocument,
+        name: String,
+    ) -> Result<(), EngineError> {
+        doc.name = name;
+        doc.change();
+        let event = DocumentChanged {
+            document_id: doc.id,
+            project_id: doc.project_id,
+            version: doc.version,
+            forward: serde_json::Value::Array(vec![]),
+        };
+        self.engine.update_document(doc.clone(), None).await?;
+        // Sending only fails when nobody is subscribed
+        let _ = self.events.send(event);
+        Ok(())
+    }
+
+    /// Revert the most recent change that has not been undone yet. The undo
+    /// is recorded as a new change, earlier history is left untouched.
+    pub async fn undo_document(&self, id: &Uuid) -> Result<RawDocument, EngineError> {
+        let current_doc = self.get_document(id).await?;
+        let changes = self
+            .engine
+            .get_document_changes(id, QueryRequest::all())
+            .await?
+            .data;
+        let target = Change::undo_target(&changes).ok_or(EngineError::NothingToUndo)?;
+
+        let mut doc = current_doc.clone();
+        apply_patch(&mut doc.body, &target.reverse)?;
+        self.commit_change(&current_doc, &mut doc, ChangeKind::Undo).await?;
+        Ok(doc)
+    }
+
+    /// Re-apply the change reverted by the most recent undo, as long as the
+    /// document has not been edited since.
+    pub async fn redo_document(&self, id: &Uuid) -> Result<RawDocument, EngineError> {
+        let current_doc = self.get_document(id).await?;
+        let changes = self
+            .engine
+            .get_document_changes(id, QueryRequest::all())
+            .await?
+            .data;
+        let target = Change::redo_target(&changes).ok_or(EngineError::NothingToRedo)?;
+
+        let mut doc = current_doc.clone();
+        apply_patch(&mut doc.body, &target.reverse)?;
+        self.commit_change(&current_doc, &mut doc, ChangeKind::Redo).await?;
+        Ok(doc)
+    }
+
+    async fn commit_change(
+        &self,
+        current_doc: &RawDocument,
+        doc: &mut RawDocument,
+        kind: ChangeKind,
+    ) -> Result<(), EngineError> {
+        doc.change();
+        let forward = diff(&current_doc.body, &doc.body);
+        let reverse = diff(&doc.body, &current_doc.body);
+        let change = Change {
+            id: 0,
+            document_id: doc.id,
+            version: doc.version,
+            kind,
+            forward: serde_json::to_value(forward).expect("Patch to convert to Value"),
+            reverse: serde_json::to_value(reverse).expect("Patch to convert to Value"),
+            created_at: chrono::Utc::now(),
+        };
+        let event = DocumentChanged {
+            document_id: doc.id,
+            project_id: doc.project_id,
+            version: doc.version,
+            forward: change.forward.clone(),
+        };
+        self.engine.update_document(doc.clone(), Some(change)).await?;
+        // Sending only fails when nobody is subscribed
+        let _ = self.events.send(event);
+        Ok(())
+    }
+
+    pub async fn delete_document(
+        &self,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError> {
+        self.engine.delete_document(id, expected_version).await
+    }
+    pub async fn get_document_changes(
+        &self,
+        document_id: &Uuid,
+        params: QueryRequest<ChangeFields>,
+    ) -> Result<QueryResponse<Change>, EngineError> {
+        self.engine.get_document_changes(document_id, params).await
+    }
+    pub async fn get_document_at_version(
+        &self,
+        id: &Uuid,
+        version: i32,
+    ) -> Result<RawDocument, EngineError> {
+        self.engine.get_document_at_version(id, version).await
+    }
+
+    pub async fn get_documents(
+        &self,
+        params: QueryRequest<DocumentFields>,
+        filter: DocumentFilter,
+    ) -> Result<QueryResponse<RawDocument>, EngineError> {
+        self.engine.get_documents(params, filter).await
     }
 
     pub async fn get_projects(
         &self,
         params: QueryRequest<ProjectFields>,
+        filter: ProjectFilter,
     ) -> Result<QueryResponse<Project>, EngineError> {
-        self.engine.get_projects(params).await
+        self.engine.get_projects(params, filter).await
     }
     pub async fn get_project(&self, id: &Uuid) -> Result<Project, EngineError> {
         self.engine.get_project(id).await
@@ -151,11 +524,24 @@ impl EngineContainer {
     pub async fn store_project(&self, doc: Project) -> Result<(), EngineError> {
         self.engine.store_project(doc).await
     }
-    pub async fn update_project(&self, doc: Project) -> Result<(), EngineError> {
-        self.engine.update_project(doc).await
+    pub async fn update_project(&self, doc: &mut Project) -> Result<(), EngineError> {
+        let current_project = self.get_project(&doc.id).await?;
+        if current_project.version != doc.version {
+            Err(EngineError::VersionMismatch(
+                doc.version,
+                current_project.version,
+            ))
+        } else {
+            doc.change();
+            self.engine.update_project(doc.clone()).await
+        }
     }
-    pub async fn delete_project(&self, id: &Uuid) -> Result<(), EngineError> {
-        self.engine.delete_project(id).await
+    pub async fn delete_project(
+        &self,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError> {
+        self.engine.delete_project(id, expected_version).await
     }
 
     pub async fn get_project_documents(
@@ -167,7 +553,112 @@ impl EngineContainer {
     }
 }
 
+/// Fail with a `VersionMismatch` when the client expects another version
+pub fn check_version(expected_version: Option<i32>, version: i32) -> Result<(), EngineError> {
+    match expected_version {
+        Some(expected_version) if expected_version != version => {
+            Err(EngineError::VersionMismatch(expected_version, version))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn apply_patch(
+    body: &mut serde_json::Value,
+    change: &serde_json::Value,
+) -> Result<(), EngineError> {
+    let change: Patch = serde_json::from_value(change.clone())
+        .map_err(|err| EngineError::Storage(err.to_string()))?;
+    patch(body, &change).map_err(|err| EngineError::Storage(err.to_string()))
+}
+
+/// One of the storage engines, picked from a database URL:
+///
+/// - `sqlite://path`, `sqlite:path` or `sqlite::memory:`
+/// - `postgres://...` or `postgresql://...`
+/// - `file:///dir` for a project directory
+/// - `memory:` for an engine that forgets everything on exit
 #[derive(Debug)]
 pub enum EngineVariant {
-    Sqlite(super::sqlite::Sqlite),
+    Sqlite(Sqlite),
+    Postgres(Postgres),
+    Fs(Fs),
+    Memory(Memory),
+}
+
+impl EngineVariant {
+    /// Set up the engine `url` points at, without running migrations
+    pub async fn connect(url: &str) -> Result<Self, EngineError> {
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            Ok(EngineVariant::Postgres(Postgres::setup(url.to_string()).await?))
+        } else if let Some(path) = url
+            .strip_prefix("sqlite://")
+            .or_else(|| url.strip_prefix("sqlite:"))
+        {
+            Ok(EngineVariant::Sqlite(Sqlite::setup(path.to_string()).await?))
+        } else if let Some(path) = url.strip_prefix("file://") {
+            Ok(EngineVariant::Fs(Fs::setup(path).await?))
+        } else if url == "memory:" || url == "memory://" {
+            Ok(EngineVariant::Memory(Memory::new()))
+        } else {
+            Err(EngineError::Storage(format!(
+                "Unsupported database url {}",
+                url
+            )))
+        }
+    }
+
+    /// Set up the engine `url` points at when its storage exists already,
+    /// without creating or migrating it. An sqlite database is opened
+    /// read-only unless `writable` is set.
+    pub async fn open(url: &str, writable: bool) -> Result<Self, EngineError> {
+        let missing = || EngineError::Storage(format!("Database {} does not exist", url));
+        if let Some(path) = url
+            .strip_prefix("sqlite://")
+            .or_else(|| url.strip_prefix("sqlite:"))
+        {
+            if !Path::new(path).is_file() {
+                return Err(missing());
+            }
+            Ok(EngineVariant::Sqlite(
+                Sqlite::open(path.to_string(), !writable).await?,
+            ))
+        } else if let Some(path) = url.strip_prefix("file://") {
+            if !Path::new(path).is_dir() {
+                return Err(missing());
+            }
+            Ok(EngineVariant::Fs(Fs::setup(path).await?))
+        } else {
+            Self::connect(url).await
+        }
+    }
+
+    /// Whether the engine keeps a schema that `migrate` brings up to date
+    pub fn has_migrations(&self) -> bool {
+        matches!(self, EngineVariant::Sqlite(_) | EngineVariant::Postgres(_))
+    }
+
+    pub async fn migrate(&self) -> Result<(), EngineError> {
+        match self {
+            EngineVariant::Sqlite(storage) => Ok(storage.migrate().await?),
+            EngineVariant::Postgres(storage) => Ok(storage.migrate().await?),
+            EngineVariant::Fs(_) | EngineVariant::Memory(_) => Ok(()),
+        }
+    }
+
+    pub fn boxed(self) -> Box<dyn Engine> {
+        match self {
+            EngineVariant::Sqlite(storage) => Box::new(storage),
+            EngineVariant::Postgres(storage) => Box::new(storage),
+            EngineVariant::Fs(storage) => Box::new(storage),
+            EngineVariant::Memory(storage) => Box::new(storage),
+        }
+    }
+}
+
+/// Set up and migrate the engine `url` points at
+pub async fn connect(url: &str) -> Result<Box<dyn Engine>, EngineError> {
+    let variant = EngineVariant::connect(url).await?;
+    variant.migrate().await?;
+    Ok(variant.boxed())
 }
@@ -0,0 +1,393 @@
+use crate::doc::change::{Change, ChangeFields};
+use crate::doc::document::{DocType, DocumentFields, RawDocument};
+use crate::doc::project::{Project, ProjectFields};
+use crate::storage::engine::{
+    check_version, DocumentFilter, Engine, EngineError, ProjectFilter, QueryRequest, QueryResponse,
+};
+use crate::storage::select::{document_matches, project_matches, select};
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+const PROJECT_FILE: &str = "project.json";
+const CHANGES_FILE: &str = "changes.jsonl";
+
+/// An engine that keeps every project in its own directory below `root`,
+/// so projects can be kept under version control:
+///
+/// ```text
+/// <root>/<project id>/project.json
+/// <root>/<project id>/<document id>.json
+/// <root>/<project id>/changes.jsonl
+/// ```
+///
+/// Files are replaced by writing a temporary file and renaming it. Changes
+/// are appended to the log of the project, one JSON object per line, and
+/// stay there when their document is deleted. An updated document is only
+/// renamed into place once its change is logged, and changes of versions
+/// that never got stored are ignored.
+#[derive(Debug)]
+pub struct Fs {
+    pub root: PathBuf,
+    /// Held while writing, with the id of the last logged change by
+    /// project, read from the log at the first change of a project
+    writes: Mutex<HashMap<Uuid, i32>>,
+}
+
+impl Fs {
+    pub async fn setup(root: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root).await?;
+        Ok(Self {
+            root,
+            writes: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn project_dir(&self, project_id: &Uuid) -> PathBuf {
+        self.root.join(project_id.to_hyphenated().to_string())
+    }
+
+    fn document_path(&self, project_id: &Uuid, id: &Uuid) -> PathBuf {
+        self.project_dir(project_id)
+            .join(format!("{}.json", id.to_hyphenated()))
+    }
+
+    async fn project_ids(&self) -> Result<Vec<Uuid>, EngineError> {
+        let mut ids = vec![];
+        let mut entries = fs::read_dir(&self.root).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| Uuid::parse_str(name).ok())
+            {
+                if fs::metadata(entry.path().join(PROJECT_FILE)).await.is_ok() {
+                    ids.push(id);
+                }
+            }
+        }
+        Ok(ids)
+    }
+
+    async fn projects(&self) -> Result<Vec<Project>, EngineError> {
+        let mut projects = vec![];
+        for id in self.project_ids().await? {
+            projects.push(read_json(&self.project_dir(&id).join(PROJECT_FILE)).await?);
+        }
+        Ok(projects)
+    }
+
+    async fn documents(&self, project_id: &Uuid) -> Result<Vec<RawDocument>, EngineError> {
+        let mut docs = vec![];
+        let mut entries = fs::read_dir(self.project_dir(project_id)).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let is_document = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(".json"))
+                .map(|id| Uuid::parse_str(id).is_ok())
+                .unwrap_or(false);
+            if is_document {
+                docs.push(read_json(&entry.path()).await?);
+            }
+        }
+        Ok(docs)
+    }
+
+    async fn all_documents(&self) -> Result<Vec<RawDocument>, EngineError> {
+        let mut docs = vec![];
+        for project_id in self.project_ids().await? {
+            docs.append(&mut self.documents(&project_id).await?);
+        }
+        Ok(docs)
+    }
+
+    /// The file of a document, found by looking through every project
+    async fn find_document(&self, id: &Uuid) -> Result<PathBuf, EngineError> {
+        for project_id in self.project_ids().await? {
+            let path = self.document_path(&project_id, id);
+            if fs::metadata(&path).await.is_ok() {
+                return Ok(path);
+            }
+        }
+        Err(EngineError::NotFound)
+    }
+
+    async fn changes(&self, project_id: &Uuid) -> Result<Vec<Change>, EngineError> {
+        let log = match fs::read_to_string(self.project_dir(project_id).join(CHANGES_FILE)).await {
+            Ok(log) => log,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+        log.lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_str(line).map_err(EngineError::from))
+            .collect()
+    }
+
+    /// Append `change` to the log of `project_id`, given the ids of the last
+    /// changes held by the write lock
+    async fn append_change(
+        &self,
+        last_change_ids: &mut HashMap<Uuid, i32>,
+        project_id: &Uuid,
+        mut change: Change,
+    ) -> Result<(), EngineError> {
+        let last_change_id = match last_change_ids.get(project_id) {
+            Some(id) => *id,
+            None => self.changes(project_id).await?.len() as i32,
+        };
+        change.id = last_change_id + 1;
+        change.created_at = chrono::Utc::now();
+
+        let mut line = serde_json::to_string(&change)?;
+        line.push('\n');
+        let mut log = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.project_dir(project_id).join(CHANGES_FILE))
+            .await?;
+        log.write_all(line.as_bytes()).await?;
+        log.sync_data().await?;
+        last_change_ids.insert(*project_id, change.id);
+        Ok(())
+    }
+}
+
+async fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, EngineError> {
+    let json = fs::read(path).await?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Replace the file at `path` by writing next to it and renaming, so
+/// readers either see the old or the new content
+async fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), EngineError> {
+    let temp_path = write_temp(path, value).await?;
+    fs::rename(&temp_path, path).await?;
+    Ok(())
+}
+
+/// Write `value` to a temporary file next to `path`, to be renamed to it
+async fn write_temp<T: Serialize>(path: &Path, value: &T) -> Result<PathBuf, EngineError> {
+    let mut json = serde_json::to_string_pretty(value)?;
+    json.push('\n');
+
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| EngineError::Storage(format!("Invalid path {}", path.display())))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+
+    let mut file = fs::File::create(&temp_path).await?;
+    file.write_all(json.as_bytes()).await?;
+    file.sync_all().await?;
+    Ok(temp_path)
+}
+
+#[async_trait]
+impl Engine for Fs {
+    async fn get_document(&self, id: &Uuid) -> Result<RawDocument, EngineError> {
+        read_json(&self.find_document(id).await?).await
+    }
+
+    async fn store_document(&self, mut doc: RawDocument) -> Result<(), EngineError> {
+        let _writes = self.writes.lock().await;
+        if fs::metadata(self.project_dir(&doc.project_id).join(PROJECT_FILE))
+            .await
+            .is_err()
+        {
+            return Err(EngineError::Storage(format!(
+                "Project {} does not exist",
+                doc.project_id
+            )));
+        }
+        if self.find_document(&doc.id).await.is_ok() {
+            return Err(EngineError::Storage(format!(
+                "Document {} already exists",
+                doc.id
+            )));
+        }
+        doc.created_at = chrono::Utc::now();
+        doc.updated_at = doc.created_at;
+        write_json(&self.document_path(&doc.project_id, &doc.id), &doc).await
+    }
+
+    async fn update_document(
+        &self,
+        doc: RawDocument,
+        change: Option<Change>,
+    ) -> Result<(), EngineError> {
+        let mut writes = self.writes.lock().await;
+        let expected_version = doc.version - 1;
+
+        let path = self.find_document(&doc.id).await?;
+        let mut stored: RawDocument = read_json(&path).await?;
+        if stored.version != expected_version {
+            return Err(EngineError::VersionMismatch(
+                expected_version,
+                stored.version,
+            ));
+        }
+        stored.name = doc.name;
+        stored.version = doc.version;
+        stored.body = doc.body;
+        stored.updated_at = chrono::Utc::now();
+
+        let temp_path = write_temp(&path, &stored).await?;
+        if let Some(change) = change {
+            let logged = self
+                .append_change(&mut writes, &stored.project_id, change)
+                .await;
+            if let Err(err) = logged {
+                let _ = fs::remove_file(&temp_path).await;
+                return Err(err);
+            }
+        }
+        fs::rename(&temp_path, &path).await?;
+        Ok(())
+    }
+
+    async fn delete_document(
+        &self,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError> {
+        let _writes = self.writes.lock().await;
+        let path = self.find_document(id).await?;
+        let stored: RawDocument = read_json(&path).await?;
+        check_version(expected_version, stored.version)?;
+        Ok(fs::remove_file(path).await?)
+    }
+
+    async fn get_document_changes(
+        &self,
+        document_id: &Uuid,
+        params: QueryRequest<ChangeFields>,
+    ) -> Result<QueryResponse<Change>, EngineError> {
+        // The log outlives deleted documents, their changes are left out.
+        // An update that was cut short leaves a change of a version newer
+        // than the document, which a later update logs again.
+        let changes = match self.get_document(document_id).await {
+            Ok(doc) => {
+                let mut by_version = BTreeMap::new();
+                for change in self.changes(&doc.project_id).await? {
+                    if change.document_id == *document_id && change.version <= doc.version {
+                        by_version.insert(change.version, change);
+                    }
+                }
+                by_version.into_values().collect()
+            }
+            Err(EngineError::NotFound) => vec![],
+            Err(err) => return Err(err),
+        };
+        select(changes, &params)
+    }
+
+    async fn get_documents(
+        &self,
+        params: QueryRequest<DocumentFields>,
+        filter: DocumentFilter,
+    ) -> Result<QueryResponse<RawDocument>, EngineError> {
+        let docs = match filter.project_id {
+            Some(project_id) => match self.documents(&project_id).await {
+                Err(EngineError::NotFound) => vec![],
+                docs => docs?,
+            },
+            None => self.all_documents().await?,
+        };
+        let docs = docs
+            .into_iter()
+            .filter(|doc| document_matches(&filter, doc))
+            .collect();
+        select(docs, &params)
+    }
+
+    async fn get_projects(
+        &self,
+        params: QueryRequest<ProjectFields>,
+        filter: ProjectFilter,
+    ) -> Result<QueryResponse<Project>, EngineError> {
+        let projects = self
+            .projects()
+            .await?
+            .into_iter()
+            .filter(|project| project_matches(&filter, project))
+            .collect();
+        select(projects, &params)
+    }
+
+    async fn get_project(&self, id: &Uuid) -> Result<Project, EngineError> {
+        read_json(&self.project_dir(id).join(PROJECT_FILE)).await
+    }
+
+    async fn store_project(&self, mut doc: Project) -> Result<(), EngineError> {
+        let _writes = self.writes.lock().await;
+        let dir = self.project_dir(&doc.id);
+        if fs::metadata(dir.join(PROJECT_FILE)).await.is_ok() {
+            return Err(EngineError::Storage(format!(
+                "Project {} already exists",
+                doc.id
+            )));
+        }
+        doc.created_at = chrono::Utc::now();
+        doc.updated_at = doc.created_at;
+        fs::create_dir_all(&dir).await?;
+        write_json(&dir.join(PROJECT_FILE), &doc).await
+    }
+
+    async fn update_project(&self, doc: Project) -> Result<(), EngineError> {
+        let _writes = self.writes.lock().await;
+        let expected_version = doc.version - 1;
+
+        let path = self.project_dir(&doc.id).join(PROJECT_FILE);
+        let mut stored: Project = read_json(&path).await?;
+        if stored.version != expected_version {
+            return Err(EngineError::VersionMismatch(
+                expected_version,
+                stored.version,
+            ));
+        }
+        stored.name = doc.name;
+        stored.version = doc.version;
+        stored.body = doc.body;
+        stored.updated_at = chrono::Utc::now();
+        write_json(&path, &stored).await
+    }
+
+    async fn delete_project(
+        &self,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError> {
+        let mut writes = self.writes.lock().await;
+        let stored: Project = read_json(&self.project_dir(id).join(PROJECT_FILE)).await?;
+        check_version(expected_version, stored.version)?;
+        writes.remove(id);
+        Ok(fs::remove_dir_all(self.project_dir(id)).await?)
+    }
+
+    async fn get_project_documents(
+        &self,
+        project_id: &Uuid,
+        variant: DocType,
+    ) -> Result<Vec<RawDocument>, EngineError> {
+        let doctype = variant.to_string();
+        let docs = match self.documents(project_id).await {
+            Err(EngineError::NotFound) => vec![],
+            docs => docs?,
+        };
+        Ok(docs
+            .into_iter()
+            .filter(|doc| doc.doctype == doctype)
+            .collect())
+    }
+}
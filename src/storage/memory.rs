@@ -0,0 +1,246 @@
+use crate::doc::change::{Change, ChangeFields};
+use crate::doc::document::{DocType, DocumentFields, RawDocument};
+use crate::doc::project::{Project, ProjectFields};
+use crate::storage::engine::{
+    check_version, DocumentFilter, Engine, EngineError, ProjectFilter, QueryRequest, QueryResponse,
+};
+use crate::storage::select::{document_matches, project_matches, select};
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use uuid::Uuid;
+
+/// An engine that keeps everything in memory and forgets it when dropped.
+/// It behaves like the SQL engines, including version checks and cascading
+/// deletes, so it can stand in for them in tests and short-lived tools.
+#[derive(Debug, Default)]
+pub struct Memory {
+    state: RwLock<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    projects: HashMap<Uuid, Project>,
+    documents: HashMap<Uuid, RawDocument>,
+    changes: Vec<Change>,
+    last_change_id: i32,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, State> {
+        self.state.read().expect("The memory lock to be intact")
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, State> {
+        self.state.write().expect("The memory lock to be intact")
+    }
+}
+
+#[async_trait]
+impl Engine for Memory {
+    async fn get_document(&self, id: &Uuid) -> Result<RawDocument, EngineError> {
+        self.read()
+            .documents
+            .get(id)
+            .cloned()
+            .ok_or(EngineError::NotFound)
+    }
+
+    async fn store_document(&self, mut doc: RawDocument) -> Result<(), EngineError> {
+        let mut state = self.write();
+        if !state.projects.contains_key(&doc.project_id) {
+            return Err(EngineError::Storage(format!(
+                "Project {} does not exist",
+                doc.project_id
+            )));
+        }
+        if state.documents.contains_key(&doc.id) {
+            return Err(EngineError::Storage(format!(
+                "Document {} already exists",
+                doc.id
+            )));
+        }
+        doc.created_at = chrono::Utc::now();
+        doc.updated_at = doc.created_at;
+        state.documents.insert(doc.id, doc);
+        Ok(())
+    }
+
+    async fn update_document(
+        &self,
+        doc: RawDocument,
+        change: Option<Change>,
+    ) -> Result<(), EngineError> {
+        let mut state = self.write();
+        let expected_version = doc.version - 1;
+
+        let stored = state
+            .documents
+            .get_mut(&doc.id)
+            .ok_or(EngineError::NotFound)?;
+        if stored.version != expected_version {
+            return Err(EngineError::VersionMismatch(
+                expected_version,
+                stored.version,
+            ));
+        }
+        stored.name = doc.name;
+        stored.version = doc.version;
+        stored.body = doc.body;
+        stored.updated_at = chrono::Utc::now();
+
+        if let Some(mut change) = change {
+            state.last_change_id += 1;
+            change.id = state.last_change_id;
+            change.created_at = chrono::Utc::now();
+            state.changes.push(change);
+        }
+        Ok(())
+    }
+
+    async fn delete_document(
+        &self,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError> {
+        let mut state = self.write();
+        let stored = state.documents.get(id).ok_or(EngineError::NotFound)?;
+        check_version(expected_version, stored.version)?;
+        state.documents.remove(id);
+        state.changes.retain(|change| change.document_id != *id);
+        Ok(())
+    }
+
+    async fn get_document_changes(
+        &self,
+        document_id: &Uuid,
+        params: QueryRequest<ChangeFields>,
+    ) -> Result<QueryResponse<Change>, EngineError> {
+        let changes = self
+            .read()
+            .changes
+            .iter()
+            .filter(|change| change.document_id == *document_id)
+            .cloned()
+            .collect();
+        select(changes, &params)
+    }
+
+    async fn get_documents(
+        &self,
+        params: QueryRequest<DocumentFields>,
+        filter: DocumentFilter,
+    ) -> Result<QueryResponse<RawDocument>, EngineError> {
+        let docs = self
+            .read()
+            .documents
+            .values()
+            .filter(|doc| document_matches(&filter, doc))
+            .cloned()
+            .collect();
+        select(docs, &params)
+    }
+
+    async fn get_projects(
+        &self,
+        params: QueryRequest<ProjectFields>,
+        filter: ProjectFilter,
+    ) -> Result<QueryResponse<Project>, EngineError> {
+        let projects = self
+            .read()
+            .projects
+            .values()
+            .filter(|project| project_matches(&filter, project))
+            .cloned()
+            .collect();
+        select(projects, &params)
+    }
+
+    async fn get_project(&self, id: &Uuid) -> Result<Project, EngineError> {
+        self.read()
+            .projects
+            .get(id)
+            .cloned()
+            .ok_or(EngineError::NotFound)
+    }
+
+    async fn store_project(&self, mut doc: Project) -> Result<(), EngineError> {
+        let mut state = self.write();
+        if state.projects.contains_key(&doc.id) {
+            return Err(EngineError::Storage(format!(
+                "Project {} already exists",
+                doc.id
+            )));
+        }
+        doc.created_at = chrono::Utc::now();
+        doc.updated_at = doc.created_at;
+        state.projects.insert(doc.id, doc);
+        Ok(())
+    }
+
+    async fn update_project(&self, doc: Project) -> Result<(), EngineError> {
+        let mut state = self.write();
+        let expected_version = doc.version - 1;
+
+        let stored = state
+            .projects
+            .get_mut(&doc.id)
+            .ok_or(EngineError::NotFound)?;
+        if stored.version != expected_version {
+            return Err(EngineError::VersionMismatch(
+                expected_version,
+                stored.version,
+            ));
+        }
+        stored.name = doc.name;
+        stored.version = doc.version;
+        stored.body = doc.body;
+        stored.updated_at = chrono::Utc::now();
+        Ok(())
+    }
+
+    async fn delete_project(
+        &self,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError> {
+        let mut state = self.write();
+        let stored = state.projects.get(id).ok_or(EngineError::NotFound)?;
+        check_version(expected_version, stored.version)?;
+        state.projects.remove(id);
+
+        let removed: Vec<Uuid> = state
+            .documents
+            .values()
+            .filter(|doc| doc.project_id == *id)
+            .map(|doc| doc.id)
+            .collect();
+        for doc_id in &removed {
+            state.documents.remove(doc_id);
+        }
+        state
+            .changes
+            .retain(|change| !removed.contains(&change.document_id));
+        Ok(())
+    }
+
+    async fn get_project_documents(
+        &self,
+        project_id: &Uuid,
+        variant: DocType,
+    ) -> Result<Vec<RawDocument>, EngineError> {
+        let doctype = variant.to_string();
+        Ok(self
+            .read()
+            .documents
+            .values()
+            .filter(|doc| doc.project_id == *project_id && doc.doctype == doctype)
+            .cloned()
+            .collect())
+    }
+}
@@ -1,2 +1,7 @@
 pub mod engine;
+pub mod fs;
+pub mod memory;
+pub mod postgres;
 pub mod sqlite;
+mod select;
+mod sql;
@@ -0,0 +1,414 @@
+use crate::doc::change::{Change, ChangeFields};
+use crate::doc::common::DateTime;
+use crate::doc::document::{DocType, DocumentFields, RawDocument};
+use crate::doc::project::{Project, ProjectFields};
+use crate::storage::engine::{
+    DocumentFilter, Engine, EngineError, ProjectFilter, QueryRequest, QueryResponse,
+    QueryResponseMeta,
+};
+use crate::storage::sql::{
+    change_column, document_column, page, project_column, Conditions, Dialect, SqlValue,
+};
+use crate::storage::sqlite::DbProject;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+/// An engine on a PostgreSQL database, for deployments with several
+/// writers. Document bodies and patches are stored as `jsonb`.
+#[derive(Debug)]
+pub struct Postgres {
+    pub url: String,
+    pub pool: PgPool,
+}
+
+impl Postgres {
+    pub async fn setup(url: String) -> Result<Self, sqlx::Error> {
+        let pool = PgPool::connect(&url).await?;
+        Ok(Self { url, pool })
+    }
+
+    pub async fn migrate(&self) -> Result<(), sqlx::Error> {
+        sqlx::migrate!("migrations/postgres").run(&self.pool).await?;
+        Ok(())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub struct PgDocument {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub owner_id: Uuid,
+    pub name: String,
+    pub doctype: String,
+    pub version: i32,
+    pub body: Value,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+impl From<PgDocument> for RawDocument {
+    fn from(doc: PgDocument) -> RawDocument {
+        RawDocument {
+            id: doc.id,
+            project_id: doc.project_id,
+            owner_id: doc.owner_id,
+            name: doc.name,
+            doctype: doc.doctype,
+            version: doc.version,
+            body: doc.body,
+            created_at: doc.created_at,
+            updated_at: doc.updated_at,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub struct PgChange {
+    pub id: i32,
+    pub version: i32,
+    pub kind: String,
+    pub forward: Value,
+    pub reverse: Value,
+    pub document_id: Uuid,
+    pub created_at: DateTime,
+}
+
+impl From<PgChange> for Change {
+    fn from(change: PgChange) -> Change {
+        Change {
+            id: change.id,
+            document_id: change.document_id,
+            version: change.version,
+            kind: change.kind.parse().expect("Change kind to be valid"),
+            forward: change.forward,
+            reverse: change.reverse,
+            created_at: change.created_at,
+        }
+    }
+}
+
+impl Postgres {
+    async fn count(&self, table: &str, conditions: &Conditions) -> Result<i32, EngineError> {
+        let query = format!("SELECT COUNT(*) FROM {}{}", table, conditions.where_clause());
+        let (total,): (i64,) = conditions
+            .bind(sqlx::query_as(&query))
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(total as i32)
+    }
+
+    /// Delete row `id` of `table`, while it is at `expected_version` when
+    /// one is given
+    async fn delete(
+        &self,
+        table: &str,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut query = format!("DELETE FROM {} WHERE id = $1", table);
+        if expected_version.is_some() {
+            query.push_str(" AND version = $2");
+        }
+        let mut delete = sqlx::query(&query).bind(id);
+        if let Some(version) = expected_version {
+            delete = delete.bind(version);
+        }
+        let result = delete.execute(&mut tx).await?;
+
+        if result.rows_affected() == 0 {
+            let current: Option<(i32,)> =
+                sqlx::query_as(&format!("SELECT version FROM {} WHERE id = $1", table))
+                    .bind(id)
+                    .fetch_optional(&mut tx)
+                    .await?;
+            return Err(match (expected_version, current) {
+                (Some(expected_version), Some((current_version,))) => {
+                    EngineError::VersionMismatch(expected_version, current_version)
+                }
+                _ => EngineError::NotFound,
+            });
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Engine for Postgres {
+    async fn get_document(&self, id: &Uuid) -> Result<RawDocument, EngineError> {
+        let doc = sqlx::query_as::<_, PgDocument>("SELECT * FROM documents WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(doc.into())
+    }
+
+    async fn store_document(&self, doc: RawDocument) -> Result<(), EngineError> {
+        sqlx::query(
+            "
+        INSERT INTO documents (id, project_id, owner_id, name, doctype, version, body)
+        VALUES ($1, $2, $3, $4, $5, $6, $7);
+        ",
+        )
+        .bind(doc.id)
+        .bind(doc.project_id)
+        .bind(doc.owner_id)
+        .bind(doc.name)
+        .bind(doc.doctype)
+        .bind(doc.version)
+        .bind(doc.body)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_document(
+        &self,
+        doc: RawDocument,
+        change: Option<Change>,
+    ) -> Result<(), EngineError> {
+        let mut tx = self.pool.begin().await?;
+        let expected_version = doc.version - 1;
+
+        let result = sqlx::query(
+            "
+        UPDATE documents SET name=$1, version=$2, body=$3 WHERE id=$4 AND version=$5
+        ",
+        )
+        .bind(doc.name)
+        .bind(doc.version)
+        .bind(doc.body)
+        .bind(doc.id)
+        .bind(expected_version)
+        .execute(&mut tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            let (current_version,): (i32,) =
+                sqlx::query_as("SELECT version FROM documents WHERE id = $1")
+                    .bind(doc.id)
+                    .fetch_one(&mut tx)
+                    .await?;
+            return Err(EngineError::VersionMismatch(
+                expected_version,
+                current_version,
+            ));
+        }
+
+        if let Some(change) = change {
+            sqlx::query(
+                "
+            INSERT INTO changes (document_id, version, kind, forward, reverse)
+            VALUES ($1, $2, $3, $4, $5);
+            ",
+            )
+            .bind(change.document_id)
+            .bind(change.version)
+            .bind(change.kind.to_string())
+            .bind(change.forward)
+            .bind(change.reverse)
+            .execute(&mut tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_document(
+        &self,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError> {
+        self.delete("documents", id, expected_version).await
+    }
+
+    async fn get_document_changes(
+        &self,
+        document_id: &Uuid,
+        params: QueryRequest<ChangeFields>,
+    ) -> Result<QueryResponse<Change>, EngineError> {
+        let mut conditions = Conditions::new(Dialect::Postgres);
+        conditions.equals("document_id", Some(SqlValue::Uuid(*document_id)));
+        let mut selection = conditions.clone();
+        let page = page(&params, change_column, &mut selection)?;
+
+        let query = format!(
+            "
+        SELECT id, version, kind, forward, reverse, document_id, created_at
+        FROM changes{}{}
+        ",
+            selection.where_clause(),
+            page
+        );
+
+        let pgchanges = selection
+            .bind(sqlx::query_as::<_, PgChange>(&query))
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut changes: Vec<Change> = pgchanges.into_iter().map(|e| e.into()).collect();
+        if params.from_end() {
+            changes.reverse();
+        }
+        let (_, offset) = params.limit_offset();
+
+        Ok(QueryResponse::<Change> {
+            data: changes,
+            meta: QueryResponseMeta {
+                offset: Some(offset),
+                total: Some(self.count("changes", &conditions).await?),
+            },
+        })
+    }
+
+    async fn get_documents(
+        &self,
+        params: QueryRequest<DocumentFields>,
+        filter: DocumentFilter,
+    ) -> Result<QueryResponse<RawDocument>, EngineError> {
+        let conditions = Conditions::documents(Dialect::Postgres, filter);
+        let mut selection = conditions.clone();
+        let page = page(&params, document_column, &mut selection)?;
+        let query = format!("SELECT * FROM documents{}{}", selection.where_clause(), page);
+
+        let pgdocs = selection
+            .bind(sqlx::query_as::<_, PgDocument>(&query))
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut docs: Vec<RawDocument> = pgdocs.into_iter().map(|e| e.into()).collect();
+        if params.from_end() {
+            docs.reverse();
+        }
+        let (_, offset) = params.limit_offset();
+
+        Ok(QueryResponse::<RawDocument> {
+            data: docs,
+            meta: QueryResponseMeta {
+                offset: Some(offset),
+                total: Some(self.count("documents", &conditions).await?),
+            },
+        })
+    }
+
+    async fn get_projects(
+        &self,
+        params: QueryRequest<ProjectFields>,
+        filter: ProjectFilter,
+    ) -> Result<QueryResponse<Project>, EngineError> {
+        let conditions = Conditions::projects(Dialect::Postgres, filter);
+        let mut selection = conditions.clone();
+        let page = page(&params, project_column, &mut selection)?;
+        let query = format!("SELECT * FROM projects{}{}", selection.where_clause(), page);
+
+        let dbprojects = selection
+            .bind(sqlx::query_as::<_, DbProject>(&query))
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut projects: Vec<Project> = dbprojects.into_iter().map(|e| e.into()).collect();
+        if params.from_end() {
+            projects.reverse();
+        }
+        let (_, offset) = params.limit_offset();
+
+        Ok(QueryResponse::<Project> {
+            data: projects,
+            meta: QueryResponseMeta {
+                offset: Some(offset),
+                total: Some(self.count("projects", &conditions).await?),
+            },
+        })
+    }
+
+    async fn get_project(&self, id: &Uuid) -> Result<Project, EngineError> {
+        let doc = sqlx::query_as::<_, DbProject>("SELECT * FROM projects WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(doc.into())
+    }
+
+    async fn store_project(&self, doc: Project) -> Result<(), EngineError> {
+        sqlx::query(
+            "
+        INSERT INTO projects (id, owner_id, name, version, body)
+        VALUES ($1, $2, $3, $4, $5);
+        ",
+        )
+        .bind(doc.id)
+        .bind(doc.owner_id)
+        .bind(doc.name)
+        .bind(doc.version)
+        .bind(doc.body)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_project(&self, doc: Project) -> Result<(), EngineError> {
+        let mut tx = self.pool.begin().await?;
+        let expected_version = doc.version - 1;
+
+        let result = sqlx::query(
+            "
+        UPDATE projects SET name=$1, version=$2, body=$3 WHERE id=$4 AND version=$5
+        ",
+        )
+        .bind(doc.name)
+        .bind(doc.version)
+        .bind(doc.body)
+        .bind(doc.id)
+        .bind(expected_version)
+        .execute(&mut tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            let (current_version,): (i32,) =
+                sqlx::query_as("SELECT version FROM projects WHERE id = $1")
+                    .bind(doc.id)
+                    .fetch_one(&mut tx)
+                    .await?;
+            return Err(EngineError::VersionMismatch(
+                expected_version,
+                current_version,
+            ));
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_project(
+        &self,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError> {
+        self.delete("projects", id, expected_version).await
+    }
+
+    async fn get_project_documents(
+        &self,
+        project_id: &Uuid,
+        variant: DocType,
+    ) -> Result<Vec<RawDocument>, EngineError> {
+        let pgdocs = sqlx::query_as::<_, PgDocument>(
+            "SELECT * FROM documents WHERE doctype = $1 AND project_id = $2",
+        )
+        .bind(variant.to_string())
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(pgdocs.into_iter().map(|e| e.into()).collect())
+    }
+}
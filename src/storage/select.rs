@@ -0,0 +1,143 @@
+//! Filtering, sorting and paging of records held in memory, the way the
+//! SQL engines do it in their queries. Shared by the memory and file
+//! system engines.
+
+use crate::doc::common::DateTime;
+use crate::doc::document::RawDocument;
+use crate::doc::project::Project;
+use crate::storage::engine::{
+    Cursor, CursorValue, DateRange, Direction, DocumentFilter, EngineError, Keyed, ProjectFilter,
+    QueryRequest, QueryResponse, QueryResponseMeta, SortField,
+};
+
+use std::cmp::Ordering;
+
+/// Whether `text` contains `part`, ignoring ASCII case like SQL LIKE does
+fn contains(text: &str, part: &Option<String>) -> bool {
+    match part {
+        Some(part) => text
+            .to_ascii_lowercase()
+            .contains(&part.to_ascii_lowercase()),
+        None => true,
+    }
+}
+
+fn equals<T: PartialEq>(value: &T, expected: &Option<T>) -> bool {
+    expected
+        .as_ref()
+        .map(|expected| value == expected)
+        .unwrap_or(true)
+}
+
+fn within(date: &DateTime, range: &Option<DateRange>) -> bool {
+    match range {
+        Some(range) => {
+            range.from.map(|from| *date >= from).unwrap_or(true)
+                && range.to.map(|to| *date <= to).unwrap_or(true)
+        }
+        None => true,
+    }
+}
+
+pub fn project_matches(filter: &ProjectFilter, project: &Project) -> bool {
+    contains(&project.name, &filter.name_contains)
+        && equals(&project.owner_id, &filter.owner_id)
+        && within(&project.created_at, &filter.created)
+        && within(&project.updated_at, &filter.updated)
+}
+
+pub fn document_matches(filter: &DocumentFilter, doc: &RawDocument) -> bool {
+    contains(&doc.name, &filter.name_contains)
+        && equals(&doc.owner_id, &filter.owner_id)
+        && equals(&doc.project_id, &filter.project_id)
+        && equals(
+            &doc.doctype,
+            &filter.doctype.map(|doctype| doctype.to_string()),
+        )
+        && within(&doc.created_at, &filter.created)
+        && within(&doc.updated_at, &filter.updated)
+}
+
+/// Compare two positions in `ordering`, field by field
+fn compare<T>(ordering: &[(T, Direction)], a: &[CursorValue], b: &[CursorValue]) -> Ordering {
+    for ((_, direction), (a, b)) in ordering.iter().zip(a.iter().zip(b)) {
+        let ordering = match direction {
+            Direction::Asc => a.cmp(b),
+            Direction::Desc => b.cmp(a),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Sort, narrow down and page `records` the way the SQL engines do
+pub fn select<T, R>(
+    records: Vec<R>,
+    params: &QueryRequest<T>,
+) -> Result<QueryResponse<R>, EngineError>
+where
+    T: SortField + Default,
+    R: Keyed<T>,
+{
+    let ordering = params.ordering();
+    let position = |record: &R| -> Vec<CursorValue> {
+        ordering
+            .iter()
+            .map(|(field, _)| record.key(field))
+            .collect()
+    };
+    let check = |cursor: &Cursor| {
+        if cursor.0.len() == ordering.len() {
+            Ok(())
+        } else {
+            Err(EngineError::InvalidCursor)
+        }
+    };
+
+    let total = records.len() as i32;
+    let mut records: Vec<(Vec<CursorValue>, R)> = records
+        .into_iter()
+        .map(|record| (position(&record), record))
+        .collect();
+
+    if let Some(page) = &params.page {
+        if let Some(after) = &page.after {
+            check(after)?;
+            records.retain(|(key, _)| compare(&ordering, key, &after.0) == Ordering::Greater);
+        }
+        if let Some(before) = &page.before {
+            check(before)?;
+            records.retain(|(key, _)| compare(&ordering, key, &before.0) == Ordering::Less);
+        }
+    }
+    records.sort_by(|(a, _), (b, _)| compare(&ordering, a, b));
+
+    if params.from_end() {
+        records.reverse();
+    }
+    let (limit, offset) = params.limit_offset();
+    let limit = if limit == 0 {
+        usize::MAX
+    } else {
+        limit as usize
+    };
+    let mut data: Vec<R> = records
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit)
+        .map(|(_, record)| record)
+        .collect();
+    if params.from_end() {
+        data.reverse();
+    }
+
+    Ok(QueryResponse {
+        data,
+        meta: QueryResponseMeta {
+            offset: Some(offset),
+            total: Some(total),
+        },
+    })
+}
@@ -0,0 +1,291 @@
+//! Query building shared by the SQL engines. Columns only ever come from the
+//! mappings in this module and values are always passed as bound parameters.
+
+use crate::doc::change::ChangeFields;
+use crate::doc::common::DateTime;
+use crate::doc::document::DocumentFields;
+use crate::doc::project::ProjectFields;
+use crate::storage::engine::{
+    Cursor, CursorValue, DateRange, Direction, DocumentFilter, EngineError, ProjectFilter,
+    QueryRequest, SortField,
+};
+
+use sqlx::database::HasArguments;
+use sqlx::query::QueryAs;
+use sqlx::{Database, Encode, Type};
+use uuid::Uuid;
+
+/// The SQL flavour to generate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dialect {
+    Sqlite,
+    Postgres,
+}
+
+impl Dialect {
+    /// Case insensitive pattern matching, which is what LIKE means in SQLite
+    fn like(self) -> &'static str {
+        match self {
+            Dialect::Sqlite => "LIKE",
+            Dialect::Postgres => "ILIKE",
+        }
+    }
+
+    fn no_limit(self) -> &'static str {
+        match self {
+            // SQLite only accepts OFFSET after a LIMIT
+            Dialect::Sqlite => "-1",
+            Dialect::Postgres => "ALL",
+        }
+    }
+}
+
+pub fn project_column(field: &ProjectFields) -> &'static str {
+    match field {
+        ProjectFields::Id => "id",
+        ProjectFields::Name => "name",
+        ProjectFields::CreatedAt => "created_at",
+        ProjectFields::UpdatedAt => "updated_at",
+        ProjectFields::Version => "version",
+    }
+}
+
+pub fn document_column(field: &DocumentFields) -> &'static str {
+    match field {
+        DocumentFields::Id => "id",
+        DocumentFields::Name => "name",
+        DocumentFields::Doctype => "doctype",
+        DocumentFields::CreatedAt => "created_at",
+        DocumentFields::UpdatedAt => "updated_at",
+        DocumentFields::Version => "version",
+    }
+}
+
+pub fn change_column(field: &ChangeFields) -> &'static str {
+    match field {
+        ChangeFields::Id => "id",
+        // changes.version is declared as TEXT in SQLite
+        ChangeFields::Version => "CAST(version AS INTEGER)",
+        ChangeFields::CreatedAt => "created_at",
+    }
+}
+
+#[derive(Clone)]
+pub enum SqlValue {
+    Int(i64),
+    Text(String),
+    Uuid(Uuid),
+    DateTime(DateTime),
+}
+
+impl From<&CursorValue> for SqlValue {
+    fn from(value: &CursorValue) -> SqlValue {
+        match value {
+            CursorValue::Int(number) => SqlValue::Int(*number),
+            CursorValue::Text(text) => SqlValue::Text(text.clone()),
+            CursorValue::Uuid(id) => SqlValue::Uuid(*id),
+            CursorValue::DateTime(date) => SqlValue::DateTime(*date),
+        }
+    }
+}
+
+/// A WHERE clause compiled from a listing filter. Values are kept apart
+/// from the SQL and only ever passed as bound parameters.
+#[derive(Clone)]
+pub struct Conditions {
+    dialect: Dialect,
+    clauses: Vec<String>,
+    values: Vec<SqlValue>,
+}
+
+impl Conditions {
+    pub fn new(dialect: Dialect) -> Self {
+        Self {
+            dialect,
+            clauses: vec![],
+            values: vec![],
+        }
+    }
+
+    pub fn projects(dialect: Dialect, filter: ProjectFilter) -> Self {
+        let mut conditions = Self::new(dialect);
+        conditions.contains("name", &filter.name_contains);
+        conditions.equals("owner_id", filter.owner_id.map(SqlValue::Uuid));
+        conditions.within("created_at", &filter.created);
+        conditions.within("updated_at", &filter.updated);
+        conditions
+    }
+
+    pub fn documents(dialect: Dialect, filter: DocumentFilter) -> Self {
+        let mut conditions = Self::new(dialect);
+        conditions.contains("name", &filter.name_contains);
+        conditions.equals("owner_id", filter.owner_id.map(SqlValue::Uuid));
+        conditions.equals("project_id", filter.project_id.map(SqlValue::Uuid));
+        conditions.equals(
+            "doctype",
+            filter.doctype.map(|doctype| SqlValue::Text(doctype.to_string())),
+        );
+        conditions.within("created_at", &filter.created);
+        conditions.within("updated_at", &filter.updated);
+        conditions
+    }
+
+    pub fn equals(&mut self, column: &'static str, value: Option<SqlValue>) {
+        if let Some(value) = value {
+            self.clauses.push(format!("{} = ?", column));
+            self.values.push(value);
+        }
+    }
+
+    fn contains(&mut self, column: &'static str, text: &Option<String>) {
+        if let Some(text) = text {
+            let escaped = text
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            self.clauses.push(format!(
+                "{} {} ? ESCAPE '\\'",
+                column,
+                self.dialect.like()
+            ));
+            self.values.push(SqlValue::Text(format!("%{}%", escaped)));
+        }
+    }
+
+    fn within(&mut self, column: &'static str, range: &Option<DateRange>) {
+        if let Some(range) = range {
+            if let Some(from) = range.from {
+                self.clauses.push(format!("{} >= ?", column));
+                self.values.push(SqlValue::DateTime(from));
+            }
+            if let Some(to) = range.to {
+                self.clauses.push(format!("{} <= ?", column));
+                self.values.push(SqlValue::DateTime(to));
+            }
+        }
+    }
+
+    /// Select the records after (or before) `cursor` in `ordering`. This is
+    /// a tuple comparison spelled out per column, since columns can be
+    /// sorted in different directions.
+    fn beyond<T>(
+        &mut self,
+        ordering: &[(T, Direction)],
+        column: fn(&T) -> &'static str,
+        cursor: &Cursor,
+        after: bool,
+    ) -> Result<(), EngineError> {
+        if cursor.0.len() != ordering.len() {
+            return Err(EngineError::InvalidCursor);
+        }
+        let mut terms: Vec<String> = vec![];
+        for (idx, ((field, direction), value)) in ordering.iter().zip(&cursor.0).enumerate() {
+            let mut term: Vec<String> = ordering[..idx]
+                .iter()
+                .map(|(field, _)| format!("{} = ?", column(field)))
+                .collect();
+            let greater = (*direction == Direction::Asc) == after;
+            term.push(format!("{} {} ?", column(field), if greater { ">" } else { "<" }));
+            terms.push(format!("({})", term.join(" AND ")));
+
+            self.values.extend(cursor.0[..idx].iter().map(SqlValue::from));
+            self.values.push(value.into());
+        }
+        self.clauses.push(format!("({})", terms.join(" OR ")));
+        Ok(())
+    }
+
+    /// The WHERE clause, with placeholders numbered for Postgres. These are
+    /// the only placeholders in a query.
+    pub fn where_clause(&self) -> String {
+        if self.clauses.is_empty() {
+            return "".into();
+        }
+        let clause = format!(" WHERE {} ", self.clauses.join(" AND "));
+        match self.dialect {
+            Dialect::Sqlite => clause,
+            Dialect::Postgres => {
+                let mut parts = clause.split('?');
+                let mut numbered = parts.next().unwrap_or_default().to_string();
+                for (idx, part) in parts.enumerate() {
+                    numbered.push_str(&format!("${}{}", idx + 1, part));
+                }
+                numbered
+            }
+        }
+    }
+
+    pub fn bind<'q, DB, O>(
+        &'q self,
+        mut query: QueryAs<'q, DB, O, <DB as HasArguments<'q>>::Arguments>,
+    ) -> QueryAs<'q, DB, O, <DB as HasArguments<'q>>::Arguments>
+    where
+        DB: Database,
+        i64: Encode<'q, DB> + Type<DB>,
+        &'q str: Encode<'q, DB> + Type<DB>,
+        Uuid: Encode<'q, DB> + Type<DB>,
+        DateTime: Encode<'q, DB> + Type<DB>,
+    {
+        for value in &self.values {
+            query = match value {
+                SqlValue::Int(number) => query.bind(*number),
+                SqlValue::Text(text) => query.bind(text.as_str()),
+                SqlValue::Uuid(id) => query.bind(*id),
+                SqlValue::DateTime(date) => query.bind(*date),
+            };
+        }
+        query
+    }
+}
+
+/// Narrow `conditions` down to the cursors of the requested page and build
+/// its ORDER BY, LIMIT and OFFSET. When the page counts from the end the
+/// order is reversed, and the caller has to reverse the rows it gets back.
+pub fn page<T>(
+    params: &QueryRequest<T>,
+    column: fn(&T) -> &'static str,
+    conditions: &mut Conditions,
+) -> Result<String, EngineError>
+where
+    T: SortField + Default,
+{
+    let ordering = params.ordering();
+    if let Some(page) = &params.page {
+        if let Some(after) = &page.after {
+            conditions.beyond(&ordering, column, after, true)?;
+        }
+        if let Some(before) = &page.before {
+            conditions.beyond(&ordering, column, before, false)?;
+        }
+    }
+
+    let terms: Vec<String> = ordering
+        .iter()
+        .map(|(field, direction)| {
+            let ascending = (*direction == Direction::Asc) != params.from_end();
+            format!("{} {}", column(field), if ascending { "ASC" } else { "DESC" })
+        })
+        .collect();
+
+    let (limit, offset) = params.limit_offset();
+    let limit = if limit == 0 {
+        conditions.dialect.no_limit().to_string()
+    } else {
+        limit.to_string()
+    };
+    Ok(format!(
+        " ORDER BY {} LIMIT {} OFFSET {} ",
+        terms.join(", "),
+        limit,
+        offset
+    ))
+}
+
+impl From<sqlx::Error> for EngineError {
+    fn from(err: sqlx::Error) -> EngineError {
+        match &err {
+            sqlx::Error::RowNotFound => EngineError::NotFound,
+            _ => EngineError::Storage(err.to_string()),
+        }
+    }
+}
@@ -1,11 +1,17 @@
-use crate::doc::change::Change;
-use crate::doc::document::{DocType, RawDocument};
+use crate::doc::change::{Change, ChangeFields};
+use crate::doc::document::{DocType, DocumentFields, RawDocument};
 use crate::doc::project::{Project, ProjectFields};
-use crate::storage::engine::{Engine, EngineError, QueryRequest, QueryResponse, QueryResponseMeta};
+use crate::storage::engine::{
+    DocumentFilter, Engine, EngineError, ProjectFilter, QueryRequest, QueryResponse,
+    QueryResponseMeta,
+};
+use crate::storage::sql::{
+    change_column, document_column, page, project_column, Conditions, Dialect, SqlValue,
+};
 
 use async_trait::async_trait;
 // use sqlx::sqlite::{SqlitePool, SqliteConnectOptions, SqliteJournalMode};
-use sqlx::sqlite::{SqlitePool};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
 use std::fs::File;
 use std::path::Path;
 use uuid::Uuid;
@@ -21,7 +27,7 @@ pub struct Sqlite {
 impl Sqlite {
     pub async fn setup(url: String) -> Result<Self, sqlx::Error> {
         if url != ":memory:" && !Path::new(&url).exists() {
-            println!("sqlite: file does not exist, creating it");
+            log::info!("sqlite: file does not exist, creating it");
             let _ = File::create(&url)?;
         }
         let pool = SqlitePool::connect(&url).await?;
@@ -36,6 +42,15 @@ impl Sqlite {
         Ok(Self { url, pool })
     }
 
+    /// Connect to the database file `path`, which has to exist already
+    pub async fn open(path: String, read_only: bool) -> Result<Self, sqlx::Error> {
+        let options = SqliteConnectOptions::new()
+            .filename(&path)
+            .read_only(read_only);
+        let pool = SqlitePool::connect_with(options).await?;
+        Ok(Self { url: path, pool })
+    }
+
     pub async fn migrate(&self) -> Result<(), sqlx::Error> {
         sqlx::migrate!("migrations/sqlite").run(&self.pool).await?;
         Ok(())
@@ -146,9 +161,11 @@ impl From<&DbProject> for Project {
 pub struct DbChange {
     pub id: i32,
     pub version: i32,
+    pub kind: String,
     pub forward: String,
     pub reverse: String,
     pub document_id: Uuid,
+    pub created_at: DateTime,
 }
 
 impl From<Change> for DbChange {
@@ -156,19 +173,66 @@ impl From<Change> for DbChange {
         DbChange {
             id: change.id,
             version: change.version,
+            kind: change.kind.to_string(),
             forward: serde_json::to_string(&change.forward).expect("Patch to be serializable"),
             reverse: serde_json::to_string(&change.reverse).expect("Patch to be serializable"),
             document_id: change.document_id,
+            created_at: change.created_at,
+        }
+    }
+}
+
+impl From<&DbChange> for Change {
+    fn from(change: &DbChange) -> Change {
+        Change {
+            id: change.id,
+            document_id: change.document_id,
+            version: change.version,
+            kind: change.kind.parse().expect("Change kind to be valid"),
+            forward: serde_json::from_str(&change.forward).expect("Patch to be deserializable"),
+            reverse: serde_json::from_str(&change.reverse).expect("Patch to be deserializable"),
+            created_at: change.created_at,
         }
     }
 }
 
-impl From<sqlx::Error> for EngineError {
-    fn from(err: sqlx::Error) -> EngineError {
-        match &err {
-            sqlx::Error::RowNotFound => EngineError::NotFound,
-            _ => EngineError::Storage(err.to_string()),
+impl Sqlite {
+    /// Delete row `id` of `table`, while it is at `expected_version` when
+    /// one is given
+    async fn delete(
+        &self,
+        table: &str,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut query = format!("DELETE FROM {} WHERE id=?", table);
+        if expected_version.is_some() {
+            query.push_str(" AND version=?");
+        }
+        let mut delete = sqlx::query(&query).bind(id);
+        if let Some(version) = expected_version {
+            delete = delete.bind(version);
         }
+        let result = delete.execute(&mut tx).await?;
+
+        if result.rows_affected() == 0 {
+            let current: Option<(i32,)> =
+                sqlx::query_as(&format!("SELECT version FROM {} WHERE id=?", table))
+                    .bind(id)
+                    .fetch_optional(&mut tx)
+                    .await?;
+            return Err(match (expected_version, current) {
+                (Some(expected_version), Some((current_version,))) => {
+                    EngineError::VersionMismatch(expected_version, current_version)
+                }
+                _ => EngineError::NotFound,
+            });
+        }
+
+        tx.commit().await?;
+        Ok(())
     }
 }
 
@@ -211,29 +275,44 @@ impl Engine for Sqlite {
         let mut tx = self.pool.begin().await?;
 
         let doc: DbDocument = doc.into();
+        let expected_version = doc.version - 1;
 
-        let _result = sqlx::query(
+        let result = sqlx::query(
             "
-        UPDATE documents SET name=?, version=?, body=? WHERE id=?
+        UPDATE documents SET name=?, version=?, body=? WHERE id=? AND version=?
         ",
         )
         .bind(doc.name)
         .bind(doc.version)
         .bind(doc.body)
         .bind(doc.id)
+        .bind(expected_version)
         .execute(&mut tx)
         .await?;
 
+        if result.rows_affected() == 0 {
+            let (current_version,): (i32,) =
+                sqlx::query_as("SELECT version FROM documents WHERE id = ?")
+                    .bind(doc.id)
+                    .fetch_one(&mut tx)
+                    .await?;
+            return Err(EngineError::VersionMismatch(
+                expected_version,
+                current_version,
+            ));
+        }
+
         if let Some(change) = change {
             let dbchange: DbChange = change.into();
             let _result = sqlx::query(
                 "
-            INSERT INTO changes (document_id, version, forward, reverse)
-            VALUES (?, ?, ?, ?);
+            INSERT INTO changes (document_id, version, kind, forward, reverse)
+            VALUES (?, ?, ?, ?, ?);
             ",
             )
             .bind(dbchange.document_id)
             .bind(dbchange.version)
+            .bind(dbchange.kind)
             .bind(dbchange.forward)
             .bind(dbchange.reverse)
             .execute(&mut tx)
@@ -245,68 +324,129 @@ impl Engine for Sqlite {
         Ok(())
     }
 
-    async fn delete_document(&self, id: &Uuid) -> Result<(), EngineError> {
-        sqlx::query("DELETE FROM documents WHERE id=?")
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
+    async fn delete_document(
+        &self,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError> {
+        self.delete("documents", id, expected_version).await
     }
 
-    async fn get_projects(
+    async fn get_document_changes(
         &self,
-        params: QueryRequest<ProjectFields>,
-    ) -> Result<QueryResponse<Project>, EngineError> {
-
-        use std::fmt::Write;
-        let mut query = "SELECT * FROM projects".to_string();
-        let query_agg = "SELECT COUNT(*) FROM projects".to_string();
+        document_id: &Uuid,
+        params: QueryRequest<ChangeFields>,
+    ) -> Result<QueryResponse<Change>, EngineError> {
+        let mut conditions = Conditions::new(Dialect::Sqlite);
+        conditions.equals("document_id", Some(SqlValue::Uuid(*document_id)));
+        let mut selection = conditions.clone();
+        let page = page(&params, change_column, &mut selection)?;
+
+        // changes.version is declared as TEXT
+        let query = format!(
+            "
+        SELECT id, CAST(version AS INTEGER) AS version, kind, forward, reverse, document_id,
+            created_at
+        FROM changes{}{}
+        ",
+            selection.where_clause(),
+            page
+        );
 
-        let mut limit = 100;
-        let mut offset = 0;
+        let dbchanges = selection
+            .bind(sqlx::query_as::<_, DbChange>(&query))
+            .fetch_all(&self.pool)
+            .await?;
 
-        match params.page {
-            Some(page) => {
-                match page.limit {
-                    Some(l) => {
-                        limit = l
-                    }
-                    None => { }
-                }
-                match page.offset {
-                    Some(o) => {
-                        offset = o
-                    }
-                    None => { }
-                }
-            }
-            None => { }
+        let mut changes: Vec<Change> = dbchanges.iter().map(|e| e.into()).collect();
+        if params.from_end() {
+            changes.reverse();
         }
 
-        if limit != 0 {
-            write!(query, " LIMIT {} ", limit).expect("String operation to succeed");
+        let count_query = format!("SELECT COUNT(*) FROM changes{}", conditions.where_clause());
+        let (total,): (i32,) = conditions
+            .bind(sqlx::query_as(&count_query))
+            .fetch_one(&self.pool)
+            .await?;
+        let (_, offset) = params.limit_offset();
+
+        Ok(QueryResponse::<Change> {
+            data: changes,
+            meta: QueryResponseMeta {
+                offset: Some(offset),
+                total: Some(total),
+            },
+        })
+    }
+
+    async fn get_documents(
+        &self,
+        params: QueryRequest<DocumentFields>,
+        filter: DocumentFilter,
+    ) -> Result<QueryResponse<RawDocument>, EngineError> {
+        let conditions = Conditions::documents(Dialect::Sqlite, filter);
+        let mut selection = conditions.clone();
+        let page = page(&params, document_column, &mut selection)?;
+        let query = format!("SELECT * FROM documents{}{}", selection.where_clause(), page);
+
+        let dbdocs = selection
+            .bind(sqlx::query_as::<_, DbDocument>(&query))
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut docs: Vec<RawDocument> = dbdocs.iter().map(|e| e.into()).collect();
+        if params.from_end() {
+            docs.reverse();
         }
-        write!(query, " OFFSET {} ", offset).expect("String operation to succeed");
 
-        println!("Query {}", &query);
+        let count_query = format!("SELECT COUNT(*) FROM documents{}", conditions.where_clause());
+        let (total,): (i32,) = conditions
+            .bind(sqlx::query_as(&count_query))
+            .fetch_one(&self.pool)
+            .await?;
+        let (_, offset) = params.limit_offset();
+
+        Ok(QueryResponse::<RawDocument> {
+            data: docs,
+            meta: QueryResponseMeta {
+                offset: Some(offset),
+                total: Some(total),
+            },
+        })
+    }
+
+    async fn get_projects(
+        &self,
+        params: QueryRequest<ProjectFields>,
+        filter: ProjectFilter,
+    ) -> Result<QueryResponse<Project>, EngineError> {
+        let conditions = Conditions::projects(Dialect::Sqlite, filter);
+        let mut selection = conditions.clone();
+        let page = page(&params, project_column, &mut selection)?;
+        let query = format!("SELECT * FROM projects{}{}", selection.where_clause(), page);
 
-        let dbdocs = sqlx::query_as::<_, DbProject>(&query)
+        let dbdocs = selection
+            .bind(sqlx::query_as::<_, DbProject>(&query))
             .fetch_all(&self.pool)
             .await?;
 
-        let docs: Vec<Project> = dbdocs.iter().map(|e| e.into()).collect();
+        let mut docs: Vec<Project> = dbdocs.iter().map(|e| e.into()).collect();
+        if params.from_end() {
+            docs.reverse();
+        }
 
-        let agg = sqlx::query!(
-            "SELECT COUNT(*) as count FROM projects"
-        )
+        let count_query = format!("SELECT COUNT(*) FROM projects{}", conditions.where_clause());
+        let (total,): (i32,) = conditions
+            .bind(sqlx::query_as(&count_query))
             .fetch_one(&self.pool)
             .await?;
+        let (_, offset) = params.limit_offset();
 
         Ok(QueryResponse::<Project> {
             data: docs,
             meta: QueryResponseMeta {
                 offset: Some(offset),
-                total: Some(agg.count),
+                total: Some(total),
             },
         })
     }
@@ -338,43 +478,44 @@ impl Engine for Sqlite {
     }
 
     async fn update_project(&self, doc: Project) -> Result<(), EngineError> {
-        let _result = sqlx::query!(
-            "
-        UPDATE projects SET name=?, version=?, body=? WHERE id=?
-        ",
-            doc.name,
-            doc.version,
-            doc.body,
-            doc.id,
-        )
-        .execute(&self.pool)
-        .await?;
-        Ok(())
-    }
+        let mut tx = self.pool.begin().await?;
+        let expected_version = doc.version - 1;
 
-    /*
-    async fn update_project(&self, doc: Project) -> Result<(), EngineError> {
-        let _result = sqlx::query(
+        let result = sqlx::query(
             "
-        UPDATE projects SET name=?, version=?, body=? WHERE id=?
+        UPDATE projects SET name=?, version=?, body=? WHERE id=? AND version=?
         ",
         )
         .bind(doc.name)
         .bind(doc.version)
         .bind(doc.body)
         .bind(doc.id)
-        .execute(&self.pool)
+        .bind(expected_version)
+        .execute(&mut tx)
         .await?;
+
+        if result.rows_affected() == 0 {
+            let (current_version,): (i32,) =
+                sqlx::query_as("SELECT version FROM projects WHERE id = ?")
+                    .bind(doc.id)
+                    .fetch_one(&mut tx)
+                    .await?;
+            return Err(EngineError::VersionMismatch(
+                expected_version,
+                current_version,
+            ));
+        }
+
+        tx.commit().await?;
         Ok(())
     }
-    */
 
-    async fn delete_project(&self, id: &Uuid) -> Result<(), EngineError> {
-        sqlx::query("DELETE FROM projects WHERE id=?")
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
+    async fn delete_project(
+        &self,
+        id: &Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), EngineError> {
+        self.delete("projects", id, expected_version).await
     }
 
     async fn get_project_documents(
@@ -403,7 +544,7 @@ mod test {
         let storage = super::Sqlite::setup(":memory:".into())
             .await
             .expect("Database to be initialized in memory");
-        let _ = storage.migrate().await.expect("Database to be migrated");
+        storage.migrate().await.expect("Database to be migrated");
         Ok(())
     }
 }
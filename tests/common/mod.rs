@@ -0,0 +1,645 @@
+//! Behaviour every `Engine` has to share, run against each implementation
+//! by the storage test files.
+
+use conduit::storage::engine::Engine;
+
+pub async fn engine_functions<E: Engine + 'static>(storage: E) -> std::io::Result<()> {
+    use conduit::doc::document::{DigraphDocument, RawDocument};
+    use conduit::doc::project::Project;
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let doc = DigraphDocument::create(&project);
+
+    let raw_doc: RawDocument = doc.clone().into();
+
+    storage
+        .store_document(raw_doc.clone())
+        .await
+        .expect("The document to be inserted");
+
+    let retrieved_raw_doc = storage
+        .get_document(&doc.id)
+        .await
+        .expect("The stored document to be retrieved");
+
+    assert_eq!(raw_doc.id, retrieved_raw_doc.id);
+    assert_eq!(raw_doc.project_id, retrieved_raw_doc.project_id);
+    assert_eq!(raw_doc.owner_id, retrieved_raw_doc.owner_id);
+    assert_eq!(raw_doc.name, retrieved_raw_doc.name);
+    assert_eq!(raw_doc.doctype, retrieved_raw_doc.doctype);
+    assert_eq!(raw_doc.version, retrieved_raw_doc.version);
+    assert_eq!(raw_doc.body, retrieved_raw_doc.body);
+
+    let doc_retrieved: DigraphDocument = retrieved_raw_doc.into();
+
+    assert_eq!(doc.id, doc_retrieved.id);
+    assert_eq!(doc.project_id, doc_retrieved.project_id);
+    assert_eq!(doc.owner_id, doc_retrieved.owner_id);
+    assert_eq!(doc.name, doc_retrieved.name);
+    assert_eq!(doc.doctype, doc_retrieved.doctype);
+    assert_eq!(doc.version, doc_retrieved.version);
+    assert_eq!(doc.body, doc_retrieved.body);
+
+    Ok(())
+}
+
+pub async fn cascading_deletes<E: Engine + 'static>(storage: E) -> std::io::Result<()> {
+    use conduit::doc::document::DigraphDocument;
+    use conduit::doc::project::Project;
+    use conduit::storage::engine::EngineError;
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    let project_id = project.id;
+
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let doc = DigraphDocument::create(&project);
+
+    storage
+        .store_document(doc.clone().into())
+        .await
+        .expect("The document to be inserted");
+
+    assert_eq!(
+        storage.delete_project(&project.id, Some(1)).await,
+        Err(EngineError::VersionMismatch(1, 0))
+    );
+    storage
+        .get_project(&project_id)
+        .await
+        .expect("The project to be kept");
+    storage
+        .delete_project(&project.id, Some(0))
+        .await
+        .expect("Project to be deleted from database");
+
+    assert_eq!(
+        storage.get_project(&project_id).await,
+        Err(EngineError::NotFound)
+    );
+    assert_eq!(
+        storage.get_document(&doc.id).await,
+        Err(EngineError::NotFound)
+    );
+
+    Ok(())
+}
+
+pub async fn undo_redo<E: Engine + 'static>(storage: E) -> std::io::Result<()> {
+    use conduit::doc::change::ChangeKind;
+    use conduit::doc::document::{DigraphDocument, RawDocument};
+    use conduit::doc::project::Project;
+    use conduit::storage::engine::{EngineContainer, EngineError, QueryRequest};
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let doc = DigraphDocument::create(&project);
+    let doc_id = doc.id;
+
+    storage
+        .store_document(doc.clone().into())
+        .await
+        .expect("The document to be inserted");
+
+    let engine = EngineContainer::new(storage);
+
+    for _ in 0..2 {
+        let mut doc: DigraphDocument = engine
+            .get_document(&doc_id)
+            .await
+            .expect("The document to be retrieved")
+            .into();
+        doc.body.add_node(None).expect("A node to be added");
+        let mut raw_doc: RawDocument = doc.into();
+        engine
+            .update_document(&mut raw_doc)
+            .await
+            .expect("The document to be updated");
+    }
+
+    let undone: DigraphDocument = engine
+        .undo_document(&doc_id)
+        .await
+        .expect("The last change to be undone")
+        .into();
+    assert_eq!(undone.version, 3);
+    assert_eq!(undone.body.nodes.len(), 1);
+
+    let undone: DigraphDocument = engine
+        .undo_document(&doc_id)
+        .await
+        .expect("The first change to be undone")
+        .into();
+    assert_eq!(undone.version, 4);
+    assert_eq!(undone.body.nodes.len(), 0);
+
+    assert_eq!(
+        engine.undo_document(&doc_id).await,
+        Err(EngineError::NothingToUndo)
+    );
+
+    let redone: DigraphDocument = engine
+        .redo_document(&doc_id)
+        .await
+        .expect("The first change to be redone")
+        .into();
+    assert_eq!(redone.version, 5);
+    assert_eq!(redone.body.nodes.len(), 1);
+
+    let stored: DigraphDocument = engine
+        .get_document(&doc_id)
+        .await
+        .expect("The document to be retrieved")
+        .into();
+    assert_eq!(stored.version, 5);
+    assert_eq!(stored.body.nodes.len(), 1);
+
+    let mut stored: RawDocument = stored.into();
+    engine
+        .update_document(&mut stored)
+        .await
+        .expect("The document to be updated");
+
+    assert_eq!(
+        engine.redo_document(&doc_id).await,
+        Err(EngineError::NothingToRedo)
+    );
+
+    let kinds: Vec<ChangeKind> = engine
+        .get_document_changes(&doc_id, QueryRequest::all())
+        .await
+        .expect("The changes to be retrieved")
+        .data
+        .iter()
+        .map(|change| change.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ChangeKind::Edit,
+            ChangeKind::Edit,
+            ChangeKind::Undo,
+            ChangeKind::Undo,
+            ChangeKind::Redo,
+            ChangeKind::Edit
+        ]
+    );
+
+    Ok(())
+}
+
+pub async fn update_version_check<E: Engine + 'static>(storage: E) -> std::io::Result<()> {
+    use conduit::doc::document::{DigraphDocument, RawDocument};
+    use conduit::doc::project::Project;
+    use conduit::storage::engine::EngineError;
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let doc = DigraphDocument::create(&project);
+
+    storage
+        .store_document(doc.clone().into())
+        .await
+        .expect("The document to be inserted");
+
+    // Two writers that both read the document at version 0
+    let mut first: RawDocument = doc.clone().into();
+    first.name = "First".into();
+    first.change();
+    let mut second: RawDocument = doc.clone().into();
+    second.name = "Second".into();
+    second.change();
+
+    storage
+        .update_document(first, None)
+        .await
+        .expect("The first update to be stored");
+
+    assert_eq!(
+        storage.update_document(second, None).await,
+        Err(EngineError::VersionMismatch(0, 1))
+    );
+
+    let stored = storage
+        .get_document(&doc.id)
+        .await
+        .expect("The stored document to be retrieved");
+    assert_eq!(stored.name, "First");
+    assert_eq!(stored.version, 1);
+
+    let mut missing: RawDocument = DigraphDocument::create(&project).into();
+    missing.change();
+    assert_eq!(
+        storage.update_document(missing, None).await,
+        Err(EngineError::NotFound)
+    );
+
+    Ok(())
+}
+
+pub async fn get_projects_sorted<E: Engine + 'static>(storage: E) -> std::io::Result<()> {
+    use conduit::doc::project::{Project, ProjectFields};
+    use conduit::storage::engine::{Direction, ProjectFilter, QueryRequest};
+
+    for (name, version) in &[("b", 1), ("c", 0), ("a", 1)] {
+        let mut project = Project::new(conduit::util::naming::empty_uuid());
+        project.name = name.to_string();
+        storage
+            .store_project(project.clone())
+            .await
+            .expect("The project to be inserted");
+        if *version > 0 {
+            project.change();
+            storage
+                .update_project(project)
+                .await
+                .expect("The project to be updated");
+        }
+    }
+
+    let names = |sort: Vec<(ProjectFields, Direction)>| {
+        let storage = &storage;
+        async move {
+            storage
+                .get_projects(
+                    QueryRequest {
+                        sort: Some(sort),
+                        ..Default::default()
+                    },
+                    ProjectFilter::default(),
+                )
+                .await
+                .expect("The projects to be retrieved")
+                .data
+                .into_iter()
+                .map(|project| project.name)
+                .collect::<Vec<String>>()
+        }
+    };
+
+    assert_eq!(
+        names(vec![(ProjectFields::Name, Direction::Asc)]).await,
+        vec!["a", "b", "c"]
+    );
+    assert_eq!(
+        names(vec![(ProjectFields::Name, Direction::Desc)]).await,
+        vec!["c", "b", "a"]
+    );
+    assert_eq!(
+        names(vec![
+            (ProjectFields::Version, Direction::Desc),
+            (ProjectFields::Name, Direction::Asc)
+        ])
+        .await,
+        vec!["a", "b", "c"]
+    );
+
+    Ok(())
+}
+
+pub async fn get_projects_paged<E: Engine + 'static>(storage: E) -> std::io::Result<()> {
+    use conduit::doc::project::{Project, ProjectFields};
+    use conduit::storage::engine::{
+        Cursor, Direction, EngineError, Pagination, ProjectFilter, QueryRequest,
+    };
+
+    for (name, version) in &[("d", 0), ("b", 1), ("e", 1), ("c", 0), ("a", 1)] {
+        let mut project = Project::new(conduit::util::naming::empty_uuid());
+        project.name = name.to_string();
+        storage
+            .store_project(project.clone())
+            .await
+            .expect("The project to be inserted");
+        if *version > 0 {
+            project.change();
+            storage
+                .update_project(project)
+                .await
+                .expect("The project to be updated");
+        }
+    }
+
+    let request = |after: Option<Cursor>, before: Option<Cursor>, from_end: bool| QueryRequest {
+        page: Some(Pagination {
+            limit: Some(2),
+            offset: Some(0),
+            after,
+            before,
+            from_end,
+        }),
+        sort: Some(vec![
+            (ProjectFields::Version, Direction::Desc),
+            (ProjectFields::Name, Direction::Asc),
+        ]),
+    };
+
+    let mut names: Vec<String> = vec![];
+    let mut after = None;
+    loop {
+        let params = request(after, None, false);
+        let page = storage
+            .get_projects(params.clone(), ProjectFilter::default())
+            .await
+            .expect("The projects to be retrieved");
+        assert_eq!(page.meta.total, Some(5));
+        match page.data.last() {
+            Some(last) => after = Some(params.cursor(last)),
+            None => break,
+        }
+        names.extend(page.data.into_iter().map(|project| project.name));
+    }
+    assert_eq!(names, vec!["a", "b", "e", "c", "d"]);
+
+    let params = request(None, None, true);
+    let last_page = storage
+        .get_projects(params.clone(), ProjectFilter::default())
+        .await
+        .expect("The projects to be retrieved")
+        .data;
+    let last_names: Vec<&str> = last_page
+        .iter()
+        .map(|project| project.name.as_str())
+        .collect();
+    assert_eq!(last_names, vec!["c", "d"]);
+
+    let before = Some(params.cursor(&last_page[0]));
+    let previous_names: Vec<String> = storage
+        .get_projects(request(None, before, true), ProjectFilter::default())
+        .await
+        .expect("The projects to be retrieved")
+        .data
+        .into_iter()
+        .map(|project| project.name)
+        .collect();
+    assert_eq!(previous_names, vec!["b", "e"]);
+
+    assert_eq!(
+        storage
+            .get_projects(
+                request(Some(Cursor(vec![])), None, false),
+                ProjectFilter::default()
+            )
+            .await,
+        Err(EngineError::InvalidCursor)
+    );
+
+    Ok(())
+}
+
+pub async fn filters<E: Engine + 'static>(storage: E) -> std::io::Result<()> {
+    use conduit::doc::document::{DigraphDocument, DocType};
+    use conduit::doc::project::Project;
+    use conduit::storage::engine::{DateRange, DocumentFilter, ProjectFilter, QueryRequest};
+    use uuid::Uuid;
+
+    let owner_id = Uuid::new_v4();
+    let mut projects = vec![];
+    for name in &["Data pipelines", "Pipeline 100%", "Org chart"] {
+        let mut project = Project::new(conduit::util::naming::empty_uuid());
+        project.name = name.to_string();
+        if name.starts_with("Org") {
+            project.owner_id = owner_id;
+        }
+        storage
+            .store_project(project.clone())
+            .await
+            .expect("The project to be inserted");
+        projects.push(project);
+    }
+
+    for project in &projects {
+        let mut doc = DigraphDocument::create(project);
+        doc.name = format!("{} graph", project.name);
+        storage
+            .store_document(doc.into())
+            .await
+            .expect("The document to be inserted");
+    }
+
+    let project_names = |filter: ProjectFilter| {
+        let storage = &storage;
+        async move {
+            storage
+                .get_projects(QueryRequest::default(), filter)
+                .await
+                .expect("The projects to be retrieved")
+                .data
+                .into_iter()
+                .map(|project| project.name)
+                .collect::<Vec<String>>()
+        }
+    };
+
+    assert_eq!(
+        project_names(ProjectFilter {
+            name_contains: Some("pipeline".into()),
+            ..Default::default()
+        })
+        .await
+        .len(),
+        2
+    );
+    assert_eq!(
+        project_names(ProjectFilter {
+            name_contains: Some("100%".into()),
+            ..Default::default()
+        })
+        .await,
+        vec!["Pipeline 100%"]
+    );
+    assert_eq!(
+        project_names(ProjectFilter {
+            name_contains: Some("%".into()),
+            owner_id: Some(owner_id),
+            ..Default::default()
+        })
+        .await,
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        project_names(ProjectFilter {
+            owner_id: Some(owner_id),
+            ..Default::default()
+        })
+        .await,
+        vec!["Org chart"]
+    );
+
+    let yesterday = chrono::Utc::now() - chrono::Duration::days(1);
+    let tomorrow = chrono::Utc::now() + chrono::Duration::days(1);
+
+    assert_eq!(
+        project_names(ProjectFilter {
+            created: Some(DateRange {
+                from: Some(yesterday),
+                to: Some(tomorrow),
+            }),
+            ..Default::default()
+        })
+        .await
+        .len(),
+        3
+    );
+    assert_eq!(
+        project_names(ProjectFilter {
+            updated: Some(DateRange {
+                from: Some(tomorrow),
+                to: None,
+            }),
+            ..Default::default()
+        })
+        .await
+        .len(),
+        0
+    );
+
+    let response = storage
+        .get_documents(
+            QueryRequest::default(),
+            DocumentFilter {
+                project_id: Some(projects[2].id),
+                doctype: Some(DocType::Digraph),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("The documents to be retrieved");
+    assert_eq!(response.meta.total, Some(1));
+    assert_eq!(response.data[0].name, "Org chart graph");
+
+    let response = storage
+        .get_documents(
+            QueryRequest::default(),
+            DocumentFilter {
+                name_contains: Some("PIPELINE".into()),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("The documents to be retrieved");
+    assert_eq!(response.meta.total, Some(2));
+
+    Ok(())
+}
+
+pub async fn changes_and_deletes<E: Engine + 'static>(storage: E) -> std::io::Result<()> {
+    use conduit::doc::document::{DigraphDocument, RawDocument};
+    use conduit::doc::project::Project;
+    use conduit::storage::engine::{EngineContainer, EngineError, QueryRequest};
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let kept = DigraphDocument::create(&project);
+    let removed = DigraphDocument::create(&project);
+    for doc in &[&kept, &removed] {
+        storage
+            .store_document((*doc).clone().into())
+            .await
+            .expect("The document to be inserted");
+    }
+    assert!(storage.store_document(kept.clone().into()).await.is_err());
+
+    let engine = EngineContainer::new(storage);
+    for doc in &[&kept, &removed] {
+        for _ in 0..2 {
+            let mut doc: DigraphDocument = engine
+                .get_document(&doc.id)
+                .await
+                .expect("The document to be retrieved")
+                .into();
+            doc.body.add_node(None).expect("A node to be added");
+            let mut raw_doc: RawDocument = doc.into();
+            engine
+                .update_document(&mut raw_doc)
+                .await
+                .expect("The document to be updated");
+        }
+    }
+
+    let changes = engine
+        .get_document_changes(&kept.id, QueryRequest::all())
+        .await
+        .expect("The changes to be retrieved");
+    assert_eq!(changes.meta.total, Some(2));
+    let versions: Vec<i32> = changes.data.iter().map(|change| change.version).collect();
+    assert_eq!(versions, vec![1, 2]);
+    assert!(changes
+        .data
+        .iter()
+        .all(|change| change.document_id == kept.id));
+
+    let first: DigraphDocument = engine
+        .get_document_at_version(&kept.id, 1)
+        .await
+        .expect("The first version to be rebuilt")
+        .into();
+    assert_eq!(first.body.nodes.len(), 1);
+    assert_eq!(
+        engine.get_document_at_version(&kept.id, 3).await,
+        Err(EngineError::NotFound)
+    );
+
+    assert_eq!(
+        engine.delete_document(&removed.id, Some(1)).await,
+        Err(EngineError::VersionMismatch(1, 2))
+    );
+    engine
+        .delete_document(&removed.id, Some(2))
+        .await
+        .expect("The document to be deleted");
+    assert_eq!(
+        engine.get_document(&removed.id).await,
+        Err(EngineError::NotFound)
+    );
+    assert_eq!(
+        engine.delete_document(&removed.id, None).await,
+        Err(EngineError::NotFound)
+    );
+    let changes = engine
+        .get_document_changes(&removed.id, QueryRequest::all())
+        .await
+        .expect("The changes to be retrieved");
+    assert_eq!(changes.meta.total, Some(0));
+
+    engine
+        .delete_project(&project.id, None)
+        .await
+        .expect("The project to be deleted");
+    assert_eq!(
+        engine.delete_project(&project.id, None).await,
+        Err(EngineError::NotFound)
+    );
+    let changes = engine
+        .get_document_changes(&kept.id, QueryRequest::all())
+        .await
+        .expect("The changes to be retrieved");
+    assert!(changes.data.is_empty());
+
+    let orphan = DigraphDocument::create(&project);
+    assert!(engine.store_document(orphan.into()).await.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+extern crate conduit;
+use conduit::config::{Config, ConfigError, Limits, Overrides};
+use std::collections::HashMap;
+use std::io::Write;
+use tempdir::TempDir;
+
+fn env(vars: &[(&str, &str)]) -> Result<Overrides, ConfigError> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    Overrides::from_env(|name| vars.get(name).cloned())
+}
+
+#[test]
+fn test_config_defaults() {
+    let config = Config::from_toml("").expect("An empty file to be valid");
+    assert_eq!(config, Config::default());
+    assert_eq!(config.listen_addr, "localhost:8000");
+    assert_eq!(config.database_url, "sqlite://test.db");
+    assert_eq!(config.limits, Limits::default());
+}
+
+#[test]
+fn test_config_from_file() -> std::io::Result<()> {
+    let dir = TempDir::new("conduit")?;
+    let path = dir.path().join("conduit.toml");
+    let mut file = std::fs::File::create(&path)?;
+    writeln!(
+        file,
+        "
+        database_url = \"memory:\"
+
+        [cors]
+        allowed_origins = [\"http://localhost:3000\"]
+
+        [limits]
+        page_size = 10
+        "
+    )?;
+
+    let config = Config::from_file(&path).expect("The file to be loaded");
+    assert_eq!(config.database_url, "memory:");
+    assert_eq!(config.listen_addr, "localhost:8000");
+    assert_eq!(config.cors.allowed_origins, vec!["http://localhost:3000"]);
+    assert_eq!(config.limits.page_size, 10);
+    assert_eq!(config.limits.max_page_size, 1000);
+
+    match Config::from_file(&dir.path().join("missing.toml")) {
+        Err(ConfigError::Io(missing, _)) => assert!(missing.ends_with("missing.toml")),
+        other => panic!("Expected a read error, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_config_rejects_unknown_settings() {
+    match Config::from_toml("[limits]\npage_sise = 10") {
+        Err(ConfigError::Parse(None, message)) => assert!(message.contains("page_sise")),
+        other => panic!("Expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_config_precedence() {
+    let mut config = Config::from_toml(
+        "
+        listen_addr = \"0.0.0.0:9000\"
+        database_url = \"memory:\"
+        log_level = \"warn\"
+        ",
+    )
+    .expect("The file to be valid");
+
+    config
+        .apply(
+            env(&[
+                ("DATABASE_URL", "sqlite::memory:"),
+                ("CONDUIT_LOG_LEVEL", "debug"),
+                ("CONDUIT_CORS_ORIGINS", "http://a.test, ,http://b.test"),
+                ("CONDUIT_AUTH_TOKEN", "secret"),
+                ("CONDUIT_PAGE_SIZE", "25"),
+            ])
+            .expect("The environment to be valid"),
+        )
+        .expect("The environment to apply");
+
+    config
+        .apply(Overrides {
+            port: Some(9100),
+            log_level: Some("trace".into()),
+            ..Overrides::default()
+        })
+        .expect("The flags to apply");
+
+    assert_eq!(config.listen_addr, "0.0.0.0:9100");
+    assert_eq!(config.database_url, "sqlite::memory:");
+    assert_eq!(config.log_level, "trace");
+    assert_eq!(
+        config.cors.allowed_origins,
+        vec!["http://a.test", "http://b.test"]
+    );
+    assert_eq!(config.auth.bearer_token, Some("secret".into()));
+    assert_eq!(config.limits.page_size, 25);
+    assert_eq!(config.limits.max_query_depth, 32);
+}
+
+#[test]
+fn test_config_invalid_values() {
+    assert_eq!(
+        env(&[("CONDUIT_MAX_QUERY_DEPTH", "deep")]),
+        Err(ConfigError::InvalidValue(
+            "CONDUIT_MAX_QUERY_DEPTH",
+            "deep".into()
+        ))
+    );
+
+    let mut config = Config::default();
+    assert_eq!(
+        config.apply(Overrides {
+            page_size: Some(2000),
+            ..Overrides::default()
+        }),
+        Err(ConfigError::InvalidValue("max_page_size", "1000".into()))
+    );
+}
+
+#[test]
+fn test_config_show_masks_secrets() {
+    let mut config = Config::default();
+    config.auth.bearer_token = Some("secret".into());
+
+    let shown = config.show();
+    assert!(!shown.contains("secret"));
+    assert!(shown.contains("bearer_token = '********'"));
+
+    let reloaded = Config::from_toml(&shown).expect("The shown config to be valid");
+    assert_eq!(reloaded.listen_addr, config.listen_addr);
+    assert_eq!(reloaded.limits, config.limits);
+}
@@ -1,13 +1,16 @@
 extern crate conduit;
 
 use async_graphql::{EmptyMutation, EmptySubscription, Schema};
+use conduit::config::Limits;
 use conduit::doc::document::DigraphDocument;
 use conduit::doc::project::Project;
-use conduit::http::graphql::{MutationRoot, Query};
+use conduit::http::graphql::{MutationRoot, Query, SubscriptionRoot};
+use conduit::model::digraph::Digraph;
 use conduit::storage::engine::{Engine, EngineContainer};
 use conduit::storage::sqlite::Sqlite;
 
 use assert_json_diff::{assert_json_eq, assert_json_include};
+use futures::{FutureExt, StreamExt};
 use serde_json::json;
 
 #[tokio::test]
@@ -17,7 +20,7 @@ async fn test_graphql_schema() -> std::io::Result<()> {
     let storage = Sqlite::setup(":memory:".into())
         .await
         .expect("The sqlite storage to be set up");
-    let _ = storage
+    storage
         .migrate()
         .await
         .expect("The sqlite storage to be migrated");
@@ -25,7 +28,7 @@ async fn test_graphql_schema() -> std::io::Result<()> {
     let project = Project::new(conduit::util::naming::empty_uuid());
     let project_id = project.id.to_hyphenated().to_string();
 
-    let _ = storage
+    storage
         .store_project(project.clone())
         .await
         .expect("The project to be inserted");
@@ -73,7 +76,7 @@ async fn test_graphql_schema_query_project_documents() -> std::io::Result<()> {
     let storage = Sqlite::setup(":memory:".into())
         .await
         .expect("The sqlite storage to be set up");
-    let _ = storage
+    storage
         .migrate()
         .await
         .expect("The sqlite storage to be migrated");
@@ -81,7 +84,7 @@ async fn test_graphql_schema_query_project_documents() -> std::io::Result<()> {
     let project = Project::new(conduit::util::naming::empty_uuid());
     let project_id = project.id.to_hyphenated().to_string();
 
-    let _ = storage
+    storage
         .store_project(project.clone())
         .await
         .expect("The project to be inserted");
@@ -92,12 +95,12 @@ async fn test_graphql_schema_query_project_documents() -> std::io::Result<()> {
     let doc2 = DigraphDocument::create(&project);
     let doc2_id = doc2.id.to_hyphenated().to_string();
 
-    let _ = storage
+    storage
         .store_document(doc1.into())
         .await
         .expect("The document to be inserted");
 
-    let _ = storage
+    storage
         .store_document(doc2.into())
         .await
         .expect("The document to be inserted");
@@ -108,6 +111,13 @@ async fn test_graphql_schema_query_project_documents() -> std::io::Result<()> {
         .data(engine)
         .finish();
 
+    // Both documents share their update time, so their id breaks the tie
+    let (first_id, second_id) = if doc1_id < doc2_id {
+        (&doc1_id, &doc2_id)
+    } else {
+        (&doc2_id, &doc1_id)
+    };
+
     let res = schema
         .execute(format!(
             "{{
@@ -117,15 +127,20 @@ async fn test_graphql_schema_query_project_documents() -> std::io::Result<()> {
                     version,
                     body,
                     digraphs {{
-                        id,
-                        name,
-                        version
-                        body {{
-                            nodes {{
-                                id
-                            }}
-                            links {{
-                                id
+                        totalCount
+                        edges {{
+                            node {{
+                                id,
+                                name,
+                                version
+                                body {{
+                                    nodes {{
+                                        id
+                                    }}
+                                    links {{
+                                        id
+                                    }}
+                                }}
                             }}
                         }}
                     }}
@@ -143,26 +158,33 @@ async fn test_graphql_schema_query_project_documents() -> std::io::Result<()> {
                     "name": "New Project",
                     "version": 0,
                     "body": "",
-                    "digraphs" : [
-                        {
-                            "id": doc1_id,
-                            "name": "New",
-                            "version": 0,
-                            "body": {
-                                "nodes": [],
-                                "links": []
-                            }
-                        },
-                        {
-                            "id": doc2_id,
-                            "name": "New",
-                            "version": 0,
-                            "body": {
-                                "nodes": [],
-                                "links": []
+                    "digraphs" : {
+                        "totalCount": 2,
+                        "edges": [
+                            {
+                                "node": {
+                                    "id": first_id,
+                                    "name": "New",
+                                    "version": 0,
+                                    "body": {
+                                        "nodes": [],
+                                        "links": []
+                                    }
+                                }
+                            },
+                            {
+                                "node": {
+                                    "id": second_id,
+                                    "name": "New",
+                                    "version": 0,
+                                    "body": {
+                                        "nodes": [],
+                                        "links": []
+                                    }
+                                }
                             }
-                        }
-                    ]
+                        ]
+                    }
                 }
             }
         })
@@ -178,7 +200,7 @@ async fn test_graphql_schema_digraph_operations() -> std::io::Result<()> {
     let storage = Sqlite::setup(":memory:".into())
         .await
         .expect("The sqlite storage to be set up");
-    let _ = storage
+    storage
         .migrate()
         .await
         .expect("The sqlite storage to be migrated");
@@ -186,7 +208,7 @@ async fn test_graphql_schema_digraph_operations() -> std::io::Result<()> {
     let project = Project::new(conduit::util::naming::empty_uuid());
     let project_id = project.id.to_hyphenated().to_string();
 
-    let _ = storage
+    storage
         .store_project(project.clone())
         .await
         .expect("The project to be inserted");
@@ -241,12 +263,18 @@ async fn test_graphql_schema_digraph_operations() -> std::io::Result<()> {
                 }},
                 docId: {}
               ) {{
-                id
-                name
-                body {{
-                  nodes {{
-                    id
-                    name
+                node {{
+                  id
+                  name
+                }}
+                document {{
+                  id
+                  name
+                  body {{
+                    nodes {{
+                      id
+                      name
+                    }}
                   }}
                 }}
               }}
@@ -262,16 +290,21 @@ async fn test_graphql_schema_digraph_operations() -> std::io::Result<()> {
         expected: json!({
             "data": {
                 "digraphAddNode": {
-                    "name": "New",
-                    "body": {
-                        "nodes": [
-                            {
-                                "id": 1,
-                                "name": "A new name"
-                            }
-                        ]
+                    "node": {
+                        "id": 1,
+                        "name": "A new name"
+                    },
+                    "document": {
+                        "name": "New",
+                        "body": {
+                            "nodes": [
+                                {
+                                    "id": 1,
+                                    "name": "A new name"
+                                }
+                            ]
+                        }
                     }
-
                 }
             }
         })
@@ -279,7 +312,7 @@ async fn test_graphql_schema_digraph_operations() -> std::io::Result<()> {
 
     let node_id = serde_json::to_string(
         create_node_res_json
-            .pointer("/data/digraphAddNode/body/nodes/0/id")
+            .pointer("/data/digraphAddNode/node/id")
             .expect("Node ID to exist in graphql response"),
     )
     .expect("Node ID value to be deserializable");
@@ -296,12 +329,18 @@ async fn test_graphql_schema_digraph_operations() -> std::io::Result<()> {
                     name: \"An updated name\"
                 }},
               ) {{
-                id
-                name
-                body {{
-                  nodes {{
-                    id
-                    name
+                node {{
+                  id
+                  name
+                }}
+                document {{
+                  id
+                  name
+                  body {{
+                    nodes {{
+                      id
+                      name
+                    }}
                   }}
                 }}
               }}
@@ -318,14 +357,20 @@ async fn test_graphql_schema_digraph_operations() -> std::io::Result<()> {
         expected: json!({
             "data": {
                 "digraphUpdateNode": {
-                    "name": "New",
-                    "body": {
-                        "nodes": [
-                            {
-                                "id": 1, // node_id,
-                                "name": "An updated name"
-                            }
-                        ]
+                    "node": {
+                        "id": 1,
+                        "name": "An updated name"
+                    },
+                    "document": {
+                        "name": "New",
+                        "body": {
+                            "nodes": [
+                                {
+                                    "id": 1, // node_id,
+                                    "name": "An updated name"
+                                }
+                            ]
+                        }
                     }
                 }
             }
@@ -334,7 +379,7 @@ async fn test_graphql_schema_digraph_operations() -> std::io::Result<()> {
 
     let updated_node_id = serde_json::to_string(
         update_node_res_json
-            .pointer("/data/digraphUpdateNode/body/nodes/0/id")
+            .pointer("/data/digraphUpdateNode/node/id")
             .expect("Node ID to exist in graphql response"),
     )
     .expect("Node ID value to be deserializable");
@@ -343,3 +388,1787 @@ async fn test_graphql_schema_digraph_operations() -> std::io::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_graphql_subscription_document_changed() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    let project_id = project.id.to_hyphenated().to_string();
+
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let doc = DigraphDocument::create(&project);
+    let doc_id = doc.id.to_hyphenated().to_string();
+
+    storage
+        .store_document(doc.into())
+        .await
+        .expect("The document to be inserted");
+
+    let engine = EngineContainer::new(storage);
+
+    let schema = Schema::build(Query, MutationRoot, SubscriptionRoot)
+        .data(engine)
+        .finish();
+
+    let mut document_stream = schema.execute_stream(format!(
+        "
+        subscription {{
+          documentChanged(docId: \"{}\") {{
+            documentId
+            version
+            forward
+          }}
+        }}",
+        doc_id
+    ));
+
+    let mut project_stream = schema.execute_stream(format!(
+        "
+        subscription {{
+          projectChanged(projectId: \"{}\") {{
+            documentId
+            version
+          }}
+        }}",
+        project_id
+    ));
+
+    // Subscription streams are lazy, poll them once so they are listening
+    assert!(document_stream.next().now_or_never().is_none());
+    assert!(project_stream.next().now_or_never().is_none());
+
+    let add_node_res = schema
+        .execute(format!(
+            "
+            mutation add {{
+              digraphAddNode(
+                projectId: \"{}\",
+                docId: \"{}\",
+                attrs: {{
+                    name: \"A new name\",
+                }},
+              ) {{
+                document {{
+                  id
+                }}
+              }}
+            }}",
+            project_id, doc_id
+        ))
+        .await;
+    assert!(add_node_res.errors.is_empty());
+
+    let document_event = document_stream
+        .next()
+        .await
+        .expect("A document change event to be published");
+
+    assert_json_include!(
+        actual: serde_json::to_value(document_event).expect("Event to be serializable"),
+        expected: json!({
+            "data": {
+                "documentChanged": {
+                    "documentId": doc_id,
+                    "version": 1,
+                    "forward": [
+                        {
+                            "op": "add",
+                            "path": "/nodes/0",
+                            "value": {
+                                "id": 1,
+                                "name": "A new name",
+                                "labels": {}
+                            }
+                        }
+                    ]
+                }
+            }
+        })
+    );
+
+    let project_event = project_stream
+        .next()
+        .await
+        .expect("A project change event to be published");
+
+    assert_json_eq!(
+        project_event,
+        json!({
+            "data": {
+                "projectChanged": {
+                    "documentId": doc_id,
+                    "version": 1
+                }
+            }
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_document_at_version() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    let project_id = project.id.to_hyphenated().to_string();
+
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let doc = DigraphDocument::create(&project);
+    let doc_id = doc.id.to_hyphenated().to_string();
+
+    storage
+        .store_document(doc.into())
+        .await
+        .expect("The document to be inserted");
+
+    let engine = EngineContainer::new(storage);
+
+    let schema = Schema::build(Query, MutationRoot, EmptySubscription)
+        .data(engine)
+        .finish();
+
+    for name in &["First", "Second"] {
+        let res = schema
+            .execute(format!(
+                "
+                mutation add {{
+                  digraphAddNode(
+                    projectId: \"{}\",
+                    docId: \"{}\",
+                    attrs: {{
+                        name: \"{}\",
+                    }},
+                  ) {{
+                    document {{
+                      id
+                    }}
+                  }}
+                }}",
+                project_id, doc_id, name
+            ))
+            .await;
+        assert!(res.errors.is_empty());
+    }
+
+    let res = schema
+        .execute(format!(
+            "{{
+                current: document (id:\"{}\") {{
+                    version
+                    body
+                }}
+                first: document (id:\"{}\", version: 1) {{
+                    version
+                    body
+                }}
+                initial: document (id:\"{}\", version: 0) {{
+                    version
+                    body
+                }}
+            }}",
+            doc_id, doc_id, doc_id
+        ))
+        .await;
+
+    let node = |id: i32, name: &str| json!({ "id": id, "name": name, "labels": {} });
+    let body = |nodes: Vec<serde_json::Value>| {
+        json!({ "name": "", "nodes": nodes, "links": [], "labels": {} })
+    };
+
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "current": {
+                    "version": 2,
+                    "body": body(vec![node(1, "First"), node(2, "Second")])
+                },
+                "first": {
+                    "version": 1,
+                    "body": body(vec![node(1, "First")])
+                },
+                "initial": {
+                    "version": 0,
+                    "body": body(vec![])
+                }
+            }
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_document_changes() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    let project_id = project.id.to_hyphenated().to_string();
+
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let doc = DigraphDocument::create(&project);
+    let doc_id = doc.id.to_hyphenated().to_string();
+
+    storage
+        .store_document(doc.into())
+        .await
+        .expect("The document to be inserted");
+
+    let engine = EngineContainer::new(storage);
+
+    let schema = Schema::build(Query, MutationRoot, EmptySubscription)
+        .data(engine)
+        .finish();
+
+    for _ in 0..3 {
+        let res = schema
+            .execute(format!(
+                "
+                mutation add {{
+                  digraphAddNode(projectId: \"{}\", docId: \"{}\") {{
+                    document {{
+                      id
+                    }}
+                  }}
+                }}",
+                project_id, doc_id
+            ))
+            .await;
+        assert!(res.errors.is_empty());
+    }
+
+    let res = schema
+        .execute(format!(
+            "{{
+                document (id:\"{}\") {{
+                    changes (first: 1) {{
+                        totalCount
+                        pageInfo {{
+                            hasNextPage
+                            endCursor
+                        }}
+                    }}
+                }}
+            }}",
+            doc_id
+        ))
+        .await;
+    assert!(res.errors.is_empty());
+    let page = res.data.into_json().expect("Data to be JSON");
+    let page = &page["document"]["changes"];
+    assert_eq!(page["totalCount"], json!(3));
+    assert_eq!(page["pageInfo"]["hasNextPage"], json!(true));
+    let cursor = page["pageInfo"]["endCursor"]
+        .as_str()
+        .expect("A cursor for the first page");
+
+    let res = schema
+        .execute(format!(
+            "{{
+                document (id:\"{}\") {{
+                    changes (first: 2, after: \"{}\") {{
+                        totalCount
+                        pageInfo {{
+                            hasPreviousPage
+                            hasNextPage
+                        }}
+                        edges {{
+                            node {{
+                                version
+                                kind
+                                reverse
+                            }}
+                        }}
+                    }}
+                }}
+            }}",
+            doc_id, cursor
+        ))
+        .await;
+
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "document": {
+                    "changes": {
+                        "totalCount": 3,
+                        "pageInfo": {
+                            "hasPreviousPage": true,
+                            "hasNextPage": false
+                        },
+                        "edges": [
+                            {
+                                "node": {
+                                    "version": 2,
+                                    "kind": "EDIT",
+                                    "reverse": [{ "op": "remove", "path": "/nodes/1" }]
+                                }
+                            },
+                            {
+                                "node": {
+                                    "version": 3,
+                                    "kind": "EDIT",
+                                    "reverse": [{ "op": "remove", "path": "/nodes/2" }]
+                                }
+                            }
+                        ]
+                    }
+                }
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "{{
+                document (id:\"{}\") {{
+                    changes (last: 2) {{
+                        pageInfo {{
+                            hasPreviousPage
+                            hasNextPage
+                        }}
+                        edges {{
+                            node {{
+                                version
+                            }}
+                        }}
+                    }}
+                }}
+            }}",
+            doc_id
+        ))
+        .await;
+
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "document": {
+                    "changes": {
+                        "pageInfo": {
+                            "hasPreviousPage": true,
+                            "hasNextPage": false
+                        },
+                        "edges": [
+                            { "node": { "version": 2 } },
+                            { "node": { "version": 3 } }
+                        ]
+                    }
+                }
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "{{
+                document (id:\"{}\") {{
+                    changes (after: \"not-a-cursor\") {{
+                        totalCount
+                    }}
+                }}
+            }}",
+            doc_id
+        ))
+        .await;
+    assert_eq!(res.errors.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_digraph_expected_version() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    let project_id = project.id.to_hyphenated().to_string();
+
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let doc = DigraphDocument::create(&project);
+    let doc_id = doc.id.to_hyphenated().to_string();
+
+    storage
+        .store_document(doc.into())
+        .await
+        .expect("The document to be inserted");
+
+    let engine = EngineContainer::new(storage);
+
+    let schema = Schema::build(Query, MutationRoot, EmptySubscription)
+        .data(engine)
+        .finish();
+
+    let add_node = |expected_version: i32| {
+        format!(
+            "
+            mutation add {{
+              digraphAddNode(projectId: \"{}\", docId: \"{}\", expectedVersion: {}) {{
+                document {{
+                  version
+                }}
+              }}
+            }}",
+            project_id, doc_id, expected_version
+        )
+    };
+
+    let res = schema.execute(add_node(0)).await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "digraphAddNode": {
+                    "document": {
+                        "version": 1
+                    }
+                }
+            }
+        })
+    );
+
+    let res = schema.execute(add_node(0)).await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json,
+        expected: json!({
+            "data": null,
+            "errors": [
+                {
+                    "extensions": {
+                        "code": "VERSION_MISMATCH",
+                        "expectedVersion": 0,
+                        "currentVersion": 1
+                    }
+                }
+            ]
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_project_mutations() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let engine = EngineContainer::new(storage);
+
+    let schema = Schema::build(Query, MutationRoot, EmptySubscription)
+        .data(engine)
+        .finish();
+
+    let res = schema
+        .execute(
+            "
+            mutation create {
+              projectCreate(name: \"Pipelines\") {
+                id
+                name
+                version
+                body
+              }
+            }",
+        )
+        .await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json.clone(),
+        expected: json!({
+            "data": {
+                "projectCreate": {
+                    "name": "Pipelines",
+                    "version": 0,
+                    "body": ""
+                }
+            }
+        })
+    );
+
+    let project_id = res_json
+        .pointer("/data/projectCreate/id")
+        .and_then(|id| id.as_str())
+        .expect("Project ID to exist in graphql response")
+        .to_string();
+
+    let update_project = |expected_version: i32| {
+        format!(
+            "
+            mutation update {{
+              projectUpdate(id: \"{}\", name: \"Renamed\", expectedVersion: {}) {{
+                name
+                version
+              }}
+            }}",
+            project_id, expected_version
+        )
+    };
+
+    let res = schema.execute(update_project(0)).await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "projectUpdate": {
+                    "name": "Renamed",
+                    "version": 1
+                }
+            }
+        })
+    );
+
+    let res = schema.execute(update_project(0)).await;
+    assert_json_include!(
+        actual: serde_json::to_value(res).expect("GraphQL response to be deserializable to Value"),
+        expected: json!({
+            "errors": [
+                {
+                    "extensions": {
+                        "code": "VERSION_MISMATCH",
+                        "currentVersion": 1
+                    }
+                }
+            ]
+        })
+    );
+
+    schema
+        .execute("mutation create { projectCreate(name: \"Archive\") { id } }")
+        .await;
+
+    let res = schema
+        .execute(
+            "{ projects(orderBy: [{ field: NAME, direction: DESC }]) { edges { node { name } } } }",
+        )
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "projects": {
+                    "edges": [{ "node": { "name": "Renamed" } }, { "node": { "name": "Archive" } }]
+                }
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "
+            mutation delete {{
+              projectDelete(id: \"{}\", expectedVersion: 0)
+            }}",
+            project_id
+        ))
+        .await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json,
+        expected: json!({
+            "data": null,
+            "errors": [{ "extensions": { "code": "VERSION_MISMATCH" } }]
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "
+            mutation delete {{
+              projectDelete(id: \"{}\", expectedVersion: 1)
+            }}",
+            project_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "projectDelete": project_id
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!("{{ project (id:\"{}\") {{ id }} }}", project_id))
+        .await;
+    assert_eq!(res.errors.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_document_mutations() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    let other_project = Project::new(conduit::util::naming::empty_uuid());
+    let other_project_id = other_project.id.to_hyphenated().to_string();
+
+    for project in &[&project, &other_project] {
+        storage
+            .store_project((*project).clone())
+            .await
+            .expect("The project to be inserted");
+    }
+
+    let mut doc = DigraphDocument::create(&project);
+    doc.body.add_node(None).expect("A node to be added");
+    let doc_id = doc.id.to_hyphenated().to_string();
+
+    storage
+        .store_document(doc.into())
+        .await
+        .expect("The document to be inserted");
+
+    let engine = EngineContainer::new(storage);
+
+    let schema = Schema::build(Query, MutationRoot, EmptySubscription)
+        .data(engine)
+        .finish();
+
+    let res = schema
+        .execute(format!(
+            "
+            mutation rename {{
+              documentRename(id: \"{}\", name: \"Pipeline\", expectedVersion: 0) {{
+                name
+                version
+              }}
+            }}",
+            doc_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "documentRename": {
+                    "name": "Pipeline",
+                    "version": 1
+                }
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "
+            mutation duplicate {{
+              documentDuplicate(id: \"{}\", projectId: \"{}\") {{
+                id
+                name
+                version
+                body
+              }}
+            }}",
+            doc_id, other_project_id
+        ))
+        .await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json.clone(),
+        expected: json!({
+            "data": {
+                "documentDuplicate": {
+                    "name": "Pipeline",
+                    "version": 0,
+                    "body": {
+                        "nodes": [{ "id": 1 }]
+                    }
+                }
+            }
+        })
+    );
+    let copy_id = res_json
+        .pointer("/data/documentDuplicate/id")
+        .and_then(|id| id.as_str())
+        .expect("Document ID to exist in graphql response")
+        .to_string();
+    assert_ne!(copy_id, doc_id);
+
+    let res = schema
+        .execute(format!(
+            "{{
+                documents (filter: {{ projectId: \"{}\", doctype: DIGRAPH, nameContains: \"pipe\" }}) {{
+                    id
+                }}
+            }}",
+            other_project_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "documents": [{ "id": copy_id }]
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "
+            mutation delete {{
+              documentDelete(id: \"{}\")
+            }}",
+            doc_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "documentDelete": doc_id
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "
+            mutation delete {{
+              documentDelete(id: \"{}\")
+            }}",
+            doc_id
+        ))
+        .await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json,
+        expected: json!({
+            "data": null,
+            "errors": [{ "extensions": { "code": "NOT_FOUND" } }]
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "{{
+                original: document (id:\"{}\") {{ id }}
+            }}",
+            doc_id
+        ))
+        .await;
+    assert_eq!(res.errors.len(), 1);
+
+    let res = schema
+        .execute(format!(
+            "{{
+                project (id:\"{}\") {{
+                    digraphs {{
+                        edges {{
+                            node {{
+                                id
+                                name
+                            }}
+                        }}
+                    }}
+                }}
+            }}",
+            other_project_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "project": {
+                    "digraphs": {
+                        "edges": [{ "node": { "id": copy_id, "name": "Pipeline" } }]
+                    }
+                }
+            }
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_document_rename_undo() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    let project_id = project.id.to_hyphenated().to_string();
+
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let doc = DigraphDocument::create(&project);
+    let doc_id = doc.id.to_hyphenated().to_string();
+
+    storage
+        .store_document(doc.into())
+        .await
+        .expect("The document to be inserted");
+
+    let engine = EngineContainer::new(storage);
+
+    let schema = Schema::build(Query, MutationRoot, EmptySubscription)
+        .data(engine)
+        .finish();
+
+    let res = schema
+        .execute(format!(
+            "
+            mutation edit {{
+              digraphAddNode(projectId: \"{0}\", docId: \"{1}\") {{
+                document {{
+                  version
+                }}
+              }}
+              documentRename(id: \"{1}\", name: \"Pipeline\", expectedVersion: 1) {{
+                version
+              }}
+            }}",
+            project_id, doc_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "digraphAddNode": { "document": { "version": 1 } },
+                "documentRename": { "version": 2 }
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "
+            mutation undo {{
+              documentUndo(id: \"{}\") {{
+                name
+                version
+                body
+              }}
+            }}",
+            doc_id
+        ))
+        .await;
+    assert_json_include!(
+        actual: serde_json::to_value(res).expect("GraphQL response to be deserializable to Value"),
+        expected: json!({
+            "data": {
+                "documentUndo": {
+                    "name": "Pipeline",
+                    "version": 3,
+                    "body": { "nodes": [] }
+                }
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "{{
+                document (id:\"{}\") {{
+                    changes {{
+                        totalCount
+                    }}
+                }}
+            }}",
+            doc_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "document": {
+                    "changes": { "totalCount": 2 }
+                }
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "mutation undo {{ documentUndo(id: \"{}\") {{ version }} }}",
+            doc_id
+        ))
+        .await;
+    assert_json_include!(
+        actual: serde_json::to_value(res).expect("GraphQL response to be deserializable to Value"),
+        expected: json!({
+            "data": null,
+            "errors": [{ "extensions": { "code": "NOTHING_TO_UNDO" } }]
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_limits() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    for _ in 0..3 {
+        storage
+            .store_project(Project::new(conduit::util::naming::empty_uuid()))
+            .await
+            .expect("The project to be inserted");
+    }
+
+    let limits = Limits {
+        page_size: 1,
+        max_page_size: 2,
+        max_query_depth: 3,
+    };
+    let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+        .limit_depth(limits.max_query_depth)
+        .data(EngineContainer::new(storage))
+        .data(limits)
+        .finish();
+
+    let count = |res: &async_graphql::Response| {
+        res.data.clone().into_json().expect("The data to be json")["projects"]["edges"]
+            .as_array()
+            .map(|edges| edges.len())
+    };
+
+    let res = schema
+        .execute("{ projects { totalCount edges { cursor } } }")
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(count(&res), Some(1));
+
+    let res = schema
+        .execute("{ projects(first: 10) { totalCount edges { cursor } } }")
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(count(&res), Some(2));
+
+    let res = schema
+        .execute("{ projects { edges { node { id } } } }")
+        .await;
+    assert_eq!(res.errors.len(), 1);
+    assert_eq!(res.errors[0].message, "Query is nested too deep.");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_digraph_dot() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let mut doc = DigraphDocument::create(&project);
+    doc.body.name = "Flow".into();
+    let _ = doc.body.add_node(None);
+    let _ = doc.body.add_node(None);
+    let _ = doc.body.add_link(1, 2, None);
+    let doc_id = doc.id.to_hyphenated().to_string();
+
+    storage
+        .store_document(doc.into())
+        .await
+        .expect("The document to be inserted");
+
+    let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(EngineContainer::new(storage))
+        .finish();
+
+    let res = schema
+        .execute(format!("{{ digraph(id: \"{}\") {{ id dot }} }}", doc_id))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "digraph": {
+                    "id": doc_id,
+                    "dot": "digraph Flow {\n    1\n    2\n    1 -> 2 [label=name]\n}\n"
+                }
+            }
+        })
+    );
+
+    for format in &["GRAPHML", "GEXF"] {
+        let res = schema
+            .execute(format!(
+                "{{ digraph(id: \"{}\") {{ export(format: {}) }} }}",
+                doc_id, format
+            ))
+            .await;
+        let res_json =
+            serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+        let exported = res_json["data"]["digraph"]["export"]
+            .as_str()
+            .expect("The digraph to be exported");
+        let format = format.to_lowercase().parse().expect("A digraph format");
+        let body = Digraph::import(format, exported).expect("The export to be imported");
+        assert_eq!(body.name, "Flow");
+        assert_eq!((body.nodes.len(), body.links.len()), (2, 1));
+    }
+
+    let res = schema
+        .execute(format!(
+            "{{ digraph(id: \"{}\") {{ \
+                mermaid: diagram(format: MERMAID, direction: LEFT_RIGHT) \
+                plantuml: diagram(format: PLANTUML) }} }}",
+            doc_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "digraph": {
+                    "mermaid": "---\ntitle: \"Flow\"\n---\nflowchart LR\n    n1[\"1\"]\n    n2[\"2\"]\n    n1 -->|\"name\"| n2\n",
+                    "plantuml": "@startuml\ntitle Flow\ncomponent \"1\" as n1\ncomponent \"2\" as n2\nn1 --> n2 : name\n@enduml\n"
+                }
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "{{ digraph(id: \"{}\") {{ dot }} }}",
+            project.id.to_hyphenated()
+        ))
+        .await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json,
+        expected: json!({
+            "data": null,
+            "errors": [{ "extensions": { "code": "NOT_FOUND" } }]
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_digraph_import() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    let project_id = project.id.to_hyphenated().to_string();
+    storage
+        .store_project(project)
+        .await
+        .expect("The project to be inserted");
+
+    let schema = Schema::build(Query, MutationRoot, EmptySubscription)
+        .data(EngineContainer::new(storage))
+        .finish();
+
+    let import = |source: &str| {
+        format!(
+            "mutation {{
+                digraphImport(projectId: \"{}\", format: DOT, source: {}) {{
+                    name
+                    version
+                    body {{ nodes {{ id name }} links {{ source target }} }}
+                }}
+            }}",
+            project_id,
+            serde_json::to_string(source).expect("The source to be serializable")
+        )
+    };
+
+    let res = schema
+        .execute(import("digraph Flow {\n  a -> b [label=next]\n}"))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "digraphImport": {
+                    "name": "Flow",
+                    "version": 0,
+                    "body": {
+                        "nodes": [{ "id": 1, "name": "a" }, { "id": 2, "name": "b" }],
+                        "links": [{ "source": 1, "target": 2 }]
+                    }
+                }
+            }
+        })
+    );
+
+    let res = schema.execute("{ documents { name } }").await;
+    assert_json_eq!(res, json!({ "data": { "documents": [{ "name": "Flow" }] } }));
+
+    let res = schema.execute(import("digraph {\n  a -> ;\n}")).await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json,
+        expected: json!({
+            "data": null,
+            "errors": [{
+                "message": "Expected a node or subgraph, found ';'",
+                "extensions": { "code": "PARSE_ERROR", "line": 2, "column": 8 }
+            }]
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_digraph_analysis() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let mut doc = DigraphDocument::create(&project);
+    for _ in 0..4 {
+        let _ = doc.body.add_node(None);
+    }
+    // Links 5 to 8
+    for (source, target) in &[(1, 2), (2, 3), (3, 2), (2, 4)] {
+        let _ = doc.body.add_link(*source, *target, None);
+    }
+    let doc_id = doc.id.to_hyphenated().to_string();
+
+    storage
+        .store_document(doc.into())
+        .await
+        .expect("The document to be inserted");
+
+    let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(EngineContainer::new(storage))
+        .finish();
+
+    let res = schema
+        .execute(format!(
+            "{{ digraph(id: \"{}\") {{ body {{ \
+                cycle components ancestors(id: 3) descendants(id: 3) \
+                reachable(source: 4, target: 1) \
+                shortestPath(source: 1, target: 4) {{ nodes links cost }} }} }} }}",
+            doc_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "digraph": {
+                    "body": {
+                        "cycle": [2, 3],
+                        "components": [[1], [2, 3], [4]],
+                        "ancestors": [2, 1],
+                        "descendants": [2, 4],
+                        "reachable": false,
+                        "shortestPath": { "nodes": [1, 2, 4], "links": [5, 8], "cost": 2.0 }
+                    }
+                }
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "{{ digraph(id: \"{}\") {{ body {{ topologicalOrder }} }} }}",
+            doc_id
+        ))
+        .await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json,
+        expected: json!({
+            "data": null,
+            "errors": [{ "extensions": { "code": "CYCLE", "nodes": [2, 3] } }]
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "{{ digraph(id: \"{}\") {{ body {{ descendants(id: 9) }} }} }}",
+            doc_id
+        ))
+        .await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json,
+        expected: json!({
+            "data": null,
+            "errors": [{ "extensions": { "code": "ID_DOES_NOT_EXIST", "id": 9 } }]
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_digraph_constraints() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    let project_id = project.id.to_hyphenated().to_string();
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let mut doc = DigraphDocument::create(&project);
+    for _ in 0..3 {
+        let _ = doc.body.add_node(None);
+    }
+    // Links 4 and 5
+    for (source, target) in &[(1, 2), (2, 3)] {
+        let _ = doc.body.add_link(*source, *target, None);
+    }
+    let doc_id = doc.id.to_hyphenated().to_string();
+
+    storage
+        .store_document(doc.into())
+        .await
+        .expect("The document to be inserted");
+
+    let schema = Schema::build(Query, MutationRoot, EmptySubscription)
+        .data(EngineContainer::new(storage))
+        .finish();
+
+    let res = schema
+        .execute(format!(
+            "mutation {{
+                digraphSetConstraints(projectId: \"{}\", docId: \"{}\",
+                                      constraints: {{ acyclic: true, maxInDegree: 1 }}) {{
+                    version
+                    body {{ constraints {{ acyclic noSelfLoops maxInDegree maxOutDegree }} }}
+                }}
+            }}",
+            project_id, doc_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "digraphSetConstraints": {
+                    "version": 1,
+                    "body": {
+                        "constraints": {
+                            "acyclic": true,
+                            "noSelfLoops": false,
+                            "maxInDegree": 1,
+                            "maxOutDegree": null
+                        }
+                    }
+                }
+            }
+        })
+    );
+
+    let add_link = |source: i32, target: i32| {
+        format!(
+            "mutation {{
+                digraphAddLink(projectId: \"{}\", docId: \"{}\", sourceId: {}, targetId: {}) {{
+                    document {{ version }}
+                }}
+            }}",
+            project_id, doc_id, source, target
+        )
+    };
+
+    let res = schema.execute(add_link(3, 1)).await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json,
+        expected: json!({
+            "data": null,
+            "errors": [{
+                "extensions": { "code": "CONSTRAINT_VIOLATION", "constraint": "ACYCLIC", "nodes": [3, 1, 2] }
+            }]
+        })
+    );
+
+    let res = schema.execute(add_link(1, 3)).await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json,
+        expected: json!({
+            "data": null,
+            "errors": [{
+                "extensions": { "code": "CONSTRAINT_VIOLATION", "constraint": "MAX_IN_DEGREE", "id": 3 }
+            }]
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "mutation {{
+                digraphSetConstraints(projectId: \"{}\", docId: \"{}\",
+                                      constraints: {{ maxOutDegree: 0 }}) {{
+                    version
+                }}
+            }}",
+            project_id, doc_id
+        ))
+        .await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json,
+        expected: json!({
+            "data": null,
+            "errors": [{
+                "extensions": { "code": "CONSTRAINT_VIOLATION", "constraint": "MAX_OUT_DEGREE", "id": 1 }
+            }]
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_digraph_apply() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    let project_id = project.id.to_hyphenated().to_string();
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+    let other = Project::new(conduit::util::naming::empty_uuid());
+    let other_id = other.id.to_hyphenated().to_string();
+    storage
+        .store_project(other)
+        .await
+        .expect("The project to be inserted");
+
+    let mut doc = DigraphDocument::create(&project);
+    let _ = doc.body.add_node(None);
+    let doc_id = doc.id.to_hyphenated().to_string();
+
+    storage
+        .store_document(doc.into())
+        .await
+        .expect("The document to be inserted");
+
+    let schema = Schema::build(Query, MutationRoot, EmptySubscription)
+        .data(EngineContainer::new(storage))
+        .finish();
+
+    let res = schema
+        .execute(format!(
+            "mutation {{
+                digraphApply(projectId: \"{}\", docId: \"{}\", messages: [{{ message: {{ addNode: {{}} }} }}]) {{
+                    document {{ version }}
+                }}
+            }}",
+            other_id, doc_id
+        ))
+        .await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json,
+        expected: json!({
+            "data": null,
+            "errors": [{ "extensions": { "code": "NOT_FOUND" } }]
+        })
+    );
+
+    let apply = |messages: &str| {
+        format!(
+            "mutation {{
+                digraphApply(projectId: \"{}\", docId: \"{}\", messages: [{}]) {{
+                    document {{
+                        version
+                        body {{ nodes {{ id name }} links {{ id source target }} }}
+                    }}
+                    tempIds {{ temp id }}
+                }}
+            }}",
+            project_id, doc_id, messages
+        )
+    };
+
+    let res = schema
+        .execute(apply(
+            "{ tempId: \"a\", message: { addNode: { name: \"a\" } } },
+             { tempId: \"b\", message: { addNode: {} } },
+             { message: { addLink: { source: { temp: \"a\" }, target: { temp: \"b\" } } } },
+             { tempId: \"ba\", message: { addLink: { source: { temp: \"b\" }, target: { id: 1 } } } },
+             { message: { updateNode: { id: { temp: \"b\" }, attrs: { name: \"b\" } } } }",
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "digraphApply": {
+                    "document": {
+                        "version": 1,
+                        "body": {
+                            "nodes": [
+                                { "id": 1, "name": "" },
+                                { "id": 2, "name": "a" },
+                                { "id": 3, "name": "b" }
+                            ],
+                            "links": [
+                                { "id": 4, "source": 2, "target": 3 },
+                                { "id": 5, "source": 3, "target": 1 }
+                            ]
+                        }
+                    },
+                    "tempIds": [
+                        { "temp": "a", "id": 2 },
+                        { "temp": "b", "id": 3 },
+                        { "temp": "ba", "id": 5 }
+                    ]
+                }
+            }
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "{{ document(id: \"{}\") {{ changes {{ totalCount }} }} }}",
+            doc_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({ "data": { "document": { "changes": { "totalCount": 1 } } } })
+    );
+
+    let res = schema
+        .execute(apply(
+            "{ tempId: \"c\", message: { addNode: {} } },
+             { message: { addLink: { source: { temp: \"c\" }, target: { temp: \"d\" } } } }",
+        ))
+        .await;
+    let res_json =
+        serde_json::to_value(res).expect("GraphQL response to be deserializable to Value");
+    assert_json_include!(
+        actual: res_json,
+        expected: json!({
+            "data": null,
+            "errors": [{
+                "message": "message 1 failed : temp id does not exist : d",
+                "extensions": { "code": "TEMP_ID_DOES_NOT_EXIST", "temp": "d", "index": 1 }
+            }]
+        })
+    );
+
+    let res = schema
+        .execute(format!(
+            "{{ digraph(id: \"{}\") {{ version body {{ nodes {{ id }} }} }} }}",
+            doc_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "digraph": { "version": 1, "body": { "nodes": [{ "id": 1 }, { "id": 2 }, { "id": 3 }] } }
+            }
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_digraph_payloads() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    let project_id = project.id.to_hyphenated().to_string();
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let mut doc = DigraphDocument::create(&project);
+    for _ in 0..3 {
+        let _ = doc.body.add_node(None);
+    }
+    let doc_id = doc.id.to_hyphenated().to_string();
+
+    storage
+        .store_document(doc.into())
+        .await
+        .expect("The document to be inserted");
+
+    let schema = Schema::build(Query, MutationRoot, EmptySubscription)
+        .data(EngineContainer::new(storage))
+        .finish();
+
+    let res = schema
+        .execute(format!(
+            "mutation {{
+                first: digraphAddLink(projectId: \"{0}\", docId: \"{1}\", sourceId: 1, targetId: 2,
+                                      attrs: {{ name: \"first\" }}) {{
+                    link {{ id name source target }}
+                    document {{ version }}
+                }}
+                second: digraphAddLink(projectId: \"{0}\", docId: \"{1}\", sourceId: 3, targetId: 1) {{
+                    link {{ id }}
+                }}
+                renamed: digraphUpdateLink(projectId: \"{0}\", docId: \"{1}\", linkId: 4,
+                                           attrs: {{ name: \"renamed\" }}) {{
+                    link {{ id name }}
+                }}
+                removed: digraphRemoveNode(projectId: \"{0}\", docId: \"{1}\", nodeId: 1) {{
+                    nodes
+                    links
+                    document {{ version body {{ nodes {{ id }} links {{ id }} }} }}
+                }}
+            }}",
+            project_id, doc_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "first": {
+                    "link": { "id": 4, "name": "first", "source": 1, "target": 2 },
+                    "document": { "version": 1 }
+                },
+                "second": { "link": { "id": 5 } },
+                "renamed": { "link": { "id": 4, "name": "renamed" } },
+                "removed": {
+                    "nodes": [1],
+                    "links": [4, 5],
+                    "document": {
+                        "version": 4,
+                        "body": { "nodes": [{ "id": 2 }, { "id": 3 }], "links": [] }
+                    }
+                }
+            }
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graphql_digraph_labels() -> std::io::Result<()> {
+    let _ = env_logger::try_init();
+
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    let project_id = project.id.to_hyphenated().to_string();
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let mut doc = DigraphDocument::create(&project);
+    let _ = doc.body.add_node(None);
+    let doc_id = doc.id.to_hyphenated().to_string();
+
+    storage
+        .store_document(doc.into())
+        .await
+        .expect("The document to be inserted");
+
+    let schema = Schema::build(Query, MutationRoot, EmptySubscription)
+        .data(EngineContainer::new(storage))
+        .finish();
+
+    let res = schema
+        .execute(format!(
+            "mutation {{
+                named: digraphSetName(projectId: \"{0}\", docId: \"{1}\", name: \"Pipeline\") {{
+                    body {{ name }}
+                }}
+                labeled: digraphUpdateLabels(projectId: \"{0}\", docId: \"{1}\",
+                                             attrs: {{ labels: {{ rankdir: \"LR\", owner: \"ops\" }} }}) {{
+                    body {{ labels }}
+                }}
+                unlabeled: digraphUpdateLabels(projectId: \"{0}\", docId: \"{1}\",
+                                               attrs: {{ removeLabels: [\"owner\"] }}) {{
+                    version
+                    body {{ labels }}
+                }}
+                merged: digraphUpdateNode(projectId: \"{0}\", docId: \"{1}\", nodeId: 1,
+                                          attrs: {{ labels: {{ color: \"red\", shape: \"box\" }} }}) {{
+                    node {{ labels }}
+                }}
+                removed: digraphUpdateNode(projectId: \"{0}\", docId: \"{1}\", nodeId: 1,
+                                           attrs: {{ removeLabels: [\"color\"] }}) {{
+                    node {{ labels }}
+                }}
+                replaced: digraphUpdateNode(projectId: \"{0}\", docId: \"{1}\", nodeId: 1,
+                                            attrs: {{ labels: {{ style: \"bold\" }}, replaceLabels: true }}) {{
+                    node {{ labels }}
+                }}
+            }}",
+            project_id, doc_id
+        ))
+        .await;
+    assert_json_eq!(
+        res,
+        json!({
+            "data": {
+                "named": { "body": { "name": "Pipeline" } },
+                "labeled": { "body": { "labels": { "rankdir": "LR", "owner": "ops" } } },
+                "unlabeled": { "version": 3, "body": { "labels": { "rankdir": "LR" } } },
+                "merged": { "node": { "labels": { "color": "red", "shape": "box" } } },
+                "removed": { "node": { "labels": { "shape": "box" } } },
+                "replaced": { "node": { "labels": { "style": "bold" } } }
+            }
+        })
+    );
+
+    Ok(())
+}
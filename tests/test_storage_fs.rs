@@ -0,0 +1,190 @@
+extern crate conduit;
+use conduit::storage::fs::Fs;
+use tempdir::TempDir;
+
+mod common;
+
+/// Storage in a fresh directory, which is removed with the returned `TempDir`
+async fn storage() -> (Fs, TempDir) {
+    let _ = env_logger::try_init();
+    let dir = TempDir::new("conduit").expect("To be able to create a temporary directory");
+    let storage = Fs::setup(dir.path())
+        .await
+        .expect("The fs storage to be set up");
+    (storage, dir)
+}
+
+#[tokio::test]
+async fn test_fs_engine_functions() -> std::io::Result<()> {
+    let (storage, _dir) = storage().await;
+    common::engine_functions(storage).await
+}
+
+#[tokio::test]
+async fn test_fs_engine_cascading_deletes() -> std::io::Result<()> {
+    let (storage, _dir) = storage().await;
+    common::cascading_deletes(storage).await
+}
+
+#[tokio::test]
+async fn test_fs_engine_undo_redo() -> std::io::Result<()> {
+    let (storage, _dir) = storage().await;
+    common::undo_redo(storage).await
+}
+
+#[tokio::test]
+async fn test_fs_engine_update_version_check() -> std::io::Result<()> {
+    let (storage, _dir) = storage().await;
+    common::update_version_check(storage).await
+}
+
+#[tokio::test]
+async fn test_fs_engine_get_projects_sorted() -> std::io::Result<()> {
+    let (storage, _dir) = storage().await;
+    common::get_projects_sorted(storage).await
+}
+
+#[tokio::test]
+async fn test_fs_engine_get_projects_paged() -> std::io::Result<()> {
+    let (storage, _dir) = storage().await;
+    common::get_projects_paged(storage).await
+}
+
+#[tokio::test]
+async fn test_fs_engine_filters() -> std::io::Result<()> {
+    let (storage, _dir) = storage().await;
+    common::filters(storage).await
+}
+
+#[tokio::test]
+async fn test_fs_engine_changes_and_deletes() -> std::io::Result<()> {
+    let (storage, _dir) = storage().await;
+    common::changes_and_deletes(storage).await
+}
+
+#[tokio::test]
+async fn test_fs_engine_layout() -> std::io::Result<()> {
+    use conduit::doc::document::{DigraphDocument, RawDocument};
+    use conduit::doc::project::Project;
+    use conduit::storage::engine::{Engine, EngineContainer};
+
+    let (storage, dir) = storage().await;
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+    let doc = DigraphDocument::create(&project);
+    storage
+        .store_document(doc.clone().into())
+        .await
+        .expect("The document to be inserted");
+
+    let engine = EngineContainer::new(storage);
+    for _ in 0..2 {
+        let mut doc: DigraphDocument = engine
+            .get_document(&doc.id)
+            .await
+            .expect("The document to be retrieved")
+            .into();
+        doc.body.add_node(None).expect("A node to be added");
+        let mut raw_doc: RawDocument = doc.into();
+        engine
+            .update_document(&mut raw_doc)
+            .await
+            .expect("The document to be updated");
+    }
+
+    let project_dir = dir.path().join(project.id.to_hyphenated().to_string());
+    let mut files: Vec<String> = std::fs::read_dir(&project_dir)?
+        .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<_, _>>()?;
+    files.sort();
+    let mut expected = vec![
+        "changes.jsonl".to_string(),
+        "project.json".to_string(),
+        format!("{}.json", doc.id.to_hyphenated()),
+    ];
+    expected.sort();
+    assert_eq!(files, expected);
+
+    let doc_json =
+        std::fs::read_to_string(project_dir.join(format!("{}.json", doc.id.to_hyphenated())))?;
+    assert!(doc_json.lines().count() > 1);
+    let stored: RawDocument = serde_json::from_str(&doc_json)?;
+    assert_eq!(stored.version, 2);
+
+    let log = std::fs::read_to_string(project_dir.join("changes.jsonl"))?;
+    assert_eq!(log.lines().count(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fs_engine_interrupted_update() -> std::io::Result<()> {
+    use conduit::doc::change::Change;
+    use conduit::doc::document::{DigraphDocument, RawDocument};
+    use conduit::doc::project::Project;
+    use conduit::storage::engine::{Engine, EngineContainer, QueryRequest};
+    use std::io::Write;
+
+    let (storage, dir) = storage().await;
+
+    let project = Project::new(conduit::util::naming::empty_uuid());
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+    let doc = DigraphDocument::create(&project);
+    storage
+        .store_document(doc.clone().into())
+        .await
+        .expect("The document to be inserted");
+
+    let id = doc.id;
+    let add_node = |engine: EngineContainer| async move {
+        let mut doc: DigraphDocument = engine
+            .get_document(&id)
+            .await
+            .expect("The document to be retrieved")
+            .into();
+        doc.body.add_node(None).expect("A node to be added");
+        let mut raw_doc: RawDocument = doc.into();
+        engine
+            .update_document(&mut raw_doc)
+            .await
+            .expect("The document to be updated");
+        engine
+    };
+    add_node(EngineContainer::new(storage)).await;
+
+    // A change logged by an update that stopped before renaming the document
+    let log_path = dir
+        .path()
+        .join(project.id.to_hyphenated().to_string())
+        .join("changes.jsonl");
+    let log = std::fs::read_to_string(&log_path)?;
+    let mut change: Change = serde_json::from_str(log.trim())?;
+    change.id = 2;
+    change.version = 2;
+    let mut log = std::fs::OpenOptions::new().append(true).open(&log_path)?;
+    writeln!(log, "{}", serde_json::to_string(&change)?)?;
+
+    let storage = Fs::setup(dir.path())
+        .await
+        .expect("The fs storage to be set up again");
+    let engine = add_node(EngineContainer::new(storage)).await;
+
+    let changes: Vec<(i32, i32)> = engine
+        .get_document_changes(&id, QueryRequest::all())
+        .await
+        .expect("The changes to be retrieved")
+        .data
+        .iter()
+        .map(|change| (change.id, change.version))
+        .collect();
+    assert_eq!(changes, vec![(1, 1), (3, 2)]);
+
+    Ok(())
+}
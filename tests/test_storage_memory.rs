@@ -0,0 +1,49 @@
+extern crate conduit;
+use conduit::storage::memory::Memory;
+
+mod common;
+
+fn storage() -> Memory {
+    let _ = env_logger::try_init();
+    Memory::new()
+}
+
+#[tokio::test]
+async fn test_memory_engine_functions() -> std::io::Result<()> {
+    common::engine_functions(storage()).await
+}
+
+#[tokio::test]
+async fn test_memory_engine_cascading_deletes() -> std::io::Result<()> {
+    common::cascading_deletes(storage()).await
+}
+
+#[tokio::test]
+async fn test_memory_engine_undo_redo() -> std::io::Result<()> {
+    common::undo_redo(storage()).await
+}
+
+#[tokio::test]
+async fn test_memory_engine_update_version_check() -> std::io::Result<()> {
+    common::update_version_check(storage()).await
+}
+
+#[tokio::test]
+async fn test_memory_engine_get_projects_sorted() -> std::io::Result<()> {
+    common::get_projects_sorted(storage()).await
+}
+
+#[tokio::test]
+async fn test_memory_engine_get_projects_paged() -> std::io::Result<()> {
+    common::get_projects_paged(storage()).await
+}
+
+#[tokio::test]
+async fn test_memory_engine_filters() -> std::io::Result<()> {
+    common::filters(storage()).await
+}
+
+#[tokio::test]
+async fn test_memory_engine_changes_and_deletes() -> std::io::Result<()> {
+    common::changes_and_deletes(storage()).await
+}
@@ -0,0 +1,95 @@
+//! Runs against the server in `CONDUIT_TEST_POSTGRES_URL`, for example
+//! `postgres://postgres@localhost/postgres`, and is skipped without it.
+//! Every test creates and drops its own database on that server.
+
+extern crate conduit;
+use conduit::storage::postgres::Postgres;
+use futures::Future;
+use sqlx::postgres::PgPool;
+
+mod common;
+
+const URL_VARIABLE: &str = "CONDUIT_TEST_POSTGRES_URL";
+
+async fn with_storage<F, Fut>(test: F) -> std::io::Result<()>
+where
+    F: FnOnce(Postgres) -> Fut,
+    Fut: Future<Output = std::io::Result<()>>,
+{
+    let _ = env_logger::try_init();
+    let url = match std::env::var(URL_VARIABLE) {
+        Ok(url) => url,
+        Err(_) => {
+            println!("{} is not set, skipping", URL_VARIABLE);
+            return Ok(());
+        }
+    };
+
+    let database = format!("conduit_test_{}", uuid::Uuid::new_v4().to_simple());
+    let server = PgPool::connect(&url)
+        .await
+        .expect("To connect to the postgres server");
+    sqlx::query(&format!("CREATE DATABASE {}", database))
+        .execute(&server)
+        .await
+        .expect("The test database to be created");
+
+    let (base, _) = url.rsplit_once('/').expect("The url to name a database");
+    let storage = Postgres::setup(format!("{}/{}", base, database))
+        .await
+        .expect("The postgres storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The postgres storage to be migrated");
+    let pool = storage.pool.clone();
+
+    let result = test(storage).await;
+
+    pool.close().await;
+    sqlx::query(&format!("DROP DATABASE {}", database))
+        .execute(&server)
+        .await
+        .expect("The test database to be dropped");
+    result
+}
+
+#[tokio::test]
+async fn test_postgres_engine_functions() -> std::io::Result<()> {
+    with_storage(common::engine_functions).await
+}
+
+#[tokio::test]
+async fn test_postgres_engine_cascading_deletes() -> std::io::Result<()> {
+    with_storage(common::cascading_deletes).await
+}
+
+#[tokio::test]
+async fn test_postgres_engine_undo_redo() -> std::io::Result<()> {
+    with_storage(common::undo_redo).await
+}
+
+#[tokio::test]
+async fn test_postgres_engine_update_version_check() -> std::io::Result<()> {
+    with_storage(common::update_version_check).await
+}
+
+#[tokio::test]
+async fn test_postgres_engine_get_projects_sorted() -> std::io::Result<()> {
+    with_storage(common::get_projects_sorted).await
+}
+
+#[tokio::test]
+async fn test_postgres_engine_get_projects_paged() -> std::io::Result<()> {
+    with_storage(common::get_projects_paged).await
+}
+
+#[tokio::test]
+async fn test_postgres_engine_filters() -> std::io::Result<()> {
+    with_storage(common::filters).await
+}
+
+#[tokio::test]
+async fn test_postgres_engine_changes_and_deletes() -> std::io::Result<()> {
+    with_storage(common::changes_and_deletes).await
+}
@@ -3,6 +3,20 @@ use tempdir::TempDir;
 extern crate conduit;
 use conduit::storage::sqlite::Sqlite;
 
+mod common;
+
+async fn storage() -> Sqlite {
+    let _ = env_logger::try_init();
+    let storage = Sqlite::setup(":memory:".into())
+        .await
+        .expect("The sqlite storage to be set up");
+    storage
+        .migrate()
+        .await
+        .expect("The sqlite storage to be migrated");
+    storage
+}
+
 #[tokio::test]
 async fn test_sqlite_in_memory() -> std::io::Result<()> {
     let _ = env_logger::try_init();
@@ -37,106 +51,40 @@ async fn test_sqlite_on_disk() -> std::io::Result<()> {
 
 #[tokio::test]
 async fn test_sqlite_engine_functions() -> std::io::Result<()> {
-    use conduit::doc::document::{DigraphDocument, RawDocument};
-    use conduit::doc::project::Project;
-    use conduit::storage::engine::Engine;
-
-    let _ = env_logger::try_init();
-    let storage = Sqlite::setup(":memory:".into())
-        .await
-        .expect("The sqlite storage to be set up");
-
-    let _ = storage
-        .migrate()
-        .await
-        .expect("The sqlite storage to be migrated");
-
-    let project = Project::new(conduit::util::naming::empty_uuid());
-
-    let _ = storage
-        .store_project(project.clone())
-        .await
-        .expect("The project to be inserted");
-
-    let doc = DigraphDocument::create(&project);
-
-    let raw_doc: RawDocument = doc.clone().into();
-
-    let _ = storage
-        .store_document(raw_doc.clone())
-        .await
-        .expect("The document to be inserted");
-
-    let retrieved_raw_doc = storage
-        .get_document(&doc.id)
-        .await
-        .expect("The stored document to be retrieved");
-
-    assert_eq!(raw_doc.id, retrieved_raw_doc.id);
-    assert_eq!(raw_doc.project_id, retrieved_raw_doc.project_id);
-    assert_eq!(raw_doc.owner_id, retrieved_raw_doc.owner_id);
-    assert_eq!(raw_doc.name, retrieved_raw_doc.name);
-    assert_eq!(raw_doc.doctype, retrieved_raw_doc.doctype);
-    assert_eq!(raw_doc.version, retrieved_raw_doc.version);
-    assert_eq!(raw_doc.body, retrieved_raw_doc.body);
-
-    let doc_retrieved: DigraphDocument = retrieved_raw_doc.into();
-
-    assert_eq!(doc.id, doc_retrieved.id);
-    assert_eq!(doc.project_id, doc_retrieved.project_id);
-    assert_eq!(doc.owner_id, doc_retrieved.owner_id);
-    assert_eq!(doc.name, doc_retrieved.name);
-    assert_eq!(doc.doctype, doc_retrieved.doctype);
-    assert_eq!(doc.version, doc_retrieved.version);
-    assert_eq!(doc.body, doc_retrieved.body);
-
-    Ok(())
+    common::engine_functions(storage().await).await
 }
 
 #[tokio::test]
 async fn test_sqlite_engine_cascading_deletes() -> std::io::Result<()> {
-    use conduit::doc::document::DigraphDocument;
-    use conduit::doc::project::Project;
-    use conduit::storage::engine::{Engine, EngineError};
-
-    let _ = env_logger::try_init();
-    let storage = Sqlite::setup(":memory:".into())
-        .await
-        .expect("The sqlite storage to be set up");
-
-    let _ = storage
-        .migrate()
-        .await
-        .expect("The sqlite storage to be migrated");
-
-    let project = Project::new(conduit::util::naming::empty_uuid());
-    let project_id = project.id;
+    common::cascading_deletes(storage().await).await
+}
 
-    let _ = storage
-        .store_project(project.clone())
-        .await
-        .expect("The project to be inserted");
+#[tokio::test]
+async fn test_sqlite_engine_undo_redo() -> std::io::Result<()> {
+    common::undo_redo(storage().await).await
+}
 
-    let doc = DigraphDocument::create(&project);
+#[tokio::test]
+async fn test_sqlite_engine_update_version_check() -> std::io::Result<()> {
+    common::update_version_check(storage().await).await
+}
 
-    let _ = storage
-        .store_document(doc.clone().into())
-        .await
-        .expect("The document to be inserted");
+#[tokio::test]
+async fn test_sqlite_engine_get_projects_sorted() -> std::io::Result<()> {
+    common::get_projects_sorted(storage().await).await
+}
 
-    let _ = storage
-        .delete_project(&project.id)
-        .await
-        .expect("Project to be deleted from database");
+#[tokio::test]
+async fn test_sqlite_engine_get_projects_paged() -> std::io::Result<()> {
+    common::get_projects_paged(storage().await).await
+}
 
-    assert_eq!(
-        storage.get_project(&project_id).await,
-        Err(EngineError::NotFound)
-    );
-    assert_eq!(
-        storage.get_document(&doc.id).await,
-        Err(EngineError::NotFound)
-    );
+#[tokio::test]
+async fn test_sqlite_engine_filters() -> std::io::Result<()> {
+    common::filters(storage().await).await
+}
 
-    Ok(())
+#[tokio::test]
+async fn test_sqlite_engine_changes_and_deletes() -> std::io::Result<()> {
+    common::changes_and_deletes(storage().await).await
 }
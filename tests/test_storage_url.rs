@@ -0,0 +1,125 @@
+extern crate conduit;
+use conduit::doc::project::Project;
+use conduit::storage::engine::{connect, Engine, EngineError, EngineVariant};
+use conduit::util::naming::empty_uuid;
+use tempdir::TempDir;
+
+/// Store a project through `storage` and read it back
+async fn round_trip(storage: Box<dyn Engine>) {
+    let project = Project::new(empty_uuid());
+    storage
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+    let retrieved = storage
+        .get_project(&project.id)
+        .await
+        .expect("The project to be retrieved");
+    assert_eq!(project.id, retrieved.id);
+}
+
+#[tokio::test]
+async fn test_connect_sqlite_in_memory() -> std::io::Result<()> {
+    let storage = EngineVariant::connect("sqlite::memory:")
+        .await
+        .expect("The sqlite storage to be set up");
+    assert!(matches!(storage, EngineVariant::Sqlite(_)));
+    assert!(storage.has_migrations());
+
+    round_trip(connect("sqlite::memory:").await.expect("To connect")).await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connect_sqlite_file() -> std::io::Result<()> {
+    let dir = TempDir::new("conduit").expect("To be able to create a temporary directory");
+    let url = format!("sqlite://{}", dir.path().join("test.db").display());
+
+    round_trip(connect(&url).await.expect("To connect")).await;
+    assert!(dir.path().join("test.db").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_open_existing() -> std::io::Result<()> {
+    let dir = TempDir::new("conduit").expect("To be able to create a temporary directory");
+    let path = dir.path().join("test.db");
+    let url = format!("sqlite://{}", path.display());
+    let root = dir.path().join("projects");
+
+    for url in &[url.clone(), format!("file://{}", root.display())] {
+        match EngineVariant::open(url, true).await {
+            Err(EngineError::Storage(message)) => {
+                assert_eq!(message, format!("Database {} does not exist", url))
+            }
+            other => panic!("Expected a missing database error, got {:?}", other),
+        }
+    }
+    assert!(!path.exists());
+    assert!(!root.exists());
+
+    let project = Project::new(empty_uuid());
+    connect(&url)
+        .await
+        .expect("To connect")
+        .store_project(project.clone())
+        .await
+        .expect("The project to be inserted");
+
+    let storage = EngineVariant::open(&url, false)
+        .await
+        .expect("To open")
+        .boxed();
+    storage
+        .get_project(&project.id)
+        .await
+        .expect("The project to be retrieved");
+    assert!(storage
+        .store_project(Project::new(empty_uuid()))
+        .await
+        .is_err());
+
+    let storage = EngineVariant::open(&url, true)
+        .await
+        .expect("To open")
+        .boxed();
+    round_trip(storage).await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connect_fs() -> std::io::Result<()> {
+    let dir = TempDir::new("conduit").expect("To be able to create a temporary directory");
+    let root = dir.path().join("projects");
+    let url = format!("file://{}", root.display());
+
+    let storage = EngineVariant::connect(&url).await.expect("To connect");
+    assert!(matches!(storage, EngineVariant::Fs(_)));
+    assert!(!storage.has_migrations());
+    assert!(root.is_dir());
+
+    round_trip(storage.boxed()).await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connect_memory() -> std::io::Result<()> {
+    let storage = EngineVariant::connect("memory:").await.expect("To connect");
+    assert!(matches!(storage, EngineVariant::Memory(_)));
+
+    round_trip(storage.boxed()).await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connect_unsupported_url() -> std::io::Result<()> {
+    for url in &["mysql://localhost/conduit", "test.db", ""] {
+        match EngineVariant::connect(url).await {
+            Err(EngineError::Storage(message)) => {
+                assert_eq!(message, format!("Unsupported database url {}", url))
+            }
+            other => panic!("Expected an unsupported url error, got {:?}", other),
+        }
+    }
+    Ok(())
+}